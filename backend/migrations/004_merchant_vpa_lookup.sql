@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS idx_merchants_upi_lower ON merchants(LOWER(upi_id));
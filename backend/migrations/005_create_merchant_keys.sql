@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS merchant_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    public_key TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchant_keys_merchant ON merchant_keys(merchant_id) WHERE revoked_at IS NULL;
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS merchant_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    transaction_ref VARCHAR(35) UNIQUE NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    note TEXT,
+    expires_at TIMESTAMP NOT NULL,
+    paid_at TIMESTAMP,
+    transaction_id UUID REFERENCES transactions(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchant_orders_merchant ON merchant_orders(merchant_id);
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS order_id UUID REFERENCES merchant_orders(id);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_order ON transactions(order_id);
@@ -0,0 +1,12 @@
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS payee_user_id UUID REFERENCES users(id);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'transactions_single_payee') THEN
+        ALTER TABLE transactions
+            ADD CONSTRAINT transactions_single_payee CHECK (merchant_id IS NULL OR payee_user_id IS NULL);
+    END IF;
+END$$;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_payee_user ON transactions(payee_user_id);
+CREATE INDEX IF NOT EXISTS idx_users_upi_lower ON users(LOWER(upi_id));
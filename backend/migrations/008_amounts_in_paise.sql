@@ -0,0 +1,11 @@
+ALTER TABLE users
+    ALTER COLUMN balance DROP DEFAULT,
+    ALTER COLUMN balance TYPE BIGINT USING ROUND(balance * 100)::BIGINT,
+    ALTER COLUMN balance SET DEFAULT 0,
+    ALTER COLUMN balance SET NOT NULL;
+
+ALTER TABLE transactions
+    ALTER COLUMN amount TYPE BIGINT USING ROUND(amount * 100)::BIGINT;
+
+ALTER TABLE merchant_orders
+    ALTER COLUMN amount TYPE BIGINT USING ROUND(amount * 100)::BIGINT;
@@ -0,0 +1,43 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'ledger_account_type') THEN
+        CREATE TYPE ledger_account_type AS ENUM ('user_wallet', 'merchant_payable', 'fee_revenue', 'funding');
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'ledger_direction') THEN
+        CREATE TYPE ledger_direction AS ENUM ('debit', 'credit');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS ledger_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    journal_id UUID NOT NULL,
+    transaction_id UUID REFERENCES transactions(id),
+    account_type ledger_account_type NOT NULL,
+    account_id UUID,
+    direction ledger_direction NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    description TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_account ON ledger_entries(account_type, account_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_journal ON ledger_entries(journal_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_transaction ON ledger_entries(transaction_id);
+
+-- Existing wallet balances and merchant takings predate the ledger; open them against the funding account.
+WITH openings AS (
+    SELECT gen_random_uuid() AS journal_id, 'user_wallet'::ledger_account_type AS account_type, id AS account_id, balance AS amount
+    FROM users
+    WHERE balance > 0
+    UNION ALL
+    SELECT gen_random_uuid(), 'merchant_payable'::ledger_account_type, merchant_id, SUM(amount)::BIGINT
+    FROM transactions
+    WHERE status = 'success' AND merchant_id IS NOT NULL
+    GROUP BY merchant_id
+)
+INSERT INTO ledger_entries (journal_id, account_type, account_id, direction, amount, description)
+SELECT journal_id, 'funding'::ledger_account_type, NULL::UUID, 'debit'::ledger_direction, amount, 'opening balance'
+FROM openings
+UNION ALL
+SELECT journal_id, account_type, account_id, 'credit'::ledger_direction, amount, 'opening balance'
+FROM openings;
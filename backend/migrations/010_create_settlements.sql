@@ -0,0 +1,34 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'settlement_schedule') THEN
+        CREATE TYPE settlement_schedule AS ENUM ('t0', 't1');
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'settlement_status') THEN
+        CREATE TYPE settlement_status AS ENUM ('pending', 'processing', 'paid', 'failed');
+    END IF;
+END$$;
+
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS settlement_schedule settlement_schedule NOT NULL DEFAULT 't1';
+
+CREATE TABLE IF NOT EXISTS settlement_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id),
+    schedule settlement_schedule NOT NULL,
+    window_start TIMESTAMP NOT NULL,
+    window_end TIMESTAMP NOT NULL,
+    amount BIGINT NOT NULL DEFAULT 0,
+    transaction_count INTEGER NOT NULL DEFAULT 0,
+    status settlement_status NOT NULL DEFAULT 'pending',
+    payout_reference VARCHAR(255),
+    failure_reason TEXT,
+    paid_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_batches_merchant ON settlement_batches(merchant_id);
+CREATE INDEX IF NOT EXISTS idx_settlement_batches_status ON settlement_batches(status);
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS settlement_batch_id UUID REFERENCES settlement_batches(id);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_unsettled ON transactions(merchant_id) WHERE settlement_batch_id IS NULL;
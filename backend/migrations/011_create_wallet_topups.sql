@@ -0,0 +1,23 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'topup_status') THEN
+        CREATE TYPE topup_status AS ENUM ('initiated', 'pending', 'success', 'failed');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS wallet_topups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    status topup_status NOT NULL DEFAULT 'initiated',
+    funding_source VARCHAR(50) NOT NULL,
+    instrument VARCHAR(255) NOT NULL,
+    idempotency_key VARCHAR(255) UNIQUE NOT NULL,
+    provider_reference VARCHAR(255),
+    failure_reason TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_wallet_topups_user ON wallet_topups(user_id);
+CREATE INDEX IF NOT EXISTS idx_wallet_topups_status ON wallet_topups(status);
@@ -0,0 +1,20 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'refund_initiator') THEN
+        CREATE TYPE refund_initiator AS ENUM ('merchant', 'admin');
+    END IF;
+END$$;
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS refunded_amount BIGINT NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS refunds (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    reason TEXT,
+    initiated_by refund_initiator NOT NULL,
+    refund_key VARCHAR(255) UNIQUE NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_refunds_transaction ON refunds(transaction_id);
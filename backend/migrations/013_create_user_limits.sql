@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS user_limits (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    per_transaction BIGINT CHECK (per_transaction > 0),
+    daily BIGINT CHECK (daily > 0),
+    monthly BIGINT CHECK (monthly > 0),
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
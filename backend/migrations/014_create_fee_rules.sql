@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS fee_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    category VARCHAR(50),
+    min_amount BIGINT NOT NULL DEFAULT 0 CHECK (min_amount >= 0),
+    max_amount BIGINT CHECK (max_amount >= min_amount),
+    percentage_bps INTEGER NOT NULL DEFAULT 0 CHECK (percentage_bps BETWEEN 0 AND 10000),
+    flat_fee BIGINT NOT NULL DEFAULT 0 CHECK (flat_fee >= 0),
+    min_fee BIGINT CHECK (min_fee >= 0),
+    max_fee BIGINT CHECK (max_fee >= 0),
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_fee_rules_category ON fee_rules(LOWER(category)) WHERE active;
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS fee_amount BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS net_amount BIGINT GENERATED ALWAYS AS (amount - fee_amount) STORED;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS refunded_fee BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,44 @@
+ALTER TYPE ledger_account_type ADD VALUE IF NOT EXISTS 'rewards_expense';
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'reward_cohort') THEN
+        CREATE TYPE reward_cohort AS ENUM ('all', 'new_user', 'first_in_category');
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'reward_status') THEN
+        CREATE TYPE reward_status AS ENUM ('credited', 'reversed');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS reward_campaigns (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    category VARCHAR(50),
+    cohort reward_cohort NOT NULL DEFAULT 'all',
+    min_amount BIGINT NOT NULL DEFAULT 0 CHECK (min_amount >= 0),
+    max_amount BIGINT CHECK (max_amount >= min_amount),
+    percentage_bps INTEGER NOT NULL DEFAULT 0 CHECK (percentage_bps BETWEEN 0 AND 10000),
+    flat_amount BIGINT NOT NULL DEFAULT 0 CHECK (flat_amount >= 0),
+    max_reward BIGINT CHECK (max_reward > 0),
+    per_user_limit INTEGER CHECK (per_user_limit > 0),
+    per_user_cap BIGINT CHECK (per_user_cap > 0),
+    starts_at TIMESTAMP NOT NULL,
+    ends_at TIMESTAMP NOT NULL CHECK (ends_at > starts_at),
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS rewards (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    campaign_id UUID NOT NULL REFERENCES reward_campaigns(id),
+    transaction_id UUID UNIQUE NOT NULL REFERENCES transactions(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    reversed_amount BIGINT NOT NULL DEFAULT 0 CHECK (reversed_amount BETWEEN 0 AND amount),
+    status reward_status NOT NULL DEFAULT 'credited',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_rewards_campaign_user ON rewards(campaign_id, user_id);
+CREATE INDEX IF NOT EXISTS idx_rewards_user ON rewards(user_id, created_at);
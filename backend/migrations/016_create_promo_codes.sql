@@ -0,0 +1,37 @@
+CREATE TABLE IF NOT EXISTS promo_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code VARCHAR(32) UNIQUE NOT NULL,
+    percentage_bps INTEGER NOT NULL DEFAULT 0 CHECK (percentage_bps BETWEEN 0 AND 10000),
+    flat_discount BIGINT NOT NULL DEFAULT 0 CHECK (flat_discount >= 0),
+    max_discount BIGINT CHECK (max_discount > 0),
+    min_amount BIGINT NOT NULL DEFAULT 0 CHECK (min_amount >= 0),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    usage_limit INTEGER CHECK (usage_limit > 0),
+    per_user_limit INTEGER CHECK (per_user_limit > 0),
+    redemption_count INTEGER NOT NULL DEFAULT 0 CHECK (usage_limit IS NULL OR redemption_count <= usage_limit),
+    expires_at TIMESTAMP NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS promo_redemptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    promo_code_id UUID NOT NULL REFERENCES promo_codes(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    transaction_id UUID UNIQUE NOT NULL REFERENCES transactions(id),
+    discount BIGINT NOT NULL CHECK (discount > 0),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_promo_redemptions_code_user ON promo_redemptions(promo_code_id, user_id);
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS promo_code_id UUID REFERENCES promo_codes(id);
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS discount_amount BIGINT NOT NULL DEFAULT 0;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'transactions_discount_within_amount') THEN
+        ALTER TABLE transactions
+            ADD CONSTRAINT transactions_discount_within_amount CHECK (discount_amount BETWEEN 0 AND amount);
+    END IF;
+END$$;
@@ -0,0 +1,26 @@
+ALTER TYPE ledger_account_type ADD VALUE IF NOT EXISTS 'split_escrow';
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'split_status') THEN
+        CREATE TYPE split_status AS ENUM ('open', 'completed', 'expired');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS split_bills (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    initiator_id UUID NOT NULL REFERENCES users(id),
+    merchant_id UUID NOT NULL REFERENCES merchants(id),
+    order_id UUID REFERENCES merchant_orders(id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    status split_status NOT NULL DEFAULT 'open',
+    expires_at TIMESTAMP NOT NULL,
+    completed_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_split_bills_open ON split_bills(expires_at) WHERE status = 'open';
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS split_id UUID REFERENCES split_bills(id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_split_user ON transactions(split_id, user_id) WHERE split_id IS NOT NULL;
@@ -0,0 +1,21 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'collect_status') THEN
+        CREATE TYPE collect_status AS ENUM ('pending', 'approved', 'declined', 'expired');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS collect_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    note TEXT,
+    status collect_status NOT NULL DEFAULT 'pending',
+    expires_at TIMESTAMP NOT NULL,
+    transaction_id UUID REFERENCES transactions(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_collect_requests_user_pending ON collect_requests(user_id, expires_at) WHERE status = 'pending';
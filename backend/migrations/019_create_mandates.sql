@@ -0,0 +1,53 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'mandate_frequency') THEN
+        CREATE TYPE mandate_frequency AS ENUM ('daily', 'weekly', 'monthly', 'yearly');
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'mandate_status') THEN
+        CREATE TYPE mandate_status AS ENUM ('pending', 'active', 'revoked', 'completed');
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'mandate_execution_status') THEN
+        CREATE TYPE mandate_execution_status AS ENUM ('notified', 'success', 'failed', 'cancelled');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS mandates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id),
+    merchant_id UUID NOT NULL REFERENCES merchants(id),
+    description TEXT,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    max_amount BIGINT NOT NULL,
+    frequency mandate_frequency NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE,
+    status mandate_status NOT NULL DEFAULT 'pending',
+    next_cycle INTEGER NOT NULL DEFAULT 0,
+    next_debit_date DATE NOT NULL,
+    approved_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT mandates_amount_within_max CHECK (amount <= max_amount),
+    CONSTRAINT mandates_end_after_start CHECK (end_date IS NULL OR end_date >= start_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_mandates_user ON mandates(user_id);
+CREATE INDEX IF NOT EXISTS idx_mandates_due ON mandates(next_debit_date) WHERE status = 'active';
+
+-- One row per mandate cycle: created by the pre-debit notification and settled by the debit.
+CREATE TABLE IF NOT EXISTS mandate_executions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    mandate_id UUID NOT NULL REFERENCES mandates(id),
+    cycle INTEGER NOT NULL,
+    due_date DATE NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    status mandate_execution_status NOT NULL DEFAULT 'notified',
+    transaction_id UUID REFERENCES transactions(id),
+    error_message TEXT,
+    notified_at TIMESTAMP NOT NULL,
+    executed_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (mandate_id, cycle)
+);
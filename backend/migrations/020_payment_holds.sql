@@ -0,0 +1,12 @@
+ALTER TYPE ledger_account_type ADD VALUE IF NOT EXISTS 'payment_hold';
+ALTER TYPE mandate_execution_status ADD VALUE IF NOT EXISTS 'pending';
+
+-- Every PSP callback we have applied, keyed by the PSP's event id so a replayed callback is a no-op.
+CREATE TABLE IF NOT EXISTS psp_callbacks (
+    event_id VARCHAR(100) PRIMARY KEY,
+    transaction_id UUID NOT NULL REFERENCES transactions(id),
+    status VARCHAR(20) NOT NULL,
+    received_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_psp_callbacks_transaction ON psp_callbacks(transaction_id);
@@ -0,0 +1,20 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'poll_outcome') THEN
+        CREATE TYPE poll_outcome AS ENUM ('pending', 'success', 'failed', 'reversed', 'error');
+    END IF;
+END$$;
+
+-- One row per status poll the reconciliation worker makes for a payment left pending.
+CREATE TABLE IF NOT EXISTS psp_status_polls (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id),
+    attempt INTEGER NOT NULL,
+    psp_status VARCHAR(20),
+    outcome poll_outcome NOT NULL,
+    error_message TEXT,
+    polled_at TIMESTAMP NOT NULL,
+    UNIQUE (transaction_id, attempt)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_pending ON transactions(updated_at) WHERE status = 'pending';
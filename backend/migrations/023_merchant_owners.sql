@@ -0,0 +1,6 @@
+-- The user who operates a merchant. Merchants are onboarded outside the app, so this is set there as well.
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS owner_id UUID REFERENCES users(id);
+
+CREATE INDEX IF NOT EXISTS idx_merchants_owner ON merchants(owner_id);
+
+ALTER TABLE refunds ADD COLUMN IF NOT EXISTS requested_by UUID REFERENCES users(id);
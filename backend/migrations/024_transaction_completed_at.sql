@@ -0,0 +1,7 @@
+-- When a payment succeeded. Settlement windows are keyed on this rather than updated_at, which later refunds move.
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS completed_at TIMESTAMP;
+
+-- Older rows only have their last update to go by.
+UPDATE transactions SET completed_at = updated_at WHERE completed_at IS NULL AND status IN ('success', 'refunded');
+
+CREATE INDEX IF NOT EXISTS idx_transactions_completed ON transactions(merchant_id, completed_at) WHERE settlement_batch_id IS NULL;
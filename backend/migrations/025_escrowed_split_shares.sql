@@ -0,0 +1,2 @@
+-- Split shares the PSP has debited wait in the split's escrow under their own status rather than as pending payments.
+ALTER TYPE transaction_status ADD VALUE IF NOT EXISTS 'escrowed';
@@ -0,0 +1,198 @@
+// Stand-alone PSP switch for local runs and docker-compose. It speaks the real switch's signed protocol:
+// payer VPAs starting with "decline" are declined and ones starting with "pending" are left pending. When
+// MOCK_PSP_CALLBACK_URL is set, pending payments are confirmed with a signed callback after
+// MOCK_PSP_SETTLE_AFTER_SECS; otherwise they stay pending until reconciliation reverses them.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use chrono::Utc;
+use qr_payment_backend::utils::upi_client::{
+    sign, verify, PayRequest, PspPayment, PspReversal, PspStatus, KEY_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+#[derive(Clone)]
+struct Switch {
+    key_id: String,
+    secret: String,
+    callback_url: Option<String>,
+    settle_after: Duration,
+    payments: Arc<Mutex<HashMap<Uuid, PspPayment>>>,
+    http: reqwest::Client,
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    dotenv::dotenv().ok();
+    env_logger::init();
+
+    let switch = Switch {
+        key_id: std::env::var("PSP_KEY_ID").unwrap_or_else(|_| "qrpay".to_string()),
+        secret: std::env::var("PSP_SECRET").expect("PSP_SECRET must be set"),
+        callback_url: std::env::var("MOCK_PSP_CALLBACK_URL").ok(),
+        settle_after: Duration::from_secs(
+            std::env::var("MOCK_PSP_SETTLE_AFTER_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+        ),
+        payments: Arc::default(),
+        http: reqwest::Client::new(),
+    };
+    let port: u16 = std::env::var("MOCK_PSP_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(9090);
+
+    log::info!("mock psp listening on 0.0.0.0:{}", port);
+    HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(switch.clone()))
+            .default_service(web::to(handle))
+    })
+    .bind(("0.0.0.0", port))?
+    .run()
+    .await
+}
+
+async fn handle(req: HttpRequest, switch: web::Data<Switch>, body: web::Bytes) -> HttpResponse {
+    let body = String::from_utf8_lossy(&body);
+    if !switch.signed(&req, &body) {
+        return error(401, "invalid_signature", "request signature does not match");
+    }
+
+    let segments: Vec<&str> = req.path().trim_start_matches('/').split('/').collect();
+    match (req.method().as_str(), segments.as_slice()) {
+        ("POST", ["v1", "payments"]) => match serde_json::from_str::<PayRequest>(&body) {
+            Ok(pay) => HttpResponse::Ok().json(switch.pay(pay)),
+            Err(_) => error(400, "invalid_request", "malformed payment request"),
+        },
+        ("GET", ["v1", "payments", reference]) => match switch.find(reference) {
+            Some(payment) => HttpResponse::Ok().json(payment),
+            None => error(404, "not_found", "unknown payment reference"),
+        },
+        ("POST", ["v1", "payments", reference, "reversals"]) => match switch.find(reference) {
+            Some(payment) if payment.status != PspStatus::Failed => {
+                if payment.status == PspStatus::Pending {
+                    switch.settle(payment.reference, PspStatus::Failed, Some("reversed"));
+                }
+                HttpResponse::Ok().json(PspReversal {
+                    reversal_id: format!("REV{}", payment.reference.simple()).to_uppercase(),
+                    status: PspStatus::Success,
+                })
+            }
+            Some(_) => error(422, "not_reversible", "failed payments cannot be reversed"),
+            None => error(404, "not_found", "unknown payment reference"),
+        },
+        _ => error(404, "not_found", "no such endpoint"),
+    }
+}
+
+impl Switch {
+    fn signed(&self, req: &HttpRequest, body: &str) -> bool {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        let (Some(_), Some(timestamp), Some(signature)) =
+            (header(KEY_ID_HEADER), header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER))
+        else {
+            return false;
+        };
+        let Ok(timestamp) = timestamp.parse::<i64>() else {
+            return false;
+        };
+        verify(&self.secret, timestamp, req.method().as_str(), req.path(), body, signature)
+    }
+
+    // Payments are keyed by reference, so a repeated call returns what the first one did.
+    fn pay(&self, pay: PayRequest) -> PspPayment {
+        let mut payments = self.payments.lock().unwrap();
+        if let Some(existing) = payments.get(&pay.reference) {
+            return existing.clone();
+        }
+
+        let payment = if pay.payer_vpa.starts_with("decline") {
+            PspPayment {
+                reference: pay.reference,
+                upi_txn_id: None,
+                status: PspStatus::Failed,
+                reason: Some("declined by remitter bank".to_string()),
+            }
+        } else {
+            PspPayment {
+                reference: pay.reference,
+                upi_txn_id: Some(format!("PSP{}", pay.reference.simple()).to_uppercase()),
+                status: if pay.payer_vpa.starts_with("pending") {
+                    PspStatus::Pending
+                } else {
+                    PspStatus::Success
+                },
+                reason: None,
+            }
+        };
+        payments.insert(pay.reference, payment.clone());
+
+        if payment.status == PspStatus::Pending && self.callback_url.is_some() {
+            let switch = self.clone();
+            actix_web::rt::spawn(async move {
+                actix_web::rt::time::sleep(switch.settle_after).await;
+                switch.confirm(pay.reference).await;
+            });
+        }
+        payment
+    }
+
+    fn find(&self, reference: &str) -> Option<PspPayment> {
+        let reference = Uuid::parse_str(reference).ok()?;
+        self.payments.lock().unwrap().get(&reference).cloned()
+    }
+
+    fn settle(&self, reference: Uuid, status: PspStatus, reason: Option<&str>) {
+        if let Some(payment) = self.payments.lock().unwrap().get_mut(&reference) {
+            payment.status = status;
+            payment.reason = reason.map(str::to_string);
+        }
+    }
+
+    // Marks a still-pending payment successful and tells the backend through a signed callback.
+    async fn confirm(&self, reference: Uuid) {
+        let Some(url) = &self.callback_url else {
+            return;
+        };
+        match self.find(&reference.to_string()) {
+            Some(payment) if payment.status == PspStatus::Pending => {}
+            _ => return,
+        }
+        self.settle(reference, PspStatus::Success, None);
+        let Some(payment) = self.find(&reference.to_string()) else {
+            return;
+        };
+
+        let mut callback = serde_json::to_value(&payment).expect("payment serializes");
+        callback["event_id"] = json!(format!("evt-{}", Uuid::new_v4()));
+        let body = callback.to_string();
+        let path = reqwest::Url::parse(url).map(|u| u.path().to_string()).unwrap_or_default();
+        let timestamp = Utc::now().timestamp();
+
+        let sent = self
+            .http
+            .post(url)
+            .header(KEY_ID_HEADER, &self.key_id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&self.secret, timestamp, "POST", &path, &body))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await;
+        match sent {
+            Ok(resp) => log::info!("callback for {} answered {}", reference, resp.status()),
+            Err(e) => log::warn!("callback for {} failed: {}", reference, e),
+        }
+    }
+}
+
+fn error(status: u16, code: &str, message: &str) -> HttpResponse {
+    let status = actix_web::http::StatusCode::from_u16(status).expect("valid status code");
+    HttpResponse::build(status).json(json!({ "code": code, "message": message }))
+}
@@ -1,13 +1,34 @@
 use serde::Deserialize;
 
+use crate::models::limits::SpendingLimits;
+use crate::models::money::Money;
+use crate::models::reconciliation::ReconciliationConfig;
+use crate::utils::circuit_breaker::CircuitBreakerConfig;
+use crate::utils::funding_source::FundingProvider;
+use crate::utils::upi_client::PspConfig;
+
+// Anything other than an explicit development environment is treated as production.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppEnv {
+    Development,
+    Production,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
+    pub app_env: AppEnv,
     pub database_url: String,
     pub redis_url: String,
     pub jwt_secret: String,
     pub server_host: String,
     pub server_port: u16,
     pub jwt_ttl_seconds: i64,
+    pub payment_session_ttl_seconds: i64,
+    pub spending_limits: SpendingLimits,
+    pub psp: PspConfig,
+    pub reconciliation: ReconciliationConfig,
+    pub funding_provider: Option<FundingProvider>,
 }
 
 impl Config {
@@ -19,7 +40,33 @@ impl Config {
             .parse()
             .unwrap_or(86400);
 
+        let payment_session_ttl_seconds = std::env::var("PAYMENT_SESSION_TTL_SECONDS")
+            .unwrap_or_else(|_| "900".to_string())
+            .parse()
+            .unwrap_or(900);
+
+        let app_env = match std::env::var("APP_ENV").as_deref() {
+            Ok("development") | Ok("dev") => AppEnv::Development,
+            _ => AppEnv::Production,
+        };
+        let funding_provider = match std::env::var("FUNDING_SOURCE").as_deref() {
+            Ok("simulated_bank") | Err(_) => FundingProvider::SimulatedBank,
+            Ok(other) => {
+                return Err(config::ConfigError::Message(format!("unknown FUNDING_SOURCE {}", other)));
+            }
+        };
+        // The simulated bank approves top-ups out of thin air, so it must never back real wallets. Without a real
+        // provider the app still runs; only wallet top-ups are refused.
+        let funding_provider = match funding_provider {
+            FundingProvider::SimulatedBank if app_env != AppEnv::Development => {
+                log::warn!("FUNDING_SOURCE=simulated_bank is ignored outside development; wallet top-ups are disabled");
+                None
+            }
+            provider => Some(provider),
+        };
+
         Ok(Config {
+            app_env,
             database_url: std::env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
             redis_url: std::env::var("REDIS_URL").expect("REDIS_URL must be set"),
             jwt_secret: std::env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
@@ -29,6 +76,53 @@ impl Config {
                 .parse()
                 .expect("SERVER_PORT must be a valid number"),
             jwt_ttl_seconds,
+            payment_session_ttl_seconds,
+            spending_limits: SpendingLimits {
+                per_transaction: limit_from_env("PER_TRANSACTION_LIMIT", Money::from_rupees(100_000)),
+                daily: limit_from_env("DAILY_SPEND_LIMIT", Money::from_rupees(200_000)),
+                monthly: limit_from_env("MONTHLY_SPEND_LIMIT", Money::from_rupees(1_000_000)),
+            },
+            psp: PspConfig {
+                base_url: std::env::var("PSP_BASE_URL").expect("PSP_BASE_URL must be set"),
+                key_id: std::env::var("PSP_KEY_ID").unwrap_or_else(|_| "qrpay".to_string()),
+                secret: std::env::var("PSP_SECRET").expect("PSP_SECRET must be set"),
+                timeout_ms: std::env::var("PSP_TIMEOUT_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(5000),
+                breaker: breaker_from_env(),
+            },
+            reconciliation: ReconciliationConfig {
+                poll_after_secs: env_or("RECONCILE_POLL_AFTER_SECS", 120),
+                backoff_secs: env_or("RECONCILE_BACKOFF_SECS", 60),
+                max_backoff_secs: env_or("RECONCILE_MAX_BACKOFF_SECS", 30 * 60),
+                max_age_secs: env_or("RECONCILE_MAX_AGE_SECS", 24 * 60 * 60),
+            },
+            funding_provider,
         })
     }
 }
+
+fn limit_from_env(key: &str, default: Money) -> Money {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<Money>().ok())
+        .filter(|limit| limit.is_positive())
+        .unwrap_or(default)
+}
+
+fn breaker_from_env() -> CircuitBreakerConfig {
+    let defaults = CircuitBreakerConfig::default();
+    CircuitBreakerConfig {
+        consecutive_failures: env_or("PSP_BREAKER_CONSECUTIVE_FAILURES", defaults.consecutive_failures),
+        failure_rate: env_or("PSP_BREAKER_FAILURE_RATE", defaults.failure_rate),
+        window_size: env_or("PSP_BREAKER_WINDOW_SIZE", defaults.window_size),
+        minimum_calls: env_or("PSP_BREAKER_MINIMUM_CALLS", defaults.minimum_calls),
+        cool_down_ms: env_or("PSP_BREAKER_COOL_DOWN_MS", defaults.cool_down_ms),
+        half_open_max_calls: env_or("PSP_BREAKER_HALF_OPEN_CALLS", defaults.half_open_max_calls),
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
@@ -0,0 +1,86 @@
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::handlers::{require_owner, AppState};
+use crate::middleware::jwt_auth::AuthenticatedUser;
+use crate::models::collect::{ApproveCollectRequest, CreateCollectRequest};
+use crate::services;
+
+#[post("/merchant/{merchant_id}/collect-requests")]
+pub async fn create_collect_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateCollectRequest>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let resp = services::collect::create_request(&state.db, &state.redis, merchant_id, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(resp))
+}
+
+#[get("/merchant/{merchant_id}/collect-requests/{request_id}")]
+pub async fn get_collect_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (merchant_id, request_id) = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let resp = services::collect::get_request(&state.db, merchant_id, request_id).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[get("/collect-requests")]
+pub async fn pending_collect_requests(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::collect::pending_for_user(&state.db, user).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[post("/collect-requests/{request_id}/approve")]
+pub async fn approve_collect_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<ApproveCollectRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::collect::approve(
+        &state.db,
+        &state.upi,
+        &state.config.spending_limits,
+        user,
+        path.into_inner(),
+        payload.into_inner(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[post("/collect-requests/{request_id}/decline")]
+pub async fn decline_collect_request(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::collect::decline(&state.db, user, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
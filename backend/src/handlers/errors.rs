@@ -1,6 +1,9 @@
 use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use serde::Serialize;
 
+use crate::models::money::Money;
+use crate::utils::upi_client::UpiError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("{0}")]
@@ -8,16 +11,36 @@ pub enum AppError {
     #[error("{0}")]
     Unauthorized(String),
     #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
     NotFound(String),
     #[error("{0}")]
     Conflict(String),
     #[error("{0}")]
     Internal(String),
+    #[error("{0}")]
+    SessionExpired(String),
+    #[error("{0}")]
+    PaymentDeclined(String),
+    #[error("{0}")]
+    PaymentNetwork(String),
+    #[error("{0}")]
+    PaymentNetworkUnavailable(String),
+    #[error("{message}")]
+    LimitExceeded {
+        code: &'static str,
+        message: String,
+        remaining: Money,
+    },
 }
 
 #[derive(Debug, Serialize)]
 struct ErrorBody {
     error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining_limit: Option<Money>,
 }
 
 impl AppError {
@@ -29,6 +52,10 @@ impl AppError {
         AppError::Unauthorized(msg.into())
     }
 
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        AppError::Forbidden(msg.into())
+    }
+
     pub fn internal(msg: impl Into<String>) -> Self {
         AppError::Internal(msg.into())
     }
@@ -46,6 +73,14 @@ impl AppError {
             _ => AppError::Internal("database error".to_string()),
         }
     }
+
+    pub fn from_upi(e: UpiError) -> Self {
+        match e {
+            UpiError::Rejected { message, .. } => AppError::PaymentDeclined(message),
+            UpiError::CircuitOpen => AppError::PaymentNetworkUnavailable(UpiError::CircuitOpen.to_string()),
+            other => AppError::PaymentNetwork(other.to_string()),
+        }
+    }
 }
 
 impl ResponseError for AppError {
@@ -53,15 +88,31 @@ impl ResponseError for AppError {
         match self {
             AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
             AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
             AppError::Conflict(_) => StatusCode::CONFLICT,
             AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::SessionExpired(_) => StatusCode::GONE,
+            AppError::PaymentDeclined(_) => StatusCode::PAYMENT_REQUIRED,
+            AppError::PaymentNetwork(_) => StatusCode::BAD_GATEWAY,
+            AppError::PaymentNetworkUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::LimitExceeded { .. } => StatusCode::UNPROCESSABLE_ENTITY,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
+        let (code, remaining_limit) = match self {
+            AppError::LimitExceeded { code, remaining, .. } => (Some(*code), Some(*remaining)),
+            AppError::SessionExpired(_) => (Some("payment_session_expired"), None),
+            AppError::PaymentDeclined(_) => (Some("payment_declined"), None),
+            AppError::PaymentNetwork(_) => (Some("payment_network_error"), None),
+            AppError::PaymentNetworkUnavailable(_) => (Some("payment_network_unavailable"), None),
+            _ => (None, None),
+        };
         HttpResponse::build(self.status_code()).json(ErrorBody {
             error: self.to_string(),
+            code,
+            remaining_limit,
         })
     }
 }
@@ -0,0 +1,36 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::handlers::{require_role, AppState};
+use crate::models::fee::CreateFeeRuleRequest;
+use crate::models::user::UserRole;
+use crate::services;
+
+#[post("/fee-rules")]
+pub async fn create_fee_rule(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Json<CreateFeeRuleRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_role(&state, &req, &[UserRole::Admin]).await?;
+    let rule = services::fee::create_rule(&state.db, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(rule))
+}
+
+#[get("/fee-rules")]
+pub async fn list_fee_rules(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let rules = services::fee::list_rules(&state.db).await?;
+    Ok(HttpResponse::Ok().json(rules))
+}
+
+#[delete("/fee-rules/{rule_id}")]
+pub async fn deactivate_fee_rule(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    require_role(&state, &req, &[UserRole::Admin]).await?;
+    services::fee::deactivate_rule(&state.db, path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
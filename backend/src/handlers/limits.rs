@@ -0,0 +1,56 @@
+use actix_web::{get, put, web, HttpMessage, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::handlers::{require_role, AppState};
+use crate::middleware::jwt_auth::AuthenticatedUser;
+use crate::models::limits::UserLimitsRequest;
+use crate::models::user::UserRole;
+use crate::services;
+
+#[get("/limits")]
+pub async fn get_limits(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::limits::get_limits(&state.db, &state.config.spending_limits, user).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[put("/limits")]
+pub async fn lower_limits(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    payload: web::Json<UserLimitsRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp =
+        services::limits::lower_limits(&state.db, &state.config.spending_limits, user, payload.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[put("/users/{user_id}/limits")]
+pub async fn set_user_limits(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<UserLimitsRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_role(&state, &req, &[UserRole::Admin]).await?;
+    let resp = services::limits::set_user_limits(
+        &state.db,
+        &state.config.spending_limits,
+        path.into_inner(),
+        payload.into_inner(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
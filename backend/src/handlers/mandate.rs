@@ -0,0 +1,95 @@
+use actix_web::{get, post, put, web, HttpMessage, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::handlers::{require_owner, AppState};
+use crate::middleware::jwt_auth::AuthenticatedUser;
+use crate::models::mandate::{ApproveMandateRequest, CreateMandateRequest, UpdateMandateRequest};
+use crate::services;
+
+#[post("/merchant/{merchant_id}/mandates")]
+pub async fn create_mandate(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateMandateRequest>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let resp = services::mandate::create_mandate(&state.db, &state.redis, merchant_id, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(resp))
+}
+
+#[put("/merchant/{merchant_id}/mandates/{mandate_id}")]
+pub async fn update_mandate(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<UpdateMandateRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (merchant_id, mandate_id) = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let resp = services::mandate::update_amount(&state.db, merchant_id, mandate_id, payload.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[get("/mandates")]
+pub async fn my_mandates(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::mandate::mandates_for_user(&state.db, user).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[post("/mandates/{mandate_id}/approve")]
+pub async fn approve_mandate(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<ApproveMandateRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::mandate::approve(&state.db, user, path.into_inner(), payload.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[post("/mandates/{mandate_id}/revoke")]
+pub async fn revoke_mandate(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::mandate::revoke(&state.db, user, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[get("/mandates/{mandate_id}/history")]
+pub async fn mandate_history(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::mandate::history(&state.db, user, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
@@ -1,9 +1,17 @@
-use actix_web::{post, web, HttpResponse};
+use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use uuid::Uuid;
 
 use crate::handlers::errors::AppError;
-use crate::handlers::AppState;
-use crate::models::merchant::QRScanRequest;
+use crate::handlers::{require_owner, require_role, AppState};
+use crate::models::ledger::{LedgerAccount, MerchantBalanceResponse};
+use crate::models::merchant::{
+    DecodedQrCode, MerchantQrQuery, QRScanRequest, QrImageDecodeResponse, RegisterMerchantKeyRequest,
+};
+use crate::models::order::CreateOrderRequest;
+use crate::models::user::UserRole;
 use crate::services;
+use crate::utils::qr_decoder;
+use crate::utils::qr_render::{self, QrRenderError};
 
 #[post("/merchant/resolve")]
 pub async fn resolve_merchant(
@@ -14,3 +22,142 @@ pub async fn resolve_merchant(
     Ok(HttpResponse::Ok().json(merchant))
 }
 
+#[get("/merchant/{merchant_id}/qr")]
+pub async fn merchant_qr(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    query: web::Query<MerchantQrQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let payload = services::merchant::merchant_qr_payload(&state.db, &state.redis, path.into_inner(), &query).await?;
+
+    let image = qr_render::render(
+        &payload,
+        query.format,
+        query.size.unwrap_or(qr_render::DEFAULT_SIZE),
+        query.ec,
+    )
+    .map_err(|e| match e {
+        QrRenderError::Encode => AppError::internal(e.to_string()),
+        _ => AppError::bad_request(e.to_string()),
+    })?;
+
+    Ok(HttpResponse::Ok().content_type(query.format.content_type()).body(image))
+}
+
+#[get("/merchant/{merchant_id}/balance")]
+pub async fn merchant_balance(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = path.into_inner();
+    // Finance reads payables across merchants; everyone else only sees their own.
+    if let Err(e) = require_owner(&state, &req, merchant_id).await {
+        if !matches!(e, AppError::Forbidden(_)) {
+            return Err(e);
+        }
+        require_role(&state, &req, &[UserRole::Finance]).await?;
+    }
+    let merchant = services::merchant::get_merchant_by_id(&state.db, &state.redis, merchant_id).await?;
+    let payable = services::ledger::balance(&state.db, LedgerAccount::merchant_payable(merchant.id)).await?;
+    Ok(HttpResponse::Ok().json(MerchantBalanceResponse {
+        merchant_id: merchant.id,
+        payable,
+    }))
+}
+
+#[post("/merchant/{merchant_id}/keys")]
+pub async fn register_merchant_key(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<RegisterMerchantKeyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let key = services::merchant::register_key(&state.db, merchant_id, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(key))
+}
+
+#[get("/merchant/{merchant_id}/keys")]
+pub async fn list_merchant_keys(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let keys = services::merchant::list_keys(&state.db, merchant_id).await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[delete("/merchant/{merchant_id}/keys/{key_id}")]
+pub async fn revoke_merchant_key(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (merchant_id, key_id) = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let key = services::merchant::revoke_key(&state.db, merchant_id, key_id).await?;
+    Ok(HttpResponse::Ok().json(key))
+}
+
+#[post("/merchant/{merchant_id}/orders")]
+pub async fn create_order(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateOrderRequest>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let order = services::order::create_order(&state.db, &state.redis, merchant_id, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(order))
+}
+
+#[get("/merchant/{merchant_id}/orders/{order_id}")]
+pub async fn get_order(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (merchant_id, order_id) = path.into_inner();
+    require_owner(&state, &req, merchant_id).await?;
+    let order = services::order::get_order(&state.db, &state.redis, merchant_id, order_id).await?;
+    Ok(HttpResponse::Ok().json(order))
+}
+
+#[post("/qr/decode")]
+pub async fn decode_qr_image(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse, AppError> {
+    if !matches!(req.content_type(), "image/png" | "image/jpeg") {
+        return Err(AppError::bad_request("expected an image/png or image/jpeg body"));
+    }
+    if body.is_empty() {
+        return Err(AppError::bad_request("empty image"));
+    }
+
+    let payloads = web::block(move || qr_decoder::decode_image(&body))
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?
+        .map_err(|e| AppError::bad_request(e.to_string()))?;
+
+    let mut codes = Vec::with_capacity(payloads.len());
+    for payload in payloads {
+        let code = match services::merchant::get_merchant_by_qr(&state.db, &state.redis, &payload).await {
+            Ok(merchant) => DecodedQrCode { payload, merchant: Some(merchant), error: None },
+            Err(e @ (AppError::BadRequest(_) | AppError::NotFound(_))) => {
+                DecodedQrCode { payload, merchant: None, error: Some(e.to_string()) }
+            }
+            Err(e) => return Err(e),
+        };
+        codes.push(code);
+    }
+
+    Ok(HttpResponse::Ok().json(QrImageDecodeResponse { codes }))
+}
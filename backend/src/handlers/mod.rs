@@ -1,22 +1,71 @@
 pub mod auth;
+pub mod collect;
 pub mod errors;
+pub mod fee;
+pub mod limits;
+pub mod mandate;
 pub mod merchant;
 pub mod payment;
+pub mod promo;
+pub mod psp;
+pub mod reward;
+pub mod settlement;
+pub mod split;
+pub mod wallet;
 
-use actix_web::{get, HttpResponse, Responder};
+use std::sync::Arc;
+
+use actix_web::{get, web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use sqlx::PgPool;
+use uuid::Uuid;
 
 use crate::cache::redis_client::RedisClient;
 use crate::config::Config;
+use crate::handlers::errors::AppError;
+use crate::middleware::jwt_auth::AuthenticatedUser;
+use crate::models::user::UserRole;
+use crate::services;
+use crate::utils::funding_source::FundingSource;
+use crate::utils::upi_client::UpiClient;
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: Config,
     pub db: PgPool,
     pub redis: RedisClient,
+    // None when no real funding source is configured; wallet top-ups are refused then.
+    pub funding: Option<Arc<dyn FundingSource>>,
+    pub upi: UpiClient,
+}
+
+// Resolves the caller and checks they hold one of `allowed` (admins always pass), returning their id.
+pub(crate) async fn require_role(state: &AppState, req: &HttpRequest, allowed: &[UserRole]) -> Result<Uuid, AppError> {
+    let user_id = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+    services::auth::require_role(&state.db, user_id, allowed).await?;
+    Ok(user_id)
+}
+
+// Resolves the caller and checks they own the merchant (admins always pass), returning their id.
+pub(crate) async fn require_owner(state: &AppState, req: &HttpRequest, merchant_id: Uuid) -> Result<Uuid, AppError> {
+    let user_id = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+    services::merchant::require_owner(&state.db, merchant_id, user_id).await?;
+    Ok(user_id)
 }
 
 #[get("/health")]
 pub async fn health() -> impl Responder {
     HttpResponse::Ok().json(serde_json::json!({"status":"ok"}))
 }
+
+#[get("/metrics/circuit-breakers")]
+pub async fn circuit_breakers(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(state.upi.circuit_breakers())
+}
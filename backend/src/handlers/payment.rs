@@ -1,9 +1,11 @@
 use actix_web::{post, web, HttpMessage, HttpRequest, HttpResponse};
+use uuid::Uuid;
 
 use crate::handlers::errors::AppError;
 use crate::handlers::AppState;
 use crate::middleware::jwt_auth::AuthenticatedUser;
 use crate::models::payment::{PaymentExecuteRequest, PaymentInitRequest};
+use crate::models::refund::RefundRequest;
 use crate::services;
 
 #[post("/payment/initiate")]
@@ -34,6 +36,32 @@ pub async fn execute_payment(
         .ok_or_else(|| AppError::unauthorized("unauthorized"))?
         .user_id;
 
-    let resp = services::payment::execute_payment(&state.db, &state.redis, user, payload.into_inner()).await?;
+    let resp = services::payment::execute_payment(
+        &state.db,
+        &state.redis,
+        &state.upi,
+        &state.config.spending_limits,
+        state.config.payment_session_ttl_seconds,
+        user,
+        payload.into_inner(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[post("/payment/{transaction_id}/refund")]
+pub async fn refund_payment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<RefundRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::refund::refund_payment(&state.db, path.into_inner(), user, payload.into_inner()).await?;
     Ok(HttpResponse::Ok().json(resp))
 }
@@ -0,0 +1,24 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+
+use crate::handlers::errors::AppError;
+use crate::handlers::{require_role, AppState};
+use crate::models::promo::CreatePromoCodeRequest;
+use crate::models::user::UserRole;
+use crate::services;
+
+#[post("/promo-codes")]
+pub async fn create_promo_code(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Json<CreatePromoCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_role(&state, &req, &[UserRole::Admin]).await?;
+    let promo = services::promo::create_code(&state.db, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(promo))
+}
+
+#[get("/promo-codes")]
+pub async fn list_promo_codes(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let promos = services::promo::list_codes(&state.db).await?;
+    Ok(HttpResponse::Ok().json(promos))
+}
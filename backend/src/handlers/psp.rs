@@ -0,0 +1,23 @@
+use actix_web::{post, web, HttpRequest, HttpResponse};
+
+use crate::handlers::errors::AppError;
+use crate::handlers::AppState;
+use crate::services;
+use crate::utils::upi_client::{SIGNATURE_HEADER, TIMESTAMP_HEADER};
+
+#[post("/psp/callbacks")]
+pub async fn psp_callback(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Bytes,
+) -> Result<HttpResponse, AppError> {
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+    let timestamp = header(TIMESTAMP_HEADER)
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| AppError::unauthorized("missing callback timestamp"))?;
+    let signature = header(SIGNATURE_HEADER).ok_or_else(|| AppError::unauthorized("missing callback signature"))?;
+
+    let resp =
+        services::payment::handle_callback(&state.db, &state.config.psp, req.path(), timestamp, signature, &body).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
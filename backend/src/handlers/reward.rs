@@ -0,0 +1,37 @@
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
+
+use crate::handlers::errors::AppError;
+use crate::handlers::{require_role, AppState};
+use crate::middleware::jwt_auth::AuthenticatedUser;
+use crate::models::reward::CreateRewardCampaignRequest;
+use crate::models::user::UserRole;
+use crate::services;
+
+#[post("/reward-campaigns")]
+pub async fn create_campaign(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Json<CreateRewardCampaignRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_role(&state, &req, &[UserRole::Admin]).await?;
+    let campaign = services::reward::create_campaign(&state.db, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(campaign))
+}
+
+#[get("/reward-campaigns")]
+pub async fn list_campaigns(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let campaigns = services::reward::list_campaigns(&state.db).await?;
+    Ok(HttpResponse::Ok().json(campaigns))
+}
+
+#[get("/rewards")]
+pub async fn my_rewards(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let rewards = services::reward::rewards_for_user(&state.db, user).await?;
+    Ok(HttpResponse::Ok().json(rewards))
+}
@@ -0,0 +1,69 @@
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::handlers::{require_role, AppState};
+use crate::models::settlement::{
+    SettlementReportQuery, SettlementRunResponse, SettlementScheduleRequest, SettlementStatusRequest,
+};
+use crate::models::user::UserRole;
+use crate::services;
+
+// Settlement moves merchant money, so every endpoint here is limited to finance staff and admins.
+async fn require_finance(state: &AppState, req: &HttpRequest) -> Result<(), AppError> {
+    require_role(state, req, &[UserRole::Finance]).await.map(|_| ())
+}
+
+#[post("/settlements/run")]
+pub async fn run_settlement(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    require_finance(&state, &req).await?;
+    let batches = services::settlement::run_settlement(&state.db, None).await?;
+    Ok(HttpResponse::Ok().json(SettlementRunResponse { batches }))
+}
+
+#[get("/settlements/report")]
+pub async fn settlement_report(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<SettlementReportQuery>,
+) -> Result<HttpResponse, AppError> {
+    require_finance(&state, &req).await?;
+    let report = services::settlement::report(&state.db, query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[get("/settlements/{batch_id}")]
+pub async fn get_settlement(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    require_finance(&state, &req).await?;
+    let batch = services::settlement::get_batch(&state.db, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(batch))
+}
+
+#[post("/settlements/{batch_id}/status")]
+pub async fn update_settlement_status(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<SettlementStatusRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_finance(&state, &req).await?;
+    let batch =
+        services::settlement::update_batch_status(&state.db, path.into_inner(), payload.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(batch))
+}
+
+#[put("/merchant/{merchant_id}/settlement-schedule")]
+pub async fn set_settlement_schedule(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<SettlementScheduleRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_finance(&state, &req).await?;
+    services::settlement::set_schedule(&state.db, path.into_inner(), payload.schedule).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
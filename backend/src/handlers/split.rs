@@ -0,0 +1,65 @@
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::handlers::AppState;
+use crate::middleware::jwt_auth::AuthenticatedUser;
+use crate::models::split::{CreateSplitRequest, PaySplitShareRequest};
+use crate::services;
+
+#[post("/splits")]
+pub async fn create_split(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    payload: web::Json<CreateSplitRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::split::create_split(&state.db, &state.redis, user, payload.into_inner()).await?;
+    Ok(HttpResponse::Created().json(resp))
+}
+
+#[get("/splits/{split_id}")]
+pub async fn get_split(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::split::get_split(&state.db, user, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[post("/splits/{split_id}/pay")]
+pub async fn pay_split_share(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<PaySplitShareRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::split::pay_share(
+        &state.db,
+        &state.upi,
+        &state.config.spending_limits,
+        user,
+        path.into_inner(),
+        payload.into_inner(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
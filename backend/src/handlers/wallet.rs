@@ -0,0 +1,56 @@
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::handlers::AppState;
+use crate::middleware::jwt_auth::AuthenticatedUser;
+use crate::models::wallet::{TopUpRequest, WalletBalanceResponse};
+use crate::services;
+
+#[post("/wallet/topup")]
+pub async fn top_up(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    payload: web::Json<TopUpRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let funding = state
+        .funding
+        .as_deref()
+        .ok_or_else(|| AppError::forbidden("wallet top-ups are not available: no funding source is configured"))?;
+    let resp = services::wallet::top_up(&state.db, funding, user, payload.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[get("/wallet/topup/{topup_id}")]
+pub async fn get_top_up(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let resp = services::wallet::get_topup(&state.db, user, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[get("/wallet/balance")]
+pub async fn balance(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or_else(|| AppError::unauthorized("unauthorized"))?
+        .user_id;
+
+    let balance = services::wallet::balance(&state.db, user).await?;
+    Ok(HttpResponse::Ok().json(WalletBalanceResponse { balance }))
+}
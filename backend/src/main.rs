@@ -1,7 +1,9 @@
 use actix_web::{middleware::Logger, web, App, HttpServer};
 use qr_payment_backend::config::Config;
 use qr_payment_backend::middleware::jwt_auth::JwtAuth;
-use qr_payment_backend::{cache, db, handlers};
+use qr_payment_backend::utils::qr_decoder;
+use qr_payment_backend::utils::upi_client::UpiClient;
+use qr_payment_backend::{cache, db, handlers, services};
 use std::time::Duration;
 
 #[actix_web::main]
@@ -21,8 +23,38 @@ async fn main() -> std::io::Result<()> {
         config: cfg.clone(),
         db,
         redis,
+        funding: cfg.funding_provider.map(|provider| provider.build()),
+        upi: UpiClient::new(cfg.psp.clone()).expect("failed to build psp client"),
     };
 
+    let sweeper_db = state.db.clone();
+    let sweeper_upi = state.upi.clone();
+    let sweeper_limits = state.config.spending_limits;
+    let session_ttl_seconds = state.config.payment_session_ttl_seconds;
+    let reconciliation = state.config.reconciliation;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let now = chrono::Utc::now().naive_utc();
+            if let Err(e) = services::payment::expire_stale_sessions(&sweeper_db, session_ttl_seconds).await {
+                log::warn!("failed to expire payment sessions: {}", e);
+            }
+            if let Err(e) = services::split::expire_due(&sweeper_db, &sweeper_upi, now).await {
+                log::warn!("failed to expire split bills: {}", e);
+            }
+            if let Err(e) = services::collect::expire_due(&sweeper_db, now).await {
+                log::warn!("failed to expire collect requests: {}", e);
+            }
+            if let Err(e) = services::mandate::run_due(&sweeper_db, &sweeper_upi, &sweeper_limits, now).await {
+                log::warn!("failed to run mandates: {}", e);
+            }
+            if let Err(e) = services::reconciliation::run_due(&sweeper_db, &sweeper_upi, &reconciliation, now).await {
+                log::warn!("failed to reconcile pending payments: {}", e);
+            }
+        }
+    });
+
     let bind_addr = format!("{}:{}", cfg.server_host, cfg.server_port);
 
     HttpServer::new(move || {
@@ -34,6 +66,8 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(state.clone()))
             .wrap(Logger::default())
             .service(handlers::health)
+            .service(handlers::circuit_breakers)
+            .service(handlers::psp::psp_callback)
             .service(
                 web::scope("/auth")
                     .service(handlers::auth::register)
@@ -42,9 +76,52 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/api")
                     .wrap(jwt)
+                    .app_data(web::PayloadConfig::new(qr_decoder::MAX_IMAGE_BYTES))
                     .service(handlers::merchant::resolve_merchant)
+                    .service(handlers::merchant::merchant_qr)
+                    .service(handlers::merchant::merchant_balance)
+                    .service(handlers::merchant::register_merchant_key)
+                    .service(handlers::merchant::list_merchant_keys)
+                    .service(handlers::merchant::revoke_merchant_key)
+                    .service(handlers::merchant::decode_qr_image)
+                    .service(handlers::merchant::create_order)
+                    .service(handlers::merchant::get_order)
                     .service(handlers::payment::initiate_payment)
-                    .service(handlers::payment::execute_payment),
+                    .service(handlers::payment::execute_payment)
+                    .service(handlers::payment::refund_payment)
+                    .service(handlers::settlement::run_settlement)
+                    .service(handlers::settlement::settlement_report)
+                    .service(handlers::settlement::get_settlement)
+                    .service(handlers::settlement::update_settlement_status)
+                    .service(handlers::settlement::set_settlement_schedule)
+                    .service(handlers::wallet::top_up)
+                    .service(handlers::wallet::get_top_up)
+                    .service(handlers::wallet::balance)
+                    .service(handlers::limits::get_limits)
+                    .service(handlers::limits::lower_limits)
+                    .service(handlers::limits::set_user_limits)
+                    .service(handlers::fee::create_fee_rule)
+                    .service(handlers::fee::list_fee_rules)
+                    .service(handlers::fee::deactivate_fee_rule)
+                    .service(handlers::reward::create_campaign)
+                    .service(handlers::reward::list_campaigns)
+                    .service(handlers::reward::my_rewards)
+                    .service(handlers::promo::create_promo_code)
+                    .service(handlers::promo::list_promo_codes)
+                    .service(handlers::split::create_split)
+                    .service(handlers::split::get_split)
+                    .service(handlers::split::pay_split_share)
+                    .service(handlers::collect::create_collect_request)
+                    .service(handlers::collect::get_collect_request)
+                    .service(handlers::collect::pending_collect_requests)
+                    .service(handlers::collect::approve_collect_request)
+                    .service(handlers::collect::decline_collect_request)
+                    .service(handlers::mandate::create_mandate)
+                    .service(handlers::mandate::update_mandate)
+                    .service(handlers::mandate::my_mandates)
+                    .service(handlers::mandate::approve_mandate)
+                    .service(handlers::mandate::revoke_mandate)
+                    .service(handlers::mandate::mandate_history),
             )
     })
     .bind(bind_addr)?
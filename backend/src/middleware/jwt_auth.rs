@@ -63,8 +63,6 @@ where
 
         let srv = self.service.clone();
 
-        let mut req = req;
-
         Box::pin(async move {
             let auth_header = auth_header.ok_or_else(|| AppError::unauthorized("missing authorization"))?;
             let token = auth_header
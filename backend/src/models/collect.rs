@@ -0,0 +1,57 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "collect_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CollectStatus {
+    Pending,
+    Approved,
+    Declined,
+    Expired,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CollectRequest {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub user_id: Uuid,
+    pub amount: Money,
+    pub note: Option<String>,
+    pub status: CollectStatus,
+    pub expires_at: NaiveDateTime,
+    pub transaction_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCollectRequest {
+    pub payer: String,
+    pub amount: Money,
+    pub note: Option<String>,
+    pub expires_in_secs: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApproveCollectRequest {
+    pub pin: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CollectRequestResponse {
+    pub request_id: Uuid,
+    pub merchant_id: Uuid,
+    pub merchant_name: String,
+    pub payer_upi_id: String,
+    pub amount: Money,
+    pub note: Option<String>,
+    pub status: CollectStatus,
+    pub expires_at: NaiveDateTime,
+    pub transaction_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
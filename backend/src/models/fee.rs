@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct FeeRule {
+    pub id: Uuid,
+    pub category: Option<String>,
+    pub min_amount: Money,
+    pub max_amount: Option<Money>,
+    pub percentage_bps: i32,
+    pub flat_fee: Money,
+    pub min_fee: Option<Money>,
+    pub max_fee: Option<Money>,
+    pub active: bool,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFeeRuleRequest {
+    pub category: Option<String>,
+    #[serde(default)]
+    pub min_amount: Money,
+    pub max_amount: Option<Money>,
+    #[serde(default)]
+    pub percentage_bps: i32,
+    #[serde(default)]
+    pub flat_fee: Money,
+    pub min_fee: Option<Money>,
+    pub max_fee: Option<Money>,
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "ledger_account_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+    UserWallet,
+    MerchantPayable,
+    FeeRevenue,
+    Funding,
+    RewardsExpense,
+    SplitEscrow,
+    PaymentHold,
+}
+
+impl AccountType {
+    // Wallets, payables and revenue grow with credits; cash held in funding and rewards paid out grow with debits.
+    pub fn is_credit_normal(self) -> bool {
+        !matches!(self, AccountType::Funding | AccountType::RewardsExpense)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "ledger_direction", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Debit,
+    Credit,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct LedgerAccount {
+    pub account_type: AccountType,
+    pub account_id: Option<Uuid>,
+}
+
+impl LedgerAccount {
+    pub fn user_wallet(user_id: Uuid) -> Self {
+        LedgerAccount {
+            account_type: AccountType::UserWallet,
+            account_id: Some(user_id),
+        }
+    }
+
+    pub fn merchant_payable(merchant_id: Uuid) -> Self {
+        LedgerAccount {
+            account_type: AccountType::MerchantPayable,
+            account_id: Some(merchant_id),
+        }
+    }
+
+    pub fn fee_revenue() -> Self {
+        LedgerAccount {
+            account_type: AccountType::FeeRevenue,
+            account_id: None,
+        }
+    }
+
+    pub fn funding() -> Self {
+        LedgerAccount {
+            account_type: AccountType::Funding,
+            account_id: None,
+        }
+    }
+
+    pub fn rewards_expense() -> Self {
+        LedgerAccount {
+            account_type: AccountType::RewardsExpense,
+            account_id: None,
+        }
+    }
+
+    pub fn split_escrow(split_id: Uuid) -> Self {
+        LedgerAccount {
+            account_type: AccountType::SplitEscrow,
+            account_id: Some(split_id),
+        }
+    }
+
+    pub fn payment_hold(transaction_id: Uuid) -> Self {
+        LedgerAccount {
+            account_type: AccountType::PaymentHold,
+            account_id: Some(transaction_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Posting {
+    pub account: LedgerAccount,
+    pub direction: Direction,
+    pub amount: Money,
+}
+
+impl Posting {
+    pub fn debit(account: LedgerAccount, amount: Money) -> Self {
+        Posting {
+            account,
+            direction: Direction::Debit,
+            amount,
+        }
+    }
+
+    pub fn credit(account: LedgerAccount, amount: Money) -> Self {
+        Posting {
+            account,
+            direction: Direction::Credit,
+            amount,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct LedgerEntry {
+    pub id: Uuid,
+    pub journal_id: Uuid,
+    pub transaction_id: Option<Uuid>,
+    pub account_type: AccountType,
+    pub account_id: Option<Uuid>,
+    pub direction: Direction,
+    pub amount: Money,
+    pub description: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, PartialEq)]
+pub struct WalletMismatch {
+    pub user_id: Uuid,
+    pub balance: Money,
+    pub ledger_balance: Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MerchantBalanceResponse {
+    pub merchant_id: Uuid,
+    pub payable: Money,
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SpendingLimits {
+    pub per_transaction: Money,
+    pub daily: Money,
+    pub monthly: Money,
+}
+
+#[derive(Debug, FromRow)]
+pub struct UserLimits {
+    pub per_transaction: Option<Money>,
+    pub daily: Option<Money>,
+    pub monthly: Option<Money>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserLimitsRequest {
+    pub per_transaction: Option<Money>,
+    pub daily: Option<Money>,
+    pub monthly: Option<Money>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RemainingLimits {
+    pub daily: Money,
+    pub monthly: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LimitsResponse {
+    pub limits: SpendingLimits,
+    pub remaining: RemainingLimits,
+}
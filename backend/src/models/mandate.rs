@@ -0,0 +1,99 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "mandate_frequency", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MandateFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "mandate_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MandateStatus {
+    Pending,
+    Active,
+    Revoked,
+    Completed,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "mandate_execution_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MandateExecutionStatus {
+    Notified,
+    Pending,
+    Success,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Mandate {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub merchant_id: Uuid,
+    pub description: Option<String>,
+    pub amount: Money,
+    pub max_amount: Money,
+    pub frequency: MandateFrequency,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub status: MandateStatus,
+    pub next_cycle: i32,
+    pub next_debit_date: NaiveDate,
+    pub approved_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MandateExecution {
+    pub id: Uuid,
+    pub mandate_id: Uuid,
+    pub cycle: i32,
+    pub due_date: NaiveDate,
+    pub amount: Money,
+    pub status: MandateExecutionStatus,
+    pub transaction_id: Option<Uuid>,
+    pub error_message: Option<String>,
+    pub notified_at: NaiveDateTime,
+    pub executed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMandateRequest {
+    pub payer: String,
+    pub description: Option<String>,
+    pub amount: Money,
+    pub max_amount: Money,
+    pub frequency: MandateFrequency,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMandateRequest {
+    pub amount: Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApproveMandateRequest {
+    pub pin: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MandateRun {
+    pub notified: usize,
+    pub debited: usize,
+    pub failed: usize,
+}
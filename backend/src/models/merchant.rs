@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::models::money::Money;
+use crate::utils::qr_render::{QrErrorCorrection, QrImageFormat};
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Merchant {
     pub id: Uuid,
@@ -12,9 +15,51 @@ pub struct Merchant {
     pub phone: Option<String>,
     pub qr_code_data: String,
     pub created_at: chrono::NaiveDateTime,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub verified: bool,
+}
+
+// A public key the merchant signs its QR intents with. Revoked keys are kept for the record.
+#[derive(Debug, Serialize, FromRow)]
+pub struct MerchantKey {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub public_key: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterMerchantKeyRequest {
+    pub public_key: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct QRScanRequest {
     pub qr_data: String,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct MerchantQrQuery {
+    #[serde(default)]
+    pub format: QrImageFormat,
+    pub size: Option<u32>,
+    #[serde(default)]
+    pub ec: QrErrorCorrection,
+    pub amount: Option<Money>,
+    pub note: Option<String>,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DecodedQrCode {
+    pub payload: String,
+    pub merchant: Option<Merchant>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QrImageDecodeResponse {
+    pub codes: Vec<DecodedQrCode>,
+}
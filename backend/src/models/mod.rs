@@ -1,3 +1,18 @@
+pub mod collect;
+pub mod fee;
+pub mod ledger;
+pub mod limits;
+pub mod mandate;
 pub mod merchant;
+pub mod money;
+pub mod order;
+pub mod payee;
 pub mod payment;
+pub mod promo;
+pub mod reconciliation;
+pub mod refund;
+pub mod reward;
+pub mod settlement;
+pub mod split;
 pub mod user;
+pub mod wallet;
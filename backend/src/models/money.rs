@@ -0,0 +1,155 @@
+use std::fmt;
+use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum MoneyError {
+    #[error("invalid amount")]
+    Invalid,
+    #[error("amount has more than two decimal places")]
+    TooPrecise,
+    #[error("amount is out of range")]
+    OutOfRange,
+}
+
+// Whole paise. Stored as BIGINT and exchanged as a rupee amount with at most two decimals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_paise(paise: i64) -> Self {
+        Money(paise)
+    }
+
+    pub const fn from_rupees(rupees: i64) -> Self {
+        Money(rupees * 100)
+    }
+
+    pub const fn paise(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.0.checked_sub(other.0).map(Money)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let (negative, unsigned) = match raw.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, raw),
+        };
+        let (whole, frac) = match unsigned.split_once('.') {
+            Some((whole, frac)) if !frac.is_empty() => (whole, frac),
+            Some(_) => return Err(MoneyError::Invalid),
+            None => (unsigned, ""),
+        };
+        if whole.is_empty() || !whole.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(MoneyError::Invalid);
+        }
+        if frac.len() > 2 {
+            return Err(MoneyError::TooPrecise);
+        }
+
+        let rupees: i64 = whole.parse().map_err(|_| MoneyError::OutOfRange)?;
+        let paise: i64 = format!("{:0<2}", frac).parse().map_err(|_| MoneyError::Invalid)?;
+        let total = rupees
+            .checked_mul(100)
+            .and_then(|p| p.checked_add(paise))
+            .ok_or(MoneyError::OutOfRange)?;
+        Ok(Money(if negative { -total } else { total }))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0 as f64 / 100.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+struct MoneyVisitor;
+
+impl Visitor<'_> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an amount with at most two decimal places")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+        v.checked_mul(100).map(Money).ok_or_else(|| E::custom(MoneyError::OutOfRange))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+        i64::try_from(v)
+            .map_err(|_| E::custom(MoneyError::OutOfRange))
+            .and_then(|v| self.visit_i64(v))
+    }
+
+    // Display for f64 prints the shortest string that round-trips, so 0.1 stays "0.1" and 0.005 is caught.
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+        if !v.is_finite() {
+            return Err(E::custom(MoneyError::Invalid));
+        }
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+        v.trim().parse().map_err(E::custom)
+    }
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct MerchantOrder {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub transaction_ref: String,
+    pub amount: Money,
+    pub note: Option<String>,
+    pub expires_at: chrono::NaiveDateTime,
+    pub paid_at: Option<chrono::NaiveDateTime>,
+    pub transaction_id: Option<Uuid>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Open,
+    Paid,
+    Expired,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrderRequest {
+    pub amount: Money,
+    pub note: Option<String>,
+    pub expires_in_secs: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderResponse {
+    pub order_id: Uuid,
+    pub transaction_ref: String,
+    pub amount: Money,
+    pub status: OrderStatus,
+    pub expires_at: chrono::NaiveDateTime,
+    pub transaction_id: Option<Uuid>,
+    pub qr_data: String,
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::merchant::Merchant;
+use crate::models::user::User;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PayeeType {
+    Merchant,
+    User,
+}
+
+#[derive(Debug)]
+pub enum Payee {
+    Merchant(Merchant),
+    User(User),
+}
+
+impl Payee {
+    pub fn payee_type(&self) -> PayeeType {
+        match self {
+            Payee::Merchant(_) => PayeeType::Merchant,
+            Payee::User(_) => PayeeType::User,
+        }
+    }
+}
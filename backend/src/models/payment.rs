@@ -2,11 +2,17 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone)]
+use crate::models::limits::RemainingLimits;
+use crate::models::money::Money;
+use crate::models::payee::PayeeType;
+use crate::utils::upi_client::PspPayment;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
 #[sqlx(type_name = "transaction_status", rename_all = "lowercase")]
 pub enum TransactionStatus {
     Initiated,
     Pending,
+    Escrowed,
     Success,
     Failed,
     Refunded,
@@ -16,12 +22,21 @@ pub enum TransactionStatus {
 pub struct Transaction {
     pub id: Uuid,
     pub user_id: Uuid,
-    pub merchant_id: Uuid,
-    pub amount: f64,
+    pub merchant_id: Option<Uuid>,
+    pub payee_user_id: Option<Uuid>,
+    pub amount: Money,
+    pub refunded_amount: Money,
+    pub fee_amount: Money,
+    pub net_amount: Money,
+    pub refunded_fee: Money,
     pub status: TransactionStatus,
     pub idempotency_key: String,
     pub upi_txn_id: Option<String>,
     pub error_message: Option<String>,
+    pub order_id: Option<Uuid>,
+    pub promo_code_id: Option<Uuid>,
+    pub discount_amount: Money,
+    pub split_id: Option<Uuid>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
@@ -29,23 +44,32 @@ pub struct Transaction {
 #[derive(Debug, Deserialize)]
 pub struct PaymentInitRequest {
     pub qr_data: String,
-    pub amount: f64,
+    pub amount: Option<Money>,
     pub idempotency_key: String,
+    pub promo_code: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaymentInitResponse {
     pub session_id: Uuid,
-    pub merchant: MerchantInfo,
-    pub amount: f64,
+    pub payee: PayeeInfo,
+    pub amount: Money,
+    pub currency: String,
+    pub amount_editable: bool,
+    pub min_amount: Option<Money>,
+    pub promo_code: Option<String>,
+    pub discount: Money,
+    pub payable_amount: Money,
     pub status: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MerchantInfo {
+pub struct PayeeInfo {
+    pub payee_type: PayeeType,
     pub name: String,
     pub upi_id: String,
     pub category: Option<String>,
+    pub verified: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -60,4 +84,21 @@ pub struct PaymentExecuteResponse {
     pub status: String,
     pub upi_txn_id: Option<String>,
     pub message: String,
+    pub remaining_limits: Option<RemainingLimits>,
+    pub cashback: Option<Money>,
+}
+
+// The PSP's notification of a payment's final state. `event_id` identifies the delivery, so a replay can be spotted.
+#[derive(Debug, Deserialize)]
+pub struct PspCallback {
+    pub event_id: String,
+    #[serde(flatten)]
+    pub payment: PspPayment,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PspCallbackResponse {
+    pub transaction_id: Uuid,
+    pub status: String,
+    pub duplicate: bool,
 }
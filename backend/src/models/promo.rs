@@ -0,0 +1,39 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PromoCode {
+    pub id: Uuid,
+    pub code: String,
+    pub percentage_bps: i32,
+    pub flat_discount: Money,
+    pub max_discount: Option<Money>,
+    pub min_amount: Money,
+    pub merchant_id: Option<Uuid>,
+    pub usage_limit: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub redemption_count: i32,
+    pub expires_at: NaiveDateTime,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePromoCodeRequest {
+    pub code: String,
+    #[serde(default)]
+    pub percentage_bps: i32,
+    #[serde(default)]
+    pub flat_discount: Money,
+    pub max_discount: Option<Money>,
+    #[serde(default)]
+    pub min_amount: Money,
+    pub merchant_id: Option<Uuid>,
+    pub usage_limit: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub expires_at: NaiveDateTime,
+}
@@ -0,0 +1,45 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// Pending payments are first polled `poll_after_secs` after they went pending, then with a delay that
+// doubles from `backoff_secs` up to `max_backoff_secs`. One still pending after `max_age_secs` is reversed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReconciliationConfig {
+    pub poll_after_secs: i64,
+    pub backoff_secs: i64,
+    pub max_backoff_secs: i64,
+    pub max_age_secs: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "poll_outcome", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PollOutcome {
+    Pending,
+    Success,
+    Failed,
+    Reversed,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct StatusPoll {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub attempt: i32,
+    pub psp_status: Option<String>,
+    pub outcome: PollOutcome,
+    pub error_message: Option<String>,
+    pub polled_at: NaiveDateTime,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReconciliationRun {
+    pub polled: usize,
+    pub settled: usize,
+    pub failed: usize,
+    pub reversed: usize,
+    pub errors: usize,
+}
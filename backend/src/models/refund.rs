@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "refund_initiator", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum RefundInitiator {
+    Merchant,
+    Admin,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Refund {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub amount: Money,
+    pub reason: Option<String>,
+    pub initiated_by: RefundInitiator,
+    pub requested_by: Option<Uuid>,
+    pub refund_key: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefundRequest {
+    pub amount: Option<Money>,
+    pub reason: Option<String>,
+    pub refund_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefundResponse {
+    pub refund_id: Uuid,
+    pub transaction_id: Uuid,
+    pub amount: Money,
+    pub refunded_amount: Money,
+    pub transaction_status: String,
+    pub created_at: chrono::NaiveDateTime,
+}
@@ -0,0 +1,76 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq, Default)]
+#[sqlx(type_name = "reward_cohort", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RewardCohort {
+    #[default]
+    All,
+    NewUser,
+    FirstInCategory,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "reward_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum RewardStatus {
+    Credited,
+    Reversed,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RewardCampaign {
+    pub id: Uuid,
+    pub name: String,
+    pub category: Option<String>,
+    pub cohort: RewardCohort,
+    pub min_amount: Money,
+    pub max_amount: Option<Money>,
+    pub percentage_bps: i32,
+    pub flat_amount: Money,
+    pub max_reward: Option<Money>,
+    pub per_user_limit: Option<i32>,
+    pub per_user_cap: Option<Money>,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: NaiveDateTime,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRewardCampaignRequest {
+    pub name: String,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub cohort: RewardCohort,
+    #[serde(default)]
+    pub min_amount: Money,
+    pub max_amount: Option<Money>,
+    #[serde(default)]
+    pub percentage_bps: i32,
+    #[serde(default)]
+    pub flat_amount: Money,
+    pub max_reward: Option<Money>,
+    pub per_user_limit: Option<i32>,
+    pub per_user_cap: Option<Money>,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Reward {
+    pub id: Uuid,
+    pub campaign_id: Uuid,
+    pub transaction_id: Uuid,
+    pub user_id: Uuid,
+    pub amount: Money,
+    pub reversed_amount: Money,
+    pub status: RewardStatus,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
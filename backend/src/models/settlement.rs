@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "settlement_schedule", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementSchedule {
+    T0,
+    T1,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "settlement_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SettlementStatus {
+    Pending,
+    Processing,
+    Paid,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SettlementBatch {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub schedule: SettlementSchedule,
+    pub window_start: chrono::NaiveDateTime,
+    pub window_end: chrono::NaiveDateTime,
+    pub amount: Money,
+    pub transaction_count: i32,
+    pub status: SettlementStatus,
+    pub payout_reference: Option<String>,
+    pub failure_reason: Option<String>,
+    pub paid_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettlementScheduleRequest {
+    pub schedule: SettlementSchedule,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettlementStatusRequest {
+    pub status: SettlementStatus,
+    pub payout_reference: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettlementReportQuery {
+    pub merchant_id: Option<Uuid>,
+    pub from: Option<chrono::NaiveDate>,
+    pub to: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MerchantSettlementSummary {
+    pub merchant_id: Uuid,
+    pub schedule: SettlementSchedule,
+    pub paid: Money,
+    pub in_flight: Money,
+    pub outstanding: Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettlementRunResponse {
+    pub batches: Vec<SettlementBatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettlementReport {
+    pub merchants: Vec<MerchantSettlementSummary>,
+    pub batches: Vec<SettlementBatch>,
+}
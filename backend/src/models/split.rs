@@ -0,0 +1,70 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "split_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SplitStatus {
+    Open,
+    Completed,
+    Expired,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SplitBill {
+    pub id: Uuid,
+    pub initiator_id: Uuid,
+    pub merchant_id: Uuid,
+    pub order_id: Option<Uuid>,
+    pub amount: Money,
+    pub status: SplitStatus,
+    pub expires_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SplitParticipantRequest {
+    pub payer: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSplitRequest {
+    pub qr_data: String,
+    pub amount: Option<Money>,
+    pub participants: Vec<SplitParticipantRequest>,
+    pub expires_in_secs: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaySplitShareRequest {
+    pub pin: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitShareResponse {
+    pub transaction_id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub upi_id: String,
+    pub amount: Money,
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitResponse {
+    pub split_id: Uuid,
+    pub merchant_id: Uuid,
+    pub merchant_name: String,
+    pub amount: Money,
+    pub paid_amount: Money,
+    pub status: SplitStatus,
+    pub expires_at: NaiveDateTime,
+    pub shares: Vec<SplitShareResponse>,
+}
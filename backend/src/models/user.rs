@@ -2,13 +2,24 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    User,
+    Finance,
+    Admin,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: Uuid,
     pub phone_number: String,
     pub upi_id: String,
     pub name: String,
-    pub balance: f64,
+    pub balance: Money,
     #[serde(skip_serializing)]
     pub pin_hash: String,
     pub created_at: chrono::NaiveDateTime,
@@ -20,7 +31,7 @@ pub struct UserPublic {
     pub id: Uuid,
     pub name: String,
     pub upi_id: String,
-    pub balance: f64,
+    pub balance: Money,
 }
 
 impl From<User> for UserPublic {
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "topup_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TopUpStatus {
+    Initiated,
+    Pending,
+    Success,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WalletTopUp {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub amount: Money,
+    pub status: TopUpStatus,
+    pub funding_source: String,
+    pub instrument: String,
+    pub idempotency_key: String,
+    pub provider_reference: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TopUpRequest {
+    pub amount: Money,
+    pub instrument: String,
+    pub idempotency_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopUpResponse {
+    pub topup_id: Uuid,
+    pub amount: Money,
+    pub status: TopUpStatus,
+    pub provider_reference: Option<String>,
+    pub failure_reason: Option<String>,
+    pub balance: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletBalanceResponse {
+    pub balance: Money,
+}
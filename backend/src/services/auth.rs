@@ -7,7 +7,7 @@ use uuid::Uuid;
 
 use crate::config::Config;
 use crate::handlers::errors::AppError;
-use crate::models::user::{AuthResponse, LoginRequest, RegisterRequest, User, UserPublic};
+use crate::models::user::{AuthResponse, LoginRequest, RegisterRequest, User, UserPublic, UserRole};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -37,7 +37,7 @@ pub async fn register(cfg: &Config, db: &PgPool, req: RegisterRequest) -> Result
     .bind(pin_hash)
     .fetch_one(db)
     .await
-    .map_err(|e| AppError::from_sqlx(e))?;
+    .map_err(AppError::from_sqlx)?;
 
     let (token, expires_in) = mint_token(cfg, user.id)?;
     Ok(AuthResponse {
@@ -58,7 +58,7 @@ pub async fn login(cfg: &Config, db: &PgPool, req: LoginRequest) -> Result<AuthR
     .bind(req.phone_number)
     .fetch_one(db)
     .await
-    .map_err(|e| AppError::from_sqlx(e))?;
+    .map_err(AppError::from_sqlx)?;
 
     let ok = verify(req.pin, &user.pin_hash).map_err(|_| AppError::internal("verify failed"))?;
     if !ok {
@@ -73,6 +73,21 @@ pub async fn login(cfg: &Config, db: &PgPool, req: LoginRequest) -> Result<AuthR
     })
 }
 
+// Roles are read on every check rather than carried in the token, so revoking one takes effect at once.
+// Admins may do anything a narrower staff role can.
+pub async fn require_role(db: &PgPool, user_id: Uuid, allowed: &[UserRole]) -> Result<(), AppError> {
+    let role: Option<UserRole> = sqlx::query_scalar("SELECT role FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+    match role {
+        Some(role) if role == UserRole::Admin || allowed.contains(&role) => Ok(()),
+        _ => Err(AppError::forbidden("not permitted for this account")),
+    }
+}
+
 fn mint_token(cfg: &Config, user_id: Uuid) -> Result<(String, i64), AppError> {
     let now = Utc::now();
     let exp = now + Duration::seconds(cfg.jwt_ttl_seconds);
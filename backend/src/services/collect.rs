@@ -0,0 +1,235 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{Acquire, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::cache::redis_client::RedisClient;
+use crate::handlers::errors::AppError;
+use crate::models::collect::{
+    ApproveCollectRequest, CollectRequest, CollectRequestResponse, CollectStatus, CreateCollectRequest,
+};
+use crate::models::limits::SpendingLimits;
+use crate::models::payment::{PaymentExecuteResponse, Transaction, TransactionStatus};
+use crate::services::{merchant, payee, payment};
+use crate::utils::upi_client::UpiClient;
+
+const DEFAULT_COLLECT_TTL_SECS: i64 = 30 * 60;
+const MAX_COLLECT_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+pub async fn create_request(
+    db: &PgPool,
+    redis: &RedisClient,
+    merchant_id: Uuid,
+    req: CreateCollectRequest,
+) -> Result<CollectRequestResponse, AppError> {
+    if !req.amount.is_positive() {
+        return Err(AppError::bad_request("amount must be greater than 0"));
+    }
+    let ttl = req.expires_in_secs.unwrap_or(DEFAULT_COLLECT_TTL_SECS);
+    if !(1..=MAX_COLLECT_TTL_SECS).contains(&ttl) {
+        return Err(AppError::bad_request(format!(
+            "expires_in_secs must be between 1 and {}",
+            MAX_COLLECT_TTL_SECS
+        )));
+    }
+
+    let merchant = merchant::get_merchant_by_id(db, redis, merchant_id).await?;
+    let handle = req.payer.trim().to_lowercase();
+    let payer = payee::get_user_by_vpa(db, &handle)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("payer {} not found", handle)))?;
+
+    let request_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO collect_requests (merchant_id, user_id, amount, note, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(merchant.id)
+    .bind(payer.id)
+    .bind(req.amount)
+    .bind(&req.note)
+    .bind(Utc::now().naive_utc() + Duration::seconds(ttl))
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    collect_response(db, request_id).await
+}
+
+pub async fn get_request(db: &PgPool, merchant_id: Uuid, request_id: Uuid) -> Result<CollectRequestResponse, AppError> {
+    let response = collect_response(db, request_id).await?;
+    if response.merchant_id != merchant_id {
+        return Err(AppError::NotFound("collect request not found".to_string()));
+    }
+    Ok(response)
+}
+
+pub async fn pending_for_user(db: &PgPool, user_id: Uuid) -> Result<Vec<CollectRequestResponse>, AppError> {
+    sqlx::query_as::<_, CollectRequestResponse>(
+        r#"
+        SELECT c.id AS request_id, c.merchant_id, m.name AS merchant_name, u.upi_id AS payer_upi_id, c.amount, c.note,
+               c.status, c.expires_at, c.transaction_id, c.created_at
+        FROM collect_requests c
+        JOIN merchants m ON m.id = c.merchant_id
+        JOIN users u ON u.id = c.user_id
+        WHERE c.user_id = $1 AND c.status = 'pending' AND c.expires_at > $2
+        ORDER BY c.created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(Utc::now().naive_utc())
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+// Approving turns the request into a merchant payment and debits it in the same database transaction,
+// so a request is never left approved without its payment or paid twice. The debit runs in a savepoint: a
+// failed attempt is kept as a failed payment under its PSP reference and the request stays open for another.
+pub async fn approve(
+    db: &PgPool,
+    upi: &UpiClient,
+    spending_limits: &SpendingLimits,
+    user_id: Uuid,
+    request_id: Uuid,
+    req: ApproveCollectRequest,
+) -> Result<PaymentExecuteResponse, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let request = lock_pending(&mut tx, user_id, request_id).await?;
+
+    payment::verify_pin(&mut tx, user_id, &req.pin).await?;
+
+    let attempts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE idempotency_key LIKE $1")
+        .bind(format!("collect:{}:%", request.id))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    let transaction = sqlx::query_as::<_, Transaction>(
+        r#"
+        INSERT INTO transactions (user_id, merchant_id, amount, status, idempotency_key)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(request.merchant_id)
+    .bind(request.amount)
+    .bind(TransactionStatus::Initiated)
+    .bind(format!("collect:{}:{}", request.id, attempts + 1))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let transaction_id = transaction.id;
+    let mut debit = tx.begin().await.map_err(AppError::from_sqlx)?;
+    let response = match payment::complete_payment(&mut debit, upi, spending_limits, transaction).await {
+        Ok(response) => {
+            debit.commit().await.map_err(AppError::from_sqlx)?;
+            response
+        }
+        Err(e) => {
+            debit.rollback().await.map_err(AppError::from_sqlx)?;
+            payment::mark_failed(&mut *tx, transaction_id, &e.to_string()).await?;
+            tx.commit().await.map_err(AppError::from_sqlx)?;
+            return Err(e);
+        }
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE collect_requests
+        SET status = $1, transaction_id = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $3
+        "#,
+    )
+    .bind(CollectStatus::Approved)
+    .bind(response.transaction_id)
+    .bind(request.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(response)
+}
+
+pub async fn decline(db: &PgPool, user_id: Uuid, request_id: Uuid) -> Result<CollectRequestResponse, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let request = lock_pending(&mut tx, user_id, request_id).await?;
+
+    sqlx::query("UPDATE collect_requests SET status = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+        .bind(CollectStatus::Declined)
+        .bind(request.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    collect_response(db, request.id).await
+}
+
+pub async fn expire_due(db: &PgPool, now: NaiveDateTime) -> Result<usize, AppError> {
+    let expired = sqlx::query(
+        r#"
+        UPDATE collect_requests
+        SET status = 'expired', updated_at = CURRENT_TIMESTAMP
+        WHERE status = 'pending' AND expires_at <= $1
+        "#,
+    )
+    .bind(now)
+    .execute(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    Ok(expired.rows_affected() as usize)
+}
+
+async fn lock_pending(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    user_id: Uuid,
+    request_id: Uuid,
+) -> Result<CollectRequest, AppError> {
+    let request = sqlx::query_as::<_, CollectRequest>(
+        r#"
+        SELECT id, merchant_id, user_id, amount, note, status, expires_at, transaction_id, created_at, updated_at
+        FROM collect_requests
+        WHERE id = $1 AND user_id = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(request_id)
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("collect request not found".to_string()))?;
+
+    match request.status {
+        CollectStatus::Pending if request.expires_at > Utc::now().naive_utc() => Ok(request),
+        CollectStatus::Pending | CollectStatus::Expired => Err(AppError::bad_request("collect request has expired")),
+        CollectStatus::Approved => Err(AppError::Conflict("collect request has already been approved".to_string())),
+        CollectStatus::Declined => Err(AppError::Conflict("collect request has already been declined".to_string())),
+    }
+}
+
+// A pending request past its expiry reads as expired even before the sweeper has marked it.
+async fn collect_response(db: &PgPool, request_id: Uuid) -> Result<CollectRequestResponse, AppError> {
+    sqlx::query_as::<_, CollectRequestResponse>(
+        r#"
+        SELECT c.id AS request_id, c.merchant_id, m.name AS merchant_name, u.upi_id AS payer_upi_id, c.amount, c.note,
+               CASE WHEN c.status = 'pending' AND c.expires_at <= $2 THEN 'expired'::collect_status ELSE c.status END
+                   AS status,
+               c.expires_at, c.transaction_id, c.created_at
+        FROM collect_requests c
+        JOIN merchants m ON m.id = c.merchant_id
+        JOIN users u ON u.id = c.user_id
+        WHERE c.id = $1
+        "#,
+    )
+    .bind(request_id)
+    .bind(Utc::now().naive_utc())
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("collect request not found".to_string()))
+}
@@ -0,0 +1,121 @@
+use sqlx::{PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::fee::{CreateFeeRuleRequest, FeeRule};
+use crate::models::money::Money;
+
+const BPS_DENOMINATOR: i64 = 10_000;
+
+pub async fn create_rule(db: &PgPool, req: CreateFeeRuleRequest) -> Result<FeeRule, AppError> {
+    let category = req.category.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    if !(0..=BPS_DENOMINATOR as i32).contains(&req.percentage_bps) {
+        return Err(AppError::bad_request("percentage_bps must be between 0 and 10000"));
+    }
+    if [Some(req.min_amount), Some(req.flat_fee), req.max_amount, req.min_fee, req.max_fee]
+        .iter()
+        .flatten()
+        .any(|amount| *amount < Money::ZERO)
+    {
+        return Err(AppError::bad_request("amounts must not be negative"));
+    }
+    if req.max_amount.is_some_and(|max| max < req.min_amount) {
+        return Err(AppError::bad_request("max_amount must not be below min_amount"));
+    }
+    if let (Some(min_fee), Some(max_fee)) = (req.min_fee, req.max_fee) {
+        if max_fee < min_fee {
+            return Err(AppError::bad_request("max_fee must not be below min_fee"));
+        }
+    }
+
+    sqlx::query_as::<_, FeeRule>(
+        r#"
+        INSERT INTO fee_rules (category, min_amount, max_amount, percentage_bps, flat_fee, min_fee, max_fee)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, category, min_amount, max_amount, percentage_bps, flat_fee, min_fee, max_fee, active, created_at
+        "#,
+    )
+    .bind(category)
+    .bind(req.min_amount)
+    .bind(req.max_amount)
+    .bind(req.percentage_bps)
+    .bind(req.flat_fee)
+    .bind(req.min_fee)
+    .bind(req.max_fee)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn list_rules(db: &PgPool) -> Result<Vec<FeeRule>, AppError> {
+    sqlx::query_as::<_, FeeRule>(
+        r#"
+        SELECT id, category, min_amount, max_amount, percentage_bps, flat_fee, min_fee, max_fee, active, created_at
+        FROM fee_rules
+        WHERE active
+        ORDER BY category NULLS LAST, min_amount
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn deactivate_rule(db: &PgPool, rule_id: Uuid) -> Result<(), AppError> {
+    let updated = sqlx::query("UPDATE fee_rules SET active = FALSE WHERE id = $1 AND active")
+        .bind(rule_id)
+        .execute(db)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::NotFound("fee rule not found".to_string()));
+    }
+    Ok(())
+}
+
+// A rule for the merchant's own category beats a catch-all rule; within those the narrowest slab wins.
+// No matching rule means the payment is free of MDR.
+pub async fn merchant_fee(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    amount: Money,
+) -> Result<Money, AppError> {
+    let rule = sqlx::query_as::<_, FeeRule>(
+        r#"
+        SELECT r.id, r.category, r.min_amount, r.max_amount, r.percentage_bps, r.flat_fee, r.min_fee, r.max_fee,
+               r.active, r.created_at
+        FROM fee_rules r
+        JOIN merchants m ON m.id = $1
+        WHERE r.active
+          AND (r.category IS NULL OR LOWER(r.category) = LOWER(m.category))
+          AND r.min_amount <= $2 AND (r.max_amount IS NULL OR r.max_amount >= $2)
+        ORDER BY r.category IS NULL, r.min_amount DESC, r.created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(merchant_id)
+    .bind(amount)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    Ok(rule.map_or(Money::ZERO, |rule| compute_fee(&rule, amount)))
+}
+
+pub fn compute_fee(rule: &FeeRule, amount: Money) -> Money {
+    let mut fee = prorate(amount, rule.percentage_bps as i64, BPS_DENOMINATOR) + rule.flat_fee;
+    if let Some(min_fee) = rule.min_fee {
+        fee = fee.max(min_fee);
+    }
+    if let Some(max_fee) = rule.max_fee {
+        fee = fee.min(max_fee);
+    }
+    fee.min(amount)
+}
+
+// value * part / whole, rounded half up to the nearest paisa.
+pub fn prorate(value: Money, part: i64, whole: i64) -> Money {
+    let scaled = value.paise() as i128 * part as i128;
+    let whole = whole as i128;
+    Money::from_paise(((2 * scaled + whole) / (2 * whole)) as i64)
+}
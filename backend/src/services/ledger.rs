@@ -0,0 +1,187 @@
+use sqlx::{PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::ledger::{AccountType, Direction, LedgerAccount, LedgerEntry, Posting, WalletMismatch};
+use crate::models::money::Money;
+use crate::models::payment::Transaction;
+
+// Every movement of money is one balanced journal. users.balance is kept in step as a projection of the wallet account.
+pub async fn post(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transaction_id: Option<Uuid>,
+    description: &str,
+    postings: &[Posting],
+) -> Result<Uuid, AppError> {
+    if postings.is_empty() || postings.iter().any(|p| !p.amount.is_positive()) {
+        return Err(AppError::internal("invalid ledger posting"));
+    }
+    let total = |direction: Direction| {
+        postings
+            .iter()
+            .filter(|p| p.direction == direction)
+            .try_fold(Money::ZERO, |sum, p| sum.checked_add(p.amount))
+    };
+    match (total(Direction::Debit), total(Direction::Credit)) {
+        (Some(debits), Some(credits)) if debits == credits => {}
+        _ => return Err(AppError::internal("unbalanced ledger journal")),
+    }
+
+    let journal_id = Uuid::new_v4();
+    for posting in postings {
+        sqlx::query(
+            r#"
+            INSERT INTO ledger_entries (journal_id, transaction_id, account_type, account_id, direction, amount, description)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(journal_id)
+        .bind(transaction_id)
+        .bind(posting.account.account_type)
+        .bind(posting.account.account_id)
+        .bind(posting.direction)
+        .bind(posting.amount)
+        .bind(description)
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+        if let (AccountType::UserWallet, Some(user_id)) = (posting.account.account_type, posting.account.account_id) {
+            let delta = match posting.direction {
+                Direction::Credit => posting.amount,
+                Direction::Debit => -posting.amount,
+            };
+            apply_wallet_delta(tx, user_id, delta).await?;
+        }
+    }
+
+    Ok(journal_id)
+}
+
+async fn apply_wallet_delta(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    user_id: Uuid,
+    delta: Money,
+) -> Result<(), AppError> {
+    let balance: (Money,) = sqlx::query_as(
+        r#"
+        UPDATE users
+        SET balance = balance + $1, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $2
+        RETURNING balance
+        "#,
+    )
+    .bind(delta)
+    .bind(user_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    if balance.0 < Money::ZERO {
+        return Err(AppError::bad_request("insufficient balance"));
+    }
+    Ok(())
+}
+
+pub async fn record_payment(tx: &mut sqlx::Transaction<'_, Postgres>, transaction: &Transaction) -> Result<Uuid, AppError> {
+    let postings = payment_postings(transaction, LedgerAccount::user_wallet(transaction.user_id))?;
+    post(tx, Some(transaction.id), "payment", &postings).await
+}
+
+// A payment the PSP has not confirmed yet moves the payer's funds into a hold of its own until it settles.
+pub async fn hold_payment(tx: &mut sqlx::Transaction<'_, Postgres>, transaction: &Transaction) -> Result<Uuid, AppError> {
+    let payable = transaction.amount - transaction.discount_amount;
+    let postings = [
+        Posting::debit(LedgerAccount::user_wallet(transaction.user_id), payable),
+        Posting::credit(LedgerAccount::payment_hold(transaction.id), payable),
+    ];
+    post(tx, Some(transaction.id), "payment hold", &postings).await
+}
+
+pub async fn settle_hold(tx: &mut sqlx::Transaction<'_, Postgres>, transaction: &Transaction) -> Result<Uuid, AppError> {
+    let postings = payment_postings(transaction, LedgerAccount::payment_hold(transaction.id))?;
+    post(tx, Some(transaction.id), "payment", &postings).await
+}
+
+pub async fn reverse_hold(tx: &mut sqlx::Transaction<'_, Postgres>, transaction: &Transaction) -> Result<Uuid, AppError> {
+    let payable = transaction.amount - transaction.discount_amount;
+    let postings = [
+        Posting::debit(LedgerAccount::payment_hold(transaction.id), payable),
+        Posting::credit(LedgerAccount::user_wallet(transaction.user_id), payable),
+    ];
+    post(tx, Some(transaction.id), "payment hold reversal", &postings).await
+}
+
+fn payment_postings(transaction: &Transaction, source: LedgerAccount) -> Result<Vec<Posting>, AppError> {
+    let payee = match (transaction.merchant_id, transaction.payee_user_id) {
+        (Some(merchant_id), None) => LedgerAccount::merchant_payable(merchant_id),
+        (None, Some(payee_user_id)) => LedgerAccount::user_wallet(payee_user_id),
+        _ => return Err(AppError::internal("transaction has no payee")),
+    };
+
+    // A promo discount is paid for by the platform, so the payee still receives the full amount less fees.
+    let postings = [
+        Posting::debit(source, transaction.amount - transaction.discount_amount),
+        Posting::debit(LedgerAccount::rewards_expense(), transaction.discount_amount),
+        Posting::credit(payee, transaction.net_amount),
+        Posting::credit(LedgerAccount::fee_revenue(), transaction.fee_amount),
+    ];
+    Ok(postings.into_iter().filter(|p| p.amount.is_positive()).collect())
+}
+
+pub async fn balance(db: &PgPool, account: LedgerAccount) -> Result<Money, AppError> {
+    let (credits, debits): (Money, Money) = sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(SUM(amount) FILTER (WHERE direction = 'credit'), 0)::BIGINT,
+            COALESCE(SUM(amount) FILTER (WHERE direction = 'debit'), 0)::BIGINT
+        FROM ledger_entries
+        WHERE account_type = $1 AND account_id IS NOT DISTINCT FROM $2
+        "#,
+    )
+    .bind(account.account_type)
+    .bind(account.account_id)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    if account.account_type.is_credit_normal() {
+        Ok(credits - debits)
+    } else {
+        Ok(debits - credits)
+    }
+}
+
+pub async fn entries_for_transaction(db: &PgPool, transaction_id: Uuid) -> Result<Vec<LedgerEntry>, AppError> {
+    sqlx::query_as::<_, LedgerEntry>(
+        r#"
+        SELECT id, journal_id, transaction_id, account_type, account_id, direction, amount, description, created_at
+        FROM ledger_entries
+        WHERE transaction_id = $1
+        ORDER BY created_at, direction
+        "#,
+    )
+    .bind(transaction_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn wallet_mismatches(db: &PgPool) -> Result<Vec<WalletMismatch>, AppError> {
+    sqlx::query_as::<_, WalletMismatch>(
+        r#"
+        SELECT u.id AS user_id, u.balance, COALESCE(l.balance, 0)::BIGINT AS ledger_balance
+        FROM users u
+        LEFT JOIN (
+            SELECT account_id, SUM(CASE WHEN direction = 'credit' THEN amount ELSE -amount END) AS balance
+            FROM ledger_entries
+            WHERE account_type = 'user_wallet'
+            GROUP BY account_id
+        ) l ON l.account_id = u.id
+        WHERE u.balance <> COALESCE(l.balance, 0)
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
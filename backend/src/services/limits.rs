@@ -0,0 +1,213 @@
+use sqlx::{PgConnection, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::ledger::{AccountType, Direction};
+use crate::models::limits::{LimitsResponse, RemainingLimits, SpendingLimits, UserLimits, UserLimitsRequest};
+use crate::models::money::Money;
+
+pub async fn enforce(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    defaults: &SpendingLimits,
+    user_id: Uuid,
+    amount: Money,
+) -> Result<RemainingLimits, AppError> {
+    // Serialises concurrent payments by the same user so two debits cannot both fit under the same remaining limit.
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+    let limits = limits_for(tx, defaults, user_id).await?;
+    let remaining = remaining(tx, &limits, user_id).await?;
+
+    if amount > limits.per_transaction {
+        return Err(AppError::LimitExceeded {
+            code: "per_transaction_limit_exceeded",
+            message: format!("amount exceeds the per-transaction limit of {}", limits.per_transaction),
+            remaining: limits.per_transaction,
+        });
+    }
+    if amount > remaining.daily {
+        return Err(AppError::LimitExceeded {
+            code: "daily_limit_exceeded",
+            message: format!("amount exceeds the remaining daily limit of {}", remaining.daily),
+            remaining: remaining.daily,
+        });
+    }
+    if amount > remaining.monthly {
+        return Err(AppError::LimitExceeded {
+            code: "monthly_limit_exceeded",
+            message: format!("amount exceeds the remaining monthly limit of {}", remaining.monthly),
+            remaining: remaining.monthly,
+        });
+    }
+
+    Ok(RemainingLimits {
+        daily: remaining.daily - amount,
+        monthly: remaining.monthly - amount,
+    })
+}
+
+pub async fn get_limits(db: &PgPool, defaults: &SpendingLimits, user_id: Uuid) -> Result<LimitsResponse, AppError> {
+    let mut conn = db.acquire().await.map_err(AppError::from_sqlx)?;
+    let limits = limits_for(&mut conn, defaults, user_id).await?;
+    let remaining = remaining(&mut conn, &limits, user_id).await?;
+    Ok(LimitsResponse { limits, remaining })
+}
+
+pub async fn set_user_limits(
+    db: &PgPool,
+    defaults: &SpendingLimits,
+    user_id: Uuid,
+    req: UserLimitsRequest,
+) -> Result<LimitsResponse, AppError> {
+    validate(&req)?;
+
+    let updated = sqlx::query(
+        r#"
+        INSERT INTO user_limits (user_id, per_transaction, daily, monthly)
+        SELECT id, $2, $3, $4 FROM users WHERE id = $1
+        ON CONFLICT (user_id) DO UPDATE
+        SET per_transaction = EXCLUDED.per_transaction, daily = EXCLUDED.daily, monthly = EXCLUDED.monthly,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(user_id)
+    .bind(req.per_transaction)
+    .bind(req.daily)
+    .bind(req.monthly)
+    .execute(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    if updated.rows_affected() == 0 {
+        return Err(AppError::NotFound("user not found".to_string()));
+    }
+
+    get_limits(db, defaults, user_id).await
+}
+
+// Self-service changes may only tighten a limit; a field left out keeps whatever applies today.
+pub async fn lower_limits(
+    db: &PgPool,
+    defaults: &SpendingLimits,
+    user_id: Uuid,
+    req: UserLimitsRequest,
+) -> Result<LimitsResponse, AppError> {
+    validate(&req)?;
+
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    let current = limits_for(&mut tx, defaults, user_id).await?;
+    let raises = [
+        (req.per_transaction, current.per_transaction),
+        (req.daily, current.daily),
+        (req.monthly, current.monthly),
+    ]
+    .iter()
+    .any(|(requested, current)| requested.is_some_and(|limit| limit > *current));
+    if raises {
+        return Err(AppError::forbidden("limits can only be lowered; contact support to raise them"));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_limits (user_id, per_transaction, daily, monthly)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET per_transaction = COALESCE(EXCLUDED.per_transaction, user_limits.per_transaction),
+            daily = COALESCE(EXCLUDED.daily, user_limits.daily),
+            monthly = COALESCE(EXCLUDED.monthly, user_limits.monthly),
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(user_id)
+    .bind(req.per_transaction)
+    .bind(req.daily)
+    .bind(req.monthly)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+
+    get_limits(db, defaults, user_id).await
+}
+
+fn validate(req: &UserLimitsRequest) -> Result<(), AppError> {
+    if [req.per_transaction, req.daily, req.monthly]
+        .iter()
+        .flatten()
+        .any(|limit| !limit.is_positive())
+    {
+        return Err(AppError::bad_request("limits must be greater than 0"));
+    }
+    Ok(())
+}
+
+async fn limits_for(
+    conn: &mut PgConnection,
+    defaults: &SpendingLimits,
+    user_id: Uuid,
+) -> Result<SpendingLimits, AppError> {
+    let overrides = sqlx::query_as::<_, UserLimits>(
+        r#"
+        SELECT per_transaction, daily, monthly
+        FROM user_limits
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    Ok(match overrides {
+        Some(o) => SpendingLimits {
+            per_transaction: o.per_transaction.unwrap_or(defaults.per_transaction),
+            daily: o.daily.unwrap_or(defaults.daily),
+            monthly: o.monthly.unwrap_or(defaults.monthly),
+        },
+        None => *defaults,
+    })
+}
+
+// Spend is the payer's wallet debits for their own payments this calendar day and month. Refunds do not restore it,
+// but a payment the PSP failed after its funds were held does not count, and neither does clawed-back cashback.
+async fn remaining(
+    conn: &mut PgConnection,
+    limits: &SpendingLimits,
+    user_id: Uuid,
+) -> Result<RemainingLimits, AppError> {
+    let (today, this_month): (Money, Money) = sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(SUM(le.amount) FILTER (WHERE le.created_at >= date_trunc('day', LOCALTIMESTAMP)), 0)::BIGINT,
+            COALESCE(SUM(le.amount), 0)::BIGINT
+        FROM ledger_entries le
+        JOIN transactions t ON t.id = le.transaction_id
+        WHERE le.account_type = $2 AND le.account_id = $1 AND le.direction = $3 AND t.user_id = $1 AND t.status <> 'failed'
+          AND le.created_at >= date_trunc('month', LOCALTIMESTAMP)
+          AND NOT EXISTS (
+              SELECT 1 FROM ledger_entries r WHERE r.journal_id = le.journal_id AND r.account_type = $4
+          )
+        "#,
+    )
+    .bind(user_id)
+    .bind(AccountType::UserWallet)
+    .bind(Direction::Debit)
+    .bind(AccountType::RewardsExpense)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    Ok(RemainingLimits {
+        daily: (limits.daily - today).max(Money::ZERO),
+        monthly: (limits.monthly - this_month).max(Money::ZERO),
+    })
+}
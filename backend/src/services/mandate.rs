@@ -0,0 +1,494 @@
+use chrono::{Days, Duration, Months, NaiveDate, NaiveDateTime, Utc};
+use sqlx::{Acquire, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::cache::redis_client::RedisClient;
+use crate::handlers::errors::AppError;
+use crate::models::limits::SpendingLimits;
+use crate::models::mandate::{
+    ApproveMandateRequest, CreateMandateRequest, Mandate, MandateExecution, MandateExecutionStatus, MandateFrequency,
+    MandateRun, MandateStatus, UpdateMandateRequest,
+};
+use crate::models::money::Money;
+use crate::models::payment::{Transaction, TransactionStatus};
+use crate::services::{merchant, payee, payment};
+use crate::utils::upi_client::UpiClient;
+
+// Debits only go out once the payer has had this much notice of the amount and date.
+const PRE_DEBIT_NOTICE_HOURS: i64 = 24;
+
+pub async fn create_mandate(
+    db: &PgPool,
+    redis: &RedisClient,
+    merchant_id: Uuid,
+    req: CreateMandateRequest,
+) -> Result<Mandate, AppError> {
+    if !req.amount.is_positive() {
+        return Err(AppError::bad_request("amount must be greater than 0"));
+    }
+    if req.amount > req.max_amount {
+        return Err(AppError::bad_request("amount must not exceed max_amount"));
+    }
+    if req.start_date < Utc::now().date_naive() {
+        return Err(AppError::bad_request("start_date must not be in the past"));
+    }
+    if req.end_date.is_some_and(|end| end < req.start_date) {
+        return Err(AppError::bad_request("end_date must not be before start_date"));
+    }
+
+    let merchant = merchant::get_merchant_by_id(db, redis, merchant_id).await?;
+    let handle = req.payer.trim().to_lowercase();
+    let payer = payee::get_user_by_vpa(db, &handle)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("payer {} not found", handle)))?;
+
+    sqlx::query_as::<_, Mandate>(
+        r#"
+        INSERT INTO mandates (user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date,
+                              next_debit_date)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $7)
+        RETURNING id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+                  next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        "#,
+    )
+    .bind(payer.id)
+    .bind(merchant.id)
+    .bind(&req.description)
+    .bind(req.amount)
+    .bind(req.max_amount)
+    .bind(req.frequency)
+    .bind(req.start_date)
+    .bind(req.end_date)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+// A new amount applies from the next pre-debit notification; a cycle already notified keeps its amount.
+pub async fn update_amount(
+    db: &PgPool,
+    merchant_id: Uuid,
+    mandate_id: Uuid,
+    req: UpdateMandateRequest,
+) -> Result<Mandate, AppError> {
+    if !req.amount.is_positive() {
+        return Err(AppError::bad_request("amount must be greater than 0"));
+    }
+
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let mandate = sqlx::query_as::<_, Mandate>(
+        r#"
+        SELECT id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+               next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        FROM mandates
+        WHERE id = $1 AND merchant_id = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(mandate_id)
+    .bind(merchant_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("mandate not found".to_string()))?;
+
+    if !matches!(mandate.status, MandateStatus::Pending | MandateStatus::Active) {
+        return Err(AppError::Conflict("mandate is no longer active".to_string()));
+    }
+    if req.amount > mandate.max_amount {
+        return Err(AppError::bad_request(format!(
+            "amount must not exceed the approved maximum of {}",
+            mandate.max_amount
+        )));
+    }
+
+    let mandate = sqlx::query_as::<_, Mandate>(
+        r#"
+        UPDATE mandates
+        SET amount = $1, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $2
+        RETURNING id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+                  next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        "#,
+    )
+    .bind(req.amount)
+    .bind(mandate.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(mandate)
+}
+
+pub async fn mandates_for_user(db: &PgPool, user_id: Uuid) -> Result<Vec<Mandate>, AppError> {
+    sqlx::query_as::<_, Mandate>(
+        r#"
+        SELECT id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+               next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        FROM mandates
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn approve(
+    db: &PgPool,
+    user_id: Uuid,
+    mandate_id: Uuid,
+    req: ApproveMandateRequest,
+) -> Result<Mandate, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let mandate = lock_for_user(&mut tx, user_id, mandate_id).await?;
+    if mandate.status != MandateStatus::Pending {
+        return Err(AppError::Conflict("mandate is not awaiting approval".to_string()));
+    }
+
+    payment::verify_pin(&mut tx, user_id, &req.pin).await?;
+
+    // Approving after the start date skips the cycles that are already in the past.
+    let today = Utc::now().date_naive();
+    let mut cycle = 0;
+    let mut due_date = mandate.start_date;
+    while due_date < today {
+        cycle += 1;
+        due_date = cycle_date(&mandate, cycle)?;
+    }
+    if mandate.end_date.is_some_and(|end| due_date > end) {
+        return Err(AppError::bad_request("mandate has already ended"));
+    }
+
+    let mandate = sqlx::query_as::<_, Mandate>(
+        r#"
+        UPDATE mandates
+        SET status = 'active', approved_at = CURRENT_TIMESTAMP, next_cycle = $1, next_debit_date = $2,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $3
+        RETURNING id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+                  next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        "#,
+    )
+    .bind(cycle)
+    .bind(due_date)
+    .bind(mandate.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(mandate)
+}
+
+// Revoking a pending mandate declines it. A cycle that was notified but not yet debited is cancelled.
+pub async fn revoke(db: &PgPool, user_id: Uuid, mandate_id: Uuid) -> Result<Mandate, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let mandate = lock_for_user(&mut tx, user_id, mandate_id).await?;
+    if !matches!(mandate.status, MandateStatus::Pending | MandateStatus::Active) {
+        return Err(AppError::Conflict("mandate is no longer active".to_string()));
+    }
+
+    let mandate = sqlx::query_as::<_, Mandate>(
+        r#"
+        UPDATE mandates
+        SET status = 'revoked', revoked_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        RETURNING id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+                  next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        "#,
+    )
+    .bind(mandate.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    sqlx::query(
+        r#"
+        UPDATE mandate_executions
+        SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP
+        WHERE mandate_id = $1 AND status = 'notified'
+        "#,
+    )
+    .bind(mandate.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(mandate)
+}
+
+pub async fn history(db: &PgPool, user_id: Uuid, mandate_id: Uuid) -> Result<Vec<MandateExecution>, AppError> {
+    let owned: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM mandates WHERE id = $1 AND user_id = $2)")
+        .bind(mandate_id)
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    if !owned {
+        return Err(AppError::NotFound("mandate not found".to_string()));
+    }
+
+    sqlx::query_as::<_, MandateExecution>(
+        r#"
+        SELECT id, mandate_id, cycle, due_date, amount, status, transaction_id, error_message, notified_at, executed_at
+        FROM mandate_executions
+        WHERE mandate_id = $1
+        ORDER BY cycle DESC
+        "#,
+    )
+    .bind(mandate_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+// One scheduler pass: debit every cycle whose notice period has run out, then send pre-debit notifications
+// for cycles falling due within the notice period. Safe to run concurrently and repeatedly.
+pub async fn run_due(
+    db: &PgPool,
+    upi: &UpiClient,
+    spending_limits: &SpendingLimits,
+    now: NaiveDateTime,
+) -> Result<MandateRun, AppError> {
+    let mut run = MandateRun::default();
+
+    let due: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT m.id
+        FROM mandates m
+        JOIN mandate_executions e ON e.mandate_id = m.id AND e.cycle = m.next_cycle
+        WHERE m.status = 'active' AND m.next_debit_date <= $1 AND e.status = 'notified' AND e.notified_at <= $2
+        ORDER BY m.next_debit_date
+        "#,
+    )
+    .bind(now.date())
+    .bind(now - Duration::hours(PRE_DEBIT_NOTICE_HOURS))
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    for mandate_id in due {
+        match debit_cycle(db, upi, spending_limits, mandate_id, now).await? {
+            Some(MandateExecutionStatus::Success | MandateExecutionStatus::Pending) => run.debited += 1,
+            Some(_) => run.failed += 1,
+            None => {}
+        }
+    }
+
+    let notified: Vec<(Uuid, NaiveDate, Money)> = sqlx::query_as(
+        r#"
+        INSERT INTO mandate_executions (mandate_id, cycle, due_date, amount, notified_at)
+        SELECT id, next_cycle, next_debit_date, amount, $2
+        FROM mandates
+        WHERE status = 'active' AND next_debit_date <= $1
+        ON CONFLICT (mandate_id, cycle) DO NOTHING
+        RETURNING mandate_id, due_date, amount
+        "#,
+    )
+    .bind((now + Duration::hours(PRE_DEBIT_NOTICE_HOURS)).date())
+    .bind(now)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    for (mandate_id, due_date, amount) in &notified {
+        log::info!("pre-debit notification: mandate {} will debit {} on {}", mandate_id, amount, due_date);
+    }
+    run.notified = notified.len();
+
+    Ok(run)
+}
+
+// Debits the mandate's current cycle through the payment service. The debit runs in a savepoint so a
+// failure still records the failed cycle and moves the mandate on; the cycle is never attempted twice.
+async fn debit_cycle(
+    db: &PgPool,
+    upi: &UpiClient,
+    spending_limits: &SpendingLimits,
+    mandate_id: Uuid,
+    now: NaiveDateTime,
+) -> Result<Option<MandateExecutionStatus>, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+
+    let mandate = sqlx::query_as::<_, Mandate>(
+        r#"
+        SELECT id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+               next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        FROM mandates
+        WHERE id = $1 AND status = 'active'
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(mandate_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    let Some(mandate) = mandate else {
+        return Ok(None);
+    };
+
+    let execution = sqlx::query_as::<_, MandateExecution>(
+        r#"
+        SELECT id, mandate_id, cycle, due_date, amount, status, transaction_id, error_message, notified_at, executed_at
+        FROM mandate_executions
+        WHERE mandate_id = $1 AND cycle = $2 AND status = 'notified'
+        FOR UPDATE
+        "#,
+    )
+    .bind(mandate.id)
+    .bind(mandate.next_cycle)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    let Some(execution) = execution else {
+        return Ok(None);
+    };
+
+    let transaction = insert_transaction(&mut tx, &mandate, &execution).await?;
+    let transaction_id = transaction.id;
+    let mut debit = tx.begin().await.map_err(AppError::from_sqlx)?;
+    let (status, error_message) = match payment::complete_payment(&mut debit, upi, spending_limits, transaction).await {
+        Ok(response) => {
+            debit.commit().await.map_err(AppError::from_sqlx)?;
+            let status = if response.status == "pending" {
+                MandateExecutionStatus::Pending
+            } else {
+                MandateExecutionStatus::Success
+            };
+            (status, None)
+        }
+        Err(e) => {
+            debit.rollback().await.map_err(AppError::from_sqlx)?;
+            let error_message = e.to_string();
+            payment::mark_failed(&mut *tx, transaction_id, &error_message).await?;
+            (MandateExecutionStatus::Failed, Some(error_message))
+        }
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE mandate_executions
+        SET status = $1, transaction_id = $2, error_message = $3, executed_at = $4, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $5
+        "#,
+    )
+    .bind(status)
+    .bind(transaction_id)
+    .bind(&error_message)
+    .bind(now)
+    .bind(execution.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let next_cycle = mandate.next_cycle + 1;
+    let next_debit_date = cycle_date(&mandate, next_cycle)?;
+    let finished = mandate.end_date.is_some_and(|end| next_debit_date > end);
+    sqlx::query(
+        r#"
+        UPDATE mandates
+        SET next_cycle = $1, next_debit_date = $2, status = $3, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $4
+        "#,
+    )
+    .bind(next_cycle)
+    .bind(next_debit_date)
+    .bind(if finished { MandateStatus::Completed } else { MandateStatus::Active })
+    .bind(mandate.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    if let Some(error_message) = error_message {
+        log::warn!("mandate {} cycle {} failed: {}", mandate.id, execution.cycle, error_message);
+    }
+    Ok(Some(status))
+}
+
+// Carries the final outcome of a debit the PSP left pending over to its execution.
+pub async fn settle_execution(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transaction_id: Uuid,
+    status: MandateExecutionStatus,
+    error_message: Option<&str>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE mandate_executions
+        SET status = $1, error_message = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE transaction_id = $3 AND status = 'pending'
+        "#,
+    )
+    .bind(status)
+    .bind(error_message)
+    .bind(transaction_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    Ok(())
+}
+
+// A failed debit is still recorded as a transaction so it shows up in the payer's history. It is inserted
+// ahead of the debit, so whatever happens it keeps the reference the PSP was given.
+async fn insert_transaction(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    mandate: &Mandate,
+    execution: &MandateExecution,
+) -> Result<Transaction, AppError> {
+    sqlx::query_as::<_, Transaction>(
+        r#"
+        INSERT INTO transactions (user_id, merchant_id, amount, status, idempotency_key)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
+        "#,
+    )
+    .bind(mandate.user_id)
+    .bind(mandate.merchant_id)
+    .bind(execution.amount)
+    .bind(TransactionStatus::Initiated)
+    .bind(format!("mandate:{}:{}", mandate.id, execution.cycle))
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+async fn lock_for_user(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    user_id: Uuid,
+    mandate_id: Uuid,
+) -> Result<Mandate, AppError> {
+    sqlx::query_as::<_, Mandate>(
+        r#"
+        SELECT id, user_id, merchant_id, description, amount, max_amount, frequency, start_date, end_date, status,
+               next_cycle, next_debit_date, approved_at, revoked_at, created_at, updated_at
+        FROM mandates
+        WHERE id = $1 AND user_id = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(mandate_id)
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("mandate not found".to_string()))
+}
+
+// Cycles are counted from the start date rather than the previous debit, so monthly mandates starting
+// on the 31st fall on the last day of shorter months without drifting.
+fn cycle_date(mandate: &Mandate, cycle: i32) -> Result<NaiveDate, AppError> {
+    let n = cycle as u32;
+    match mandate.frequency {
+        MandateFrequency::Daily => mandate.start_date.checked_add_days(Days::new(n as u64)),
+        MandateFrequency::Weekly => mandate.start_date.checked_add_days(Days::new(7 * n as u64)),
+        MandateFrequency::Monthly => mandate.start_date.checked_add_months(Months::new(n)),
+        MandateFrequency::Yearly => mandate.start_date.checked_add_months(Months::new(12 * n)),
+    }
+    .ok_or_else(|| AppError::internal("mandate schedule out of range"))
+}
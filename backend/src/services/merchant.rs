@@ -1,17 +1,152 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::cache::redis_client::RedisClient;
 use crate::handlers::errors::AppError;
-use crate::models::merchant::Merchant;
+use crate::models::merchant::{Merchant, MerchantKey, MerchantQrQuery, RegisterMerchantKeyRequest};
+use crate::utils::emv_qr::{self, EmvQr};
+use crate::utils::intent_signature;
+use crate::utils::upi_intent::UpiIntent;
+
+pub fn parse_qr(qr_data: &str) -> Result<UpiIntent, AppError> {
+    if emv_qr::is_emv_payload(qr_data) {
+        return EmvQr::parse(qr_data)
+            .and_then(EmvQr::into_upi_intent)
+            .map_err(|e| AppError::bad_request(e.to_string()));
+    }
+    UpiIntent::parse(qr_data).map_err(|e| AppError::bad_request(e.to_string()))
+}
 
 pub async fn get_merchant_by_qr(db: &PgPool, redis: &RedisClient, qr_data: &str) -> Result<Merchant, AppError> {
-    let cache_key = format!("merchant:qr:{}", qr_data);
+    let intent = parse_qr(qr_data)?;
+    let mut merchant = get_merchant_by_vpa(db, redis, &intent.payee_address).await?;
+    merchant.verified = verify_intent(db, merchant.id, &intent).await?;
+    Ok(merchant)
+}
+
+pub async fn verify_intent(db: &PgPool, merchant_id: Uuid, intent: &UpiIntent) -> Result<bool, AppError> {
+    let Some(signature) = &intent.signature else {
+        return Ok(false);
+    };
+
+    let public_keys: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT public_key
+        FROM merchant_keys
+        WHERE merchant_id = $1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(merchant_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    if public_keys.is_empty() {
+        return Ok(false);
+    }
+
+    match intent_signature::verify(&intent.signed_payload(), signature, &public_keys) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(AppError::bad_request("qr signature does not match the merchant")),
+        Err(e) => Err(AppError::bad_request(e.to_string())),
+    }
+}
+
+// Merchant self-service is for the user who owns the merchant; admins can act for any merchant.
+pub async fn require_owner(db: &PgPool, merchant_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+    let allowed: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM merchants WHERE id = $1 AND owner_id = $2)
+            OR EXISTS (SELECT 1 FROM users WHERE id = $2 AND role = 'admin')
+        "#,
+    )
+    .bind(merchant_id)
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    if !allowed {
+        return Err(AppError::forbidden("not permitted for this merchant"));
+    }
+    Ok(())
+}
+
+// Keys are stored in standard base64 whatever encoding they were submitted in.
+pub async fn register_key(
+    db: &PgPool,
+    merchant_id: Uuid,
+    req: RegisterMerchantKeyRequest,
+) -> Result<MerchantKey, AppError> {
+    let key = intent_signature::decode_public_key(&req.public_key).map_err(|e| AppError::bad_request(e.to_string()))?;
+    let public_key = STANDARD.encode(key.to_bytes());
+
+    let active: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM merchant_keys WHERE merchant_id = $1 AND public_key = $2 AND revoked_at IS NULL)",
+    )
+    .bind(merchant_id)
+    .bind(&public_key)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    if active {
+        return Err(AppError::Conflict("key is already registered".to_string()));
+    }
+
+    sqlx::query_as::<_, MerchantKey>(
+        r#"
+        INSERT INTO merchant_keys (merchant_id, public_key)
+        VALUES ($1, $2)
+        RETURNING id, merchant_id, public_key, created_at, revoked_at
+        "#,
+    )
+    .bind(merchant_id)
+    .bind(public_key)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn list_keys(db: &PgPool, merchant_id: Uuid) -> Result<Vec<MerchantKey>, AppError> {
+    sqlx::query_as::<_, MerchantKey>(
+        r#"
+        SELECT id, merchant_id, public_key, created_at, revoked_at
+        FROM merchant_keys
+        WHERE merchant_id = $1
+        ORDER BY created_at
+        "#,
+    )
+    .bind(merchant_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn revoke_key(db: &PgPool, merchant_id: Uuid, key_id: Uuid) -> Result<MerchantKey, AppError> {
+    sqlx::query_as::<_, MerchantKey>(
+        r#"
+        UPDATE merchant_keys
+        SET revoked_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND merchant_id = $2 AND revoked_at IS NULL
+        RETURNING id, merchant_id, public_key, created_at, revoked_at
+        "#,
+    )
+    .bind(key_id)
+    .bind(merchant_id)
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("active key not found".to_string()))
+}
+
+pub async fn get_merchant_by_vpa(db: &PgPool, redis: &RedisClient, vpa: &str) -> Result<Merchant, AppError> {
+    let cache_key = format!("merchant:vpa:{}", vpa);
 
     if let Some(merchant) = redis
         .get::<Merchant>(&cache_key)
         .await
-        .map_err(|e| AppError::internal(e))?
+        .map_err(AppError::internal)?
     {
         return Ok(merchant);
     }
@@ -20,10 +155,10 @@ pub async fn get_merchant_by_qr(db: &PgPool, redis: &RedisClient, qr_data: &str)
         r#"
         SELECT id, name, upi_id, category, address, phone, qr_code_data, created_at
         FROM merchants
-        WHERE qr_code_data = $1
+        WHERE LOWER(upi_id) = $1
         "#,
     )
-    .bind(qr_data)
+    .bind(vpa)
     .fetch_one(db)
     .await
     .map_err(AppError::from_sqlx)?;
@@ -31,7 +166,7 @@ pub async fn get_merchant_by_qr(db: &PgPool, redis: &RedisClient, qr_data: &str)
     redis
         .set(&cache_key, &merchant, 3600)
         .await
-        .map_err(|e| AppError::internal(e))?;
+        .map_err(AppError::internal)?;
 
     Ok(merchant)
 }
@@ -42,7 +177,7 @@ pub async fn get_merchant_by_id(db: &PgPool, redis: &RedisClient, merchant_id: U
     if let Some(merchant) = redis
         .get::<Merchant>(&cache_key)
         .await
-        .map_err(|e| AppError::internal(e))?
+        .map_err(AppError::internal)?
     {
         return Ok(merchant);
     }
@@ -62,8 +197,30 @@ pub async fn get_merchant_by_id(db: &PgPool, redis: &RedisClient, merchant_id: U
     redis
         .set(&cache_key, &merchant, 3600)
         .await
-        .map_err(|e| AppError::internal(e))?;
+        .map_err(AppError::internal)?;
 
     Ok(merchant)
 }
 
+pub async fn merchant_qr_payload(
+    db: &PgPool,
+    redis: &RedisClient,
+    merchant_id: Uuid,
+    query: &MerchantQrQuery,
+) -> Result<String, AppError> {
+    if query.amount.is_some_and(|amount| !amount.is_positive()) {
+        return Err(AppError::bad_request("amount must be greater than 0"));
+    }
+
+    let merchant = get_merchant_by_id(db, redis, merchant_id).await?;
+
+    let mut intent = UpiIntent::for_payee(&merchant.upi_id, &merchant.name);
+    if let Some(amount) = query.amount {
+        intent.amount = Some(amount);
+        intent.currency = Some("INR".to_string());
+    }
+    intent.transaction_note = query.note.clone();
+    intent.transaction_ref = query.reference.clone();
+
+    Ok(intent.to_uri())
+}
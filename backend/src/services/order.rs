@@ -0,0 +1,173 @@
+use chrono::{Duration, Utc};
+use sqlx::{PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::cache::redis_client::RedisClient;
+use crate::handlers::errors::AppError;
+use crate::models::merchant::Merchant;
+use crate::models::order::{CreateOrderRequest, MerchantOrder, OrderResponse, OrderStatus};
+use crate::services::merchant;
+use crate::utils::upi_intent::UpiIntent;
+
+const DEFAULT_ORDER_TTL_SECS: i64 = 15 * 60;
+const MAX_ORDER_TTL_SECS: i64 = 24 * 60 * 60;
+
+pub async fn create_order(
+    db: &PgPool,
+    redis: &RedisClient,
+    merchant_id: Uuid,
+    req: CreateOrderRequest,
+) -> Result<OrderResponse, AppError> {
+    if !req.amount.is_positive() {
+        return Err(AppError::bad_request("amount must be greater than 0"));
+    }
+    let ttl = req.expires_in_secs.unwrap_or(DEFAULT_ORDER_TTL_SECS);
+    if !(1..=MAX_ORDER_TTL_SECS).contains(&ttl) {
+        return Err(AppError::bad_request(format!(
+            "expires_in_secs must be between 1 and {}",
+            MAX_ORDER_TTL_SECS
+        )));
+    }
+
+    let merchant = merchant::get_merchant_by_id(db, redis, merchant_id).await?;
+    let transaction_ref = format!("ORD{}", Uuid::new_v4().simple()).to_uppercase();
+    let expires_at = Utc::now().naive_utc() + Duration::seconds(ttl);
+
+    let order = sqlx::query_as::<_, MerchantOrder>(
+        r#"
+        INSERT INTO merchant_orders (merchant_id, transaction_ref, amount, note, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, merchant_id, transaction_ref, amount, note, expires_at, paid_at, transaction_id, created_at
+        "#,
+    )
+    .bind(merchant.id)
+    .bind(&transaction_ref)
+    .bind(req.amount)
+    .bind(&req.note)
+    .bind(expires_at)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    Ok(order_response(&merchant, order))
+}
+
+pub async fn get_order(
+    db: &PgPool,
+    redis: &RedisClient,
+    merchant_id: Uuid,
+    order_id: Uuid,
+) -> Result<OrderResponse, AppError> {
+    let merchant = merchant::get_merchant_by_id(db, redis, merchant_id).await?;
+    let order = sqlx::query_as::<_, MerchantOrder>(
+        r#"
+        SELECT id, merchant_id, transaction_ref, amount, note, expires_at, paid_at, transaction_id, created_at
+        FROM merchant_orders
+        WHERE id = $1 AND merchant_id = $2
+        "#,
+    )
+    .bind(order_id)
+    .bind(merchant.id)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    Ok(order_response(&merchant, order))
+}
+
+// Orders are looked up by the tr of the scanned intent; a tr that matches no order is a plain static-QR reference.
+pub async fn find_payable_order(
+    db: &PgPool,
+    merchant_id: Uuid,
+    transaction_ref: &str,
+) -> Result<Option<MerchantOrder>, AppError> {
+    let order = sqlx::query_as::<_, MerchantOrder>(
+        r#"
+        SELECT id, merchant_id, transaction_ref, amount, note, expires_at, paid_at, transaction_id, created_at
+        FROM merchant_orders
+        WHERE merchant_id = $1 AND transaction_ref = $2
+        "#,
+    )
+    .bind(merchant_id)
+    .bind(transaction_ref)
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    match order {
+        Some(order) => match order_status(&order) {
+            OrderStatus::Open => Ok(Some(order)),
+            OrderStatus::Paid => Err(AppError::Conflict("order has already been paid".to_string())),
+            OrderStatus::Expired => Err(AppError::bad_request("order has expired")),
+        },
+        None => Ok(None),
+    }
+}
+
+pub async fn claim_order(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    order_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<(), AppError> {
+    let claimed = sqlx::query(
+        r#"
+        UPDATE merchant_orders
+        SET paid_at = $1, transaction_id = $2
+        WHERE id = $3 AND paid_at IS NULL AND expires_at > $1
+        "#,
+    )
+    .bind(Utc::now().naive_utc())
+    .bind(transaction_id)
+    .bind(order_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    if claimed.rows_affected() == 0 {
+        return Err(AppError::Conflict("order has expired or was already paid".to_string()));
+    }
+    Ok(())
+}
+
+// Frees an order claimed by a payment that later failed, so it can be paid again until it expires.
+pub async fn release_order(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    order_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query("UPDATE merchant_orders SET paid_at = NULL, transaction_id = NULL WHERE id = $1 AND transaction_id = $2")
+        .bind(order_id)
+        .bind(transaction_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    Ok(())
+}
+
+pub fn order_status(order: &MerchantOrder) -> OrderStatus {
+    if order.paid_at.is_some() {
+        OrderStatus::Paid
+    } else if order.expires_at <= Utc::now().naive_utc() {
+        OrderStatus::Expired
+    } else {
+        OrderStatus::Open
+    }
+}
+
+fn order_response(merchant: &Merchant, order: MerchantOrder) -> OrderResponse {
+    let mut intent = UpiIntent::for_payee(&merchant.upi_id, &merchant.name);
+    intent.amount = Some(order.amount);
+    intent.currency = Some("INR".to_string());
+    intent.transaction_ref = Some(order.transaction_ref.clone());
+    intent.transaction_note = order.note.clone();
+
+    OrderResponse {
+        order_id: order.id,
+        status: order_status(&order),
+        transaction_ref: order.transaction_ref,
+        amount: order.amount,
+        expires_at: order.expires_at,
+        transaction_id: order.transaction_id,
+        qr_data: intent.to_uri(),
+    }
+}
@@ -0,0 +1,41 @@
+use sqlx::PgPool;
+
+use crate::cache::redis_client::RedisClient;
+use crate::handlers::errors::AppError;
+use crate::models::payee::Payee;
+use crate::models::user::User;
+use crate::services::merchant;
+
+pub async fn resolve_payee(db: &PgPool, redis: &RedisClient, vpa: &str) -> Result<Payee, AppError> {
+    match merchant::get_merchant_by_vpa(db, redis, vpa).await {
+        Ok(merchant) => return Ok(Payee::Merchant(merchant)),
+        Err(AppError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    if let Some(user) = get_user_by_vpa(db, vpa).await? {
+        return Ok(Payee::User(user));
+    }
+    Err(AppError::NotFound("payee not found".to_string()))
+}
+
+// Mobile-number VPAs (9876543210@upi) fall back to the phone number when no user registered that exact VPA.
+pub async fn get_user_by_vpa(db: &PgPool, vpa: &str) -> Result<Option<User>, AppError> {
+    let handle = vpa.split_once('@').map(|(handle, _)| handle).unwrap_or(vpa);
+    let phone_number = (handle.len() == 10 && handle.chars().all(|c| c.is_ascii_digit())).then_some(handle);
+
+    sqlx::query_as::<_, User>(
+        r#"
+        SELECT id, phone_number, upi_id, name, balance, pin_hash, created_at, updated_at
+        FROM users
+        WHERE LOWER(upi_id) = $1 OR phone_number = $2
+        ORDER BY LOWER(upi_id) = $1 DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(vpa)
+    .bind(phone_number)
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
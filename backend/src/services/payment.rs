@@ -1,14 +1,31 @@
+use chrono::Utc;
 use sqlx::{PgPool, Postgres};
 use uuid::Uuid;
 
 use crate::cache::redis_client::RedisClient;
 use crate::handlers::errors::AppError;
+use crate::models::mandate::MandateExecutionStatus;
 use crate::models::payment::{
-    MerchantInfo, PaymentExecuteRequest, PaymentExecuteResponse, PaymentInitRequest, PaymentInitResponse, Transaction,
-    TransactionStatus,
+    PayeeInfo, PaymentExecuteRequest, PaymentExecuteResponse, PaymentInitRequest, PaymentInitResponse, PspCallback,
+    PspCallbackResponse, Transaction, TransactionStatus,
 };
+use crate::models::limits::SpendingLimits;
+use crate::models::money::Money;
+use crate::models::payee::{Payee, PayeeType};
 use crate::models::user::User;
-use crate::services::merchant;
+use crate::services::{fee, ledger, limits, mandate, merchant, order, payee, promo, reward, split};
+use crate::utils::upi_client::{self, PspConfig, PspPayment, PspStatus, UpiClient, UpiError};
+use crate::utils::upi_intent::UpiIntent;
+
+const SUPPORTED_CURRENCY: &str = "INR";
+const SESSION_EXPIRED_MESSAGE: &str = "payment session expired";
+const CALLBACK_TOLERANCE_SECS: i64 = 5 * 60;
+
+pub struct PayableAmount {
+    pub amount: Money,
+    pub editable: bool,
+    pub min_amount: Option<Money>,
+}
 
 pub async fn initiate_payment(
     db: &PgPool,
@@ -16,7 +33,7 @@ pub async fn initiate_payment(
     user_id: Uuid,
     req: PaymentInitRequest,
 ) -> Result<PaymentInitResponse, AppError> {
-    if req.amount <= 0.0 {
+    if req.amount.is_some_and(|amount| !amount.is_positive()) {
         return Err(AppError::bad_request("amount must be greater than 0"));
     }
 
@@ -24,25 +41,61 @@ pub async fn initiate_payment(
     if let Some(existing) = redis
         .get::<PaymentInitResponse>(&cache_key)
         .await
-        .map_err(|e| AppError::internal(e))?
+        .map_err(AppError::internal)?
     {
         return Ok(existing);
     }
 
-    let merchant = merchant::get_merchant_by_qr(db, redis, &req.qr_data).await?;
+    let intent = merchant::parse_qr(&req.qr_data)?;
+    let payable = payable_amount(&intent, req.amount)?;
+    let payee = payee::resolve_payee(db, redis, &intent.payee_address).await?;
+
+    let (verified, order) = match &payee {
+        Payee::Merchant(m) => {
+            let verified = merchant::verify_intent(db, m.id, &intent).await?;
+            let order = match &intent.transaction_ref {
+                Some(transaction_ref) => order::find_payable_order(db, m.id, transaction_ref).await?,
+                None => None,
+            };
+            (verified, order)
+        }
+        Payee::User(u) if u.id == user_id => return Err(AppError::bad_request("cannot pay yourself")),
+        Payee::User(_) => (false, None),
+    };
+    if order.as_ref().is_some_and(|o| o.amount != payable.amount) {
+        return Err(AppError::bad_request("amount does not match the order"));
+    }
+    let promo = match (req.promo_code.as_deref().filter(|c| !c.trim().is_empty()), &payee) {
+        (None, _) => None,
+        (Some(code), Payee::Merchant(m)) => Some(promo::apply(db, code, user_id, m.id, payable.amount).await?),
+        (Some(_), Payee::User(_)) => {
+            return Err(AppError::bad_request("promo codes can only be used for merchant payments"))
+        }
+    };
 
     let created: Result<Transaction, sqlx::Error> = sqlx::query_as::<_, Transaction>(
         r#"
-        INSERT INTO transactions (user_id, merchant_id, amount, status, idempotency_key)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, user_id, merchant_id, amount, status, idempotency_key, upi_txn_id, error_message, created_at, updated_at
+        INSERT INTO transactions (user_id, merchant_id, payee_user_id, amount, status, idempotency_key, order_id,
+                                  promo_code_id, discount_amount)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
         "#,
     )
     .bind(user_id)
-    .bind(merchant.id)
-    .bind(req.amount)
+    .bind(match &payee {
+        Payee::Merchant(m) => Some(m.id),
+        Payee::User(_) => None,
+    })
+    .bind(match &payee {
+        Payee::User(u) => Some(u.id),
+        Payee::Merchant(_) => None,
+    })
+    .bind(payable.amount)
     .bind(TransactionStatus::Initiated)
     .bind(&req.idempotency_key)
+    .bind(order.map(|o| o.id))
+    .bind(promo.as_ref().map(|(p, _)| p.id))
+    .bind(promo.as_ref().map_or(Money::ZERO, |(_, discount)| *discount))
     .fetch_one(db)
     .await;
 
@@ -53,7 +106,7 @@ pub async fn initiate_payment(
                 if db_err.is_unique_violation() {
                     let existing: Transaction = sqlx::query_as::<_, Transaction>(
                         r#"
-                        SELECT id, user_id, merchant_id, amount, status, idempotency_key, upi_txn_id, error_message, created_at, updated_at
+                        SELECT id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
                         FROM transactions
                         WHERE idempotency_key = $1 AND user_id = $2
                         "#,
@@ -75,26 +128,78 @@ pub async fn initiate_payment(
 
     let response = PaymentInitResponse {
         session_id: transaction.id,
-        merchant: MerchantInfo {
-            name: merchant.name,
-            upi_id: merchant.upi_id,
-            category: merchant.category,
+        payee: match payee {
+            Payee::Merchant(m) => PayeeInfo {
+                payee_type: PayeeType::Merchant,
+                name: m.name,
+                upi_id: m.upi_id,
+                category: m.category,
+                verified,
+            },
+            Payee::User(u) => PayeeInfo {
+                payee_type: PayeeType::User,
+                name: u.name,
+                upi_id: u.upi_id,
+                category: None,
+                verified,
+            },
         },
         amount: transaction.amount,
+        currency: SUPPORTED_CURRENCY.to_string(),
+        amount_editable: payable.editable,
+        min_amount: payable.min_amount,
+        promo_code: promo.map(|(p, _)| p.code),
+        discount: transaction.discount_amount,
+        payable_amount: transaction.amount - transaction.discount_amount,
         status: "initiated".to_string(),
     };
 
     redis
         .set(&cache_key, &response, 600)
         .await
-        .map_err(|e| AppError::internal(e))?;
+        .map_err(AppError::internal)?;
 
     Ok(response)
 }
 
+pub fn payable_amount(intent: &UpiIntent, requested: Option<Money>) -> Result<PayableAmount, AppError> {
+    if intent.currency.as_deref().is_some_and(|cu| cu != SUPPORTED_CURRENCY) {
+        return Err(AppError::bad_request("only INR payments are supported"));
+    }
+
+    match (intent.amount, intent.min_amount) {
+        (Some(fixed), None) => {
+            if requested.is_some_and(|amount| amount != fixed) {
+                return Err(AppError::bad_request("amount does not match the amount in the qr code"));
+            }
+            Ok(PayableAmount {
+                amount: fixed,
+                editable: false,
+                min_amount: None,
+            })
+        }
+        (default, min_amount) => {
+            let amount = requested
+                .or(default)
+                .ok_or_else(|| AppError::bad_request("amount is required"))?;
+            if min_amount.is_some_and(|min| amount < min) {
+                return Err(AppError::bad_request("amount is below the minimum in the qr code"));
+            }
+            Ok(PayableAmount {
+                amount,
+                editable: true,
+                min_amount,
+            })
+        }
+    }
+}
+
 pub async fn execute_payment(
     db: &PgPool,
     redis: &RedisClient,
+    upi: &UpiClient,
+    spending_limits: &SpendingLimits,
+    session_ttl_seconds: i64,
     user_id: Uuid,
     req: PaymentExecuteRequest,
 ) -> Result<PaymentExecuteResponse, AppError> {
@@ -102,9 +207,10 @@ pub async fn execute_payment(
 
     let transaction: Transaction = sqlx::query_as::<_, Transaction>(
         r#"
-        SELECT id, user_id, merchant_id, amount, status, idempotency_key, upi_txn_id, error_message, created_at, updated_at
+        SELECT id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
         FROM transactions
         WHERE id = $1 AND user_id = $2
+        FOR UPDATE
         "#,
     )
     .bind(req.session_id)
@@ -113,62 +219,366 @@ pub async fn execute_payment(
     .await
     .map_err(AppError::from_sqlx)?;
 
-    if !matches!(transaction.status, TransactionStatus::Initiated | TransactionStatus::Pending) {
+    if transaction.split_id.is_some() {
+        return Err(AppError::bad_request("split shares are paid through the split"));
+    }
+    if transaction.status != TransactionStatus::Initiated {
         tx.commit().await.map_err(AppError::from_sqlx)?;
+        let message = match transaction.status {
+            TransactionStatus::Pending => "payment is awaiting confirmation from the payment network",
+            _ => "transaction already processed",
+        };
         return Ok(PaymentExecuteResponse {
             transaction_id: transaction.id,
             status: format!("{:?}", transaction.status).to_lowercase(),
             upi_txn_id: transaction.upi_txn_id,
-            message: "transaction already processed".to_string(),
+            message: message.to_string(),
+            remaining_limits: None,
+            cashback: None,
         });
     }
 
+    let idempotency_cache_key = format!("payment:idempotency:{}", transaction.idempotency_key);
+    if session_expired(&mut tx, transaction.id, session_ttl_seconds).await? {
+        mark_failed(&mut *tx, transaction.id, SESSION_EXPIRED_MESSAGE).await?;
+        tx.commit().await.map_err(AppError::from_sqlx)?;
+        let _ = redis.delete(&idempotency_cache_key).await;
+        return Err(AppError::SessionExpired(
+            "payment session has expired, start a new payment".to_string(),
+        ));
+    }
+
     verify_pin(&mut tx, user_id, &req.pin).await?;
-    ensure_balance(&mut tx, user_id, transaction.amount).await?;
+    let transaction_id = transaction.id;
+    let response = match complete_payment(&mut tx, upi, spending_limits, transaction).await {
+        Ok(response) => response,
+        Err(AppError::PaymentDeclined(reason)) => {
+            tx.rollback().await.map_err(AppError::from_sqlx)?;
+            mark_failed(db, transaction_id, &reason).await?;
+            let _ = redis.delete(&idempotency_cache_key).await;
+            return Err(AppError::PaymentDeclined(reason));
+        }
+        Err(e) => return Err(e),
+    };
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    let _ = redis.delete(&idempotency_cache_key).await;
 
-    let upi_txn_id = format!("UPI{}", Uuid::new_v4());
+    Ok(response)
+}
 
-    sqlx::query(
+// Debits an authorised payment: limits, balance, order and promo claims, MDR, the PSP call, ledger and
+// cashback. The caller owns the database transaction and has already checked the payer's PIN.
+// If the PSP has not settled the payment yet it is left pending with the payer's funds on hold.
+pub async fn complete_payment(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    upi: &UpiClient,
+    spending_limits: &SpendingLimits,
+    transaction: Transaction,
+) -> Result<PaymentExecuteResponse, AppError> {
+    let payable = transaction.amount - transaction.discount_amount;
+    let remaining_limits = limits::enforce(tx, spending_limits, transaction.user_id, payable).await?;
+    ensure_balance(tx, transaction.user_id, payable).await?;
+    if let Some(order_id) = transaction.order_id {
+        order::claim_order(tx, order_id, transaction.id).await?;
+    }
+    promo::redeem(tx, &transaction).await?;
+
+    let fee_amount = match transaction.merchant_id {
+        Some(merchant_id) => fee::merchant_fee(tx, merchant_id, transaction.amount).await?,
+        None => Money::ZERO,
+    };
+    let psp_payment = send_to_psp(tx, upi, &transaction, payable).await?;
+    let status = match psp_payment.status {
+        PspStatus::Pending => TransactionStatus::Pending,
+        _ => TransactionStatus::Success,
+    };
+
+    let transaction = sqlx::query_as::<_, Transaction>(
         r#"
         UPDATE transactions
-        SET status = $1, upi_txn_id = $2, error_message = NULL, updated_at = CURRENT_TIMESTAMP
-        WHERE id = $3
+        SET status = $1, upi_txn_id = $2, fee_amount = $3, error_message = NULL, updated_at = CURRENT_TIMESTAMP,
+            completed_at = CASE WHEN $1 = 'success'::transaction_status THEN CURRENT_TIMESTAMP END
+        WHERE id = $4
+        RETURNING id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
         "#,
     )
-    .bind(TransactionStatus::Success)
-    .bind(&upi_txn_id)
+    .bind(status)
+    .bind(&psp_payment.upi_txn_id)
+    .bind(fee_amount)
     .bind(transaction.id)
-    .execute(&mut *tx)
+    .fetch_one(&mut **tx)
     .await
     .map_err(AppError::from_sqlx)?;
 
-    sqlx::query(
+    if status == TransactionStatus::Pending {
+        ledger::hold_payment(tx, &transaction).await?;
+        return Ok(PaymentExecuteResponse {
+            transaction_id: transaction.id,
+            status: "pending".to_string(),
+            upi_txn_id: transaction.upi_txn_id,
+            message: "payment is awaiting confirmation from the payment network".to_string(),
+            remaining_limits: Some(remaining_limits),
+            cashback: None,
+        });
+    }
+
+    ledger::record_payment(tx, &transaction).await?;
+    let cashback = reward::award_cashback(tx, &transaction).await?.map_or(Money::ZERO, |r| r.amount);
+
+    Ok(PaymentExecuteResponse {
+        transaction_id: transaction.id,
+        status: "success".to_string(),
+        upi_txn_id: transaction.upi_txn_id,
+        message: "payment successful".to_string(),
+        remaining_limits: Some(remaining_limits),
+        cashback: Some(cashback),
+    })
+}
+
+// The payer's row lock is held across the switch call, so a user's payments reach the PSP one at a time.
+// A decline surfaces as PaymentDeclined for the caller to record. A timeout or network error may still have
+// moved the money, so the payment is reported pending and the callback or reconciliation settles it.
+pub async fn send_to_psp(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    upi: &UpiClient,
+    transaction: &Transaction,
+    payable: Money,
+) -> Result<PspPayment, AppError> {
+    let (payer_vpa, payee_vpa): (String, String) = sqlx::query_as(
         r#"
-        UPDATE users
-        SET balance = balance - $1, updated_at = CURRENT_TIMESTAMP
-        WHERE id = $2
+        SELECT u.upi_id, COALESCE(m.upi_id, p.upi_id)
+        FROM transactions t
+        JOIN users u ON u.id = t.user_id
+        LEFT JOIN merchants m ON m.id = t.merchant_id
+        LEFT JOIN users p ON p.id = t.payee_user_id
+        WHERE t.id = $1
         "#,
     )
-    .bind(transaction.amount)
-    .bind(user_id)
+    .bind(transaction.id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let payment = match upi.pay(transaction.id, &payer_vpa, &payee_vpa, payable, None).await {
+        Ok(payment) => payment,
+        Err(e) if e.is_network_failure() => {
+            log::warn!("payment {} left pending after a psp error: {}", transaction.id, e);
+            return Ok(PspPayment {
+                reference: transaction.id,
+                upi_txn_id: None,
+                status: PspStatus::Pending,
+                reason: None,
+            });
+        }
+        Err(e) => return Err(AppError::from_upi(e)),
+    };
+    match payment.status {
+        PspStatus::Success if payment.upi_txn_id.is_none() => {
+            Err(AppError::from_upi(UpiError::InvalidResponse("missing upi_txn_id".to_string())))
+        }
+        PspStatus::Success | PspStatus::Pending => Ok(payment),
+        PspStatus::Failed => Err(AppError::PaymentDeclined(
+            payment.reason.unwrap_or_else(|| "payment declined".to_string()),
+        )),
+    }
+}
+
+// Applies a signed PSP callback. The timestamp bounds how long a captured callback stays usable and the
+// recorded event id rejects a replay within that window; both are checked before anything is applied.
+pub async fn handle_callback(
+    db: &PgPool,
+    psp: &PspConfig,
+    path: &str,
+    timestamp: i64,
+    signature: &str,
+    body: &[u8],
+) -> Result<PspCallbackResponse, AppError> {
+    if (Utc::now().timestamp() - timestamp).abs() > CALLBACK_TOLERANCE_SECS {
+        return Err(AppError::unauthorized("callback timestamp is outside the allowed window"));
+    }
+    let body = std::str::from_utf8(body).map_err(|_| AppError::bad_request("callback body must be utf-8"))?;
+    if !upi_client::verify(&psp.secret, timestamp, "POST", path, body, signature) {
+        return Err(AppError::unauthorized("invalid callback signature"));
+    }
+    let callback: PspCallback =
+        serde_json::from_str(body).map_err(|e| AppError::bad_request(format!("invalid callback: {}", e)))?;
+
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let recorded = sqlx::query(
+        r#"
+        INSERT INTO psp_callbacks (event_id, transaction_id, status)
+        SELECT $1, id, $3 FROM transactions WHERE id = $2
+        ON CONFLICT (event_id) DO NOTHING
+        "#,
+    )
+    .bind(&callback.event_id)
+    .bind(callback.payment.reference)
+    .bind(format!("{:?}", callback.payment.status).to_lowercase())
     .execute(&mut *tx)
     .await
     .map_err(AppError::from_sqlx)?;
 
+    let duplicate = recorded.rows_affected() == 0;
+    let status = if duplicate {
+        sqlx::query_scalar::<_, TransactionStatus>(
+            "SELECT t.status FROM psp_callbacks c JOIN transactions t ON t.id = c.transaction_id WHERE c.event_id = $1",
+        )
+        .bind(&callback.event_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?
+        .ok_or_else(|| AppError::NotFound("transaction not found".to_string()))?
+    } else {
+        settle_pending(&mut tx, &callback.payment).await?
+    };
     tx.commit().await.map_err(AppError::from_sqlx)?;
 
-    let idempotency_cache_key = format!("payment:idempotency:{}", transaction.idempotency_key);
-    let _ = redis.delete(&idempotency_cache_key).await;
-
-    Ok(PaymentExecuteResponse {
-        transaction_id: transaction.id,
-        status: "success".to_string(),
-        upi_txn_id: Some(upi_txn_id),
-        message: "payment successful".to_string(),
+    Ok(PspCallbackResponse {
+        transaction_id: callback.payment.reference,
+        status: format!("{:?}", status).to_lowercase(),
+        duplicate,
     })
 }
 
-async fn verify_pin(
+// The one place a pending payment reaches its final state: success releases the hold to the payee,
+// failure reverses it to the payer and gives back the order and promo claims. Anything that is not
+// pending, or a PSP status that is itself still pending, leaves the transaction untouched. Split shares
+// settle against their split's escrow instead.
+pub async fn settle_pending(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    payment: &PspPayment,
+) -> Result<TransactionStatus, AppError> {
+    let split_id: Option<Uuid> = sqlx::query_scalar("SELECT split_id FROM transactions WHERE id = $1")
+        .bind(payment.reference)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?
+        .flatten();
+    let split = match split_id {
+        Some(split_id) => Some(split::lock(tx, split_id).await?),
+        None => None,
+    };
+
+    let transaction = sqlx::query_as::<_, Transaction>(
+        r#"
+        SELECT id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
+        FROM transactions
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(payment.reference)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("transaction not found".to_string()))?;
+
+    if transaction.status != TransactionStatus::Pending {
+        return Ok(transaction.status);
+    }
+    if let Some(split) = split {
+        return split::settle_share(tx, split, &transaction, payment).await;
+    }
+
+    match payment.status {
+        PspStatus::Pending => Ok(TransactionStatus::Pending),
+        PspStatus::Success => {
+            let upi_txn_id = payment
+                .upi_txn_id
+                .clone()
+                .or(transaction.upi_txn_id)
+                .ok_or_else(|| AppError::from_upi(UpiError::InvalidResponse("missing upi_txn_id".to_string())))?;
+            let transaction = sqlx::query_as::<_, Transaction>(
+                r#"
+                UPDATE transactions
+                SET status = $1, upi_txn_id = $2, updated_at = CURRENT_TIMESTAMP, completed_at = CURRENT_TIMESTAMP
+                WHERE id = $3
+                RETURNING id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
+                "#,
+            )
+            .bind(TransactionStatus::Success)
+            .bind(&upi_txn_id)
+            .bind(transaction.id)
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(AppError::from_sqlx)?;
+
+            ledger::settle_hold(tx, &transaction).await?;
+            reward::award_cashback(tx, &transaction).await?;
+            mandate::settle_execution(tx, transaction.id, MandateExecutionStatus::Success, None).await?;
+            Ok(TransactionStatus::Success)
+        }
+        PspStatus::Failed => {
+            let reason = payment.reason.as_deref().unwrap_or("payment declined");
+            mark_failed(&mut **tx, transaction.id, reason).await?;
+            ledger::reverse_hold(tx, &transaction).await?;
+            if let Some(order_id) = transaction.order_id {
+                order::release_order(tx, order_id, transaction.id).await?;
+            }
+            promo::release(tx, &transaction).await?;
+            mandate::settle_execution(tx, transaction.id, MandateExecutionStatus::Failed, Some(reason)).await?;
+            Ok(TransactionStatus::Failed)
+        }
+    }
+}
+
+pub async fn mark_failed<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    transaction_id: Uuid,
+    error_message: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE transactions
+        SET status = $1, error_message = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $3
+        "#,
+    )
+    .bind(TransactionStatus::Failed)
+    .bind(error_message)
+    .bind(transaction_id)
+    .execute(executor)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    Ok(())
+}
+
+// Fails sessions abandoned before execution. A share of a split that is still open lives as long as its split.
+// Pending payments hold funds the PSP may still move, so only the PSP's final status settles those.
+pub async fn expire_stale_sessions(db: &PgPool, session_ttl_seconds: i64) -> Result<usize, AppError> {
+    let expired = sqlx::query(
+        r#"
+        UPDATE transactions t
+        SET status = $1, error_message = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE t.status = 'initiated' AND t.created_at <= LOCALTIMESTAMP - $3 * INTERVAL '1 second'
+          AND NOT EXISTS (SELECT 1 FROM split_bills s WHERE s.id = t.split_id AND s.status = 'open')
+        "#,
+    )
+    .bind(TransactionStatus::Failed)
+    .bind(SESSION_EXPIRED_MESSAGE)
+    .bind(session_ttl_seconds)
+    .execute(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    Ok(expired.rows_affected() as usize)
+}
+
+// created_at is stamped by the database in its session time zone, so the age check is done there as well.
+async fn session_expired(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transaction_id: Uuid,
+    session_ttl_seconds: i64,
+) -> Result<bool, AppError> {
+    sqlx::query_scalar("SELECT created_at <= LOCALTIMESTAMP - $2 * INTERVAL '1 second' FROM transactions WHERE id = $1")
+        .bind(transaction_id)
+        .bind(session_ttl_seconds)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)
+}
+
+pub async fn verify_pin(
     tx: &mut sqlx::Transaction<'_, Postgres>,
     user_id: Uuid,
     pin: &str,
@@ -192,12 +602,12 @@ async fn verify_pin(
     Ok(())
 }
 
-async fn ensure_balance(
+pub async fn ensure_balance(
     tx: &mut sqlx::Transaction<'_, Postgres>,
     user_id: Uuid,
-    amount: f64,
+    amount: Money,
 ) -> Result<(), AppError> {
-    let balance: (f64,) = sqlx::query_as(
+    let balance: (Money,) = sqlx::query_as(
         r#"
         SELECT balance
         FROM users
@@ -0,0 +1,213 @@
+use chrono::Utc;
+use sqlx::{PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::money::Money;
+use crate::models::payment::Transaction;
+use crate::models::promo::{CreatePromoCodeRequest, PromoCode};
+use crate::services::fee;
+
+pub async fn create_code(db: &PgPool, req: CreatePromoCodeRequest) -> Result<PromoCode, AppError> {
+    let code = normalize(&req.code);
+    if code.is_empty() || code.len() > 32 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::bad_request("code must be 1 to 32 letters or digits"));
+    }
+    if !(0..=10_000).contains(&req.percentage_bps) {
+        return Err(AppError::bad_request("percentage_bps must be between 0 and 10000"));
+    }
+    if req.flat_discount < Money::ZERO || req.min_amount < Money::ZERO {
+        return Err(AppError::bad_request("amounts must not be negative"));
+    }
+    if req.percentage_bps == 0 && !req.flat_discount.is_positive() {
+        return Err(AppError::bad_request("promo code must give a percentage or a flat discount"));
+    }
+    if req.max_discount.is_some_and(|max| !max.is_positive()) {
+        return Err(AppError::bad_request("max_discount must be greater than 0"));
+    }
+    if req.usage_limit.is_some_and(|limit| limit <= 0) || req.per_user_limit.is_some_and(|limit| limit <= 0) {
+        return Err(AppError::bad_request("usage limits must be greater than 0"));
+    }
+    if req.expires_at <= Utc::now().naive_utc() {
+        return Err(AppError::bad_request("expires_at must be in the future"));
+    }
+
+    sqlx::query_as::<_, PromoCode>(
+        r#"
+        INSERT INTO promo_codes (code, percentage_bps, flat_discount, max_discount, min_amount, merchant_id,
+                                 usage_limit, per_user_limit, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, code, percentage_bps, flat_discount, max_discount, min_amount, merchant_id, usage_limit,
+                  per_user_limit, redemption_count, expires_at, active, created_at
+        "#,
+    )
+    .bind(&code)
+    .bind(req.percentage_bps)
+    .bind(req.flat_discount)
+    .bind(req.max_discount)
+    .bind(req.min_amount)
+    .bind(req.merchant_id)
+    .bind(req.usage_limit)
+    .bind(req.per_user_limit)
+    .bind(req.expires_at)
+    .fetch_one(db)
+    .await
+    .map_err(|e| match AppError::from_sqlx(e) {
+        AppError::Conflict(_) => AppError::Conflict("promo code already exists".to_string()),
+        other => other,
+    })
+}
+
+pub async fn list_codes(db: &PgPool) -> Result<Vec<PromoCode>, AppError> {
+    sqlx::query_as::<_, PromoCode>(
+        r#"
+        SELECT id, code, percentage_bps, flat_discount, max_discount, min_amount, merchant_id, usage_limit,
+               per_user_limit, redemption_count, expires_at, active, created_at
+        FROM promo_codes
+        WHERE active
+        ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+// Checks a code at initiation and returns the discount to lock in on the payment session.
+// Usage is only counted when the payment executes, see `redeem`.
+pub async fn apply(
+    db: &PgPool,
+    code: &str,
+    user_id: Uuid,
+    merchant_id: Uuid,
+    amount: Money,
+) -> Result<(PromoCode, Money), AppError> {
+    let promo = sqlx::query_as::<_, PromoCode>(
+        r#"
+        SELECT id, code, percentage_bps, flat_discount, max_discount, min_amount, merchant_id, usage_limit,
+               per_user_limit, redemption_count, expires_at, active, created_at
+        FROM promo_codes
+        WHERE code = $1 AND active
+        "#,
+    )
+    .bind(normalize(code))
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::bad_request("invalid promo code"))?;
+
+    if promo.expires_at <= Utc::now().naive_utc() {
+        return Err(AppError::bad_request("promo code has expired"));
+    }
+    if promo.merchant_id.is_some_and(|id| id != merchant_id) {
+        return Err(AppError::bad_request("promo code is not valid for this merchant"));
+    }
+    if amount < promo.min_amount {
+        return Err(AppError::bad_request(format!(
+            "promo code needs a payment of at least {}",
+            promo.min_amount
+        )));
+    }
+    if promo.usage_limit.is_some_and(|limit| promo.redemption_count >= limit) {
+        return Err(AppError::bad_request("promo code has reached its usage limit"));
+    }
+    if let Some(per_user_limit) = promo.per_user_limit {
+        if user_redemptions(db, promo.id, user_id).await? >= per_user_limit as i64 {
+            return Err(AppError::bad_request("promo code has already been used"));
+        }
+    }
+
+    let discount = discount(&promo, amount);
+    if !discount.is_positive() {
+        return Err(AppError::bad_request("promo code gives no discount on this amount"));
+    }
+    Ok((promo, discount))
+}
+
+// The conditional increment takes the promo row lock, so concurrent payments queue here and the
+// usage limit is re-checked against the committed count. Runs inside the payment's transaction.
+pub async fn redeem(tx: &mut sqlx::Transaction<'_, Postgres>, transaction: &Transaction) -> Result<(), AppError> {
+    let Some(promo_code_id) = transaction.promo_code_id else {
+        return Ok(());
+    };
+
+    let per_user_limit: Option<(Option<i32>,)> = sqlx::query_as(
+        r#"
+        UPDATE promo_codes
+        SET redemption_count = redemption_count + 1
+        WHERE id = $1 AND active AND (usage_limit IS NULL OR redemption_count < usage_limit)
+        RETURNING per_user_limit
+        "#,
+    )
+    .bind(promo_code_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    let Some((per_user_limit,)) = per_user_limit else {
+        return Err(AppError::Conflict("promo code is no longer available".to_string()));
+    };
+
+    if let Some(per_user_limit) = per_user_limit {
+        if user_redemptions(&mut **tx, promo_code_id, transaction.user_id).await? >= per_user_limit as i64 {
+            return Err(AppError::Conflict("promo code has already been used".to_string()));
+        }
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO promo_redemptions (promo_code_id, user_id, transaction_id, discount)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(promo_code_id)
+    .bind(transaction.user_id)
+    .bind(transaction.id)
+    .bind(transaction.discount_amount)
+    .execute(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    Ok(())
+}
+
+// Undoes a redemption whose payment failed after it was claimed, returning the use to the code.
+pub async fn release(tx: &mut sqlx::Transaction<'_, Postgres>, transaction: &Transaction) -> Result<(), AppError> {
+    let released = sqlx::query("DELETE FROM promo_redemptions WHERE transaction_id = $1")
+        .bind(transaction.id)
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    if released.rows_affected() > 0 {
+        sqlx::query("UPDATE promo_codes SET redemption_count = redemption_count - 1 WHERE id = $1")
+            .bind(transaction.promo_code_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::from_sqlx)?;
+    }
+    Ok(())
+}
+
+async fn user_redemptions<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    promo_code_id: Uuid,
+    user_id: Uuid,
+) -> Result<i64, AppError> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM promo_redemptions WHERE promo_code_id = $1 AND user_id = $2")
+        .bind(promo_code_id)
+        .bind(user_id)
+        .fetch_one(executor)
+        .await
+        .map_err(AppError::from_sqlx)
+}
+
+// Something is always left to pay: a zero-amount payment cannot go to the PSP or be held on the ledger.
+fn discount(promo: &PromoCode, amount: Money) -> Money {
+    let mut discount = fee::prorate(amount, promo.percentage_bps as i64, 10_000) + promo.flat_discount;
+    if let Some(max_discount) = promo.max_discount {
+        discount = discount.min(max_discount);
+    }
+    discount.min(amount - Money::from_paise(1))
+}
+
+fn normalize(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
@@ -0,0 +1,201 @@
+use chrono::{Duration, NaiveDateTime};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::money::Money;
+use crate::models::payment::TransactionStatus;
+use crate::models::reconciliation::{PollOutcome, ReconciliationConfig, ReconciliationRun, StatusPoll};
+use crate::services::payment;
+use crate::utils::upi_client::{PspPayment, PspStatus, UpiClient, UpiError};
+
+const REVERSAL_REASON: &str = "payment was not confirmed by the payment network in time";
+const NOT_RECEIVED_REASON: &str = "payment never reached the payment network";
+const NOT_FOUND_CODE: &str = "not_found";
+
+#[derive(Debug, FromRow)]
+struct PendingPayment {
+    id: Uuid,
+    payable: Money,
+    pending_since: NaiveDateTime,
+    expired: bool,
+    attempts: i32,
+    last_polled_at: Option<NaiveDateTime>,
+}
+
+enum Resolution {
+    Settle(PspPayment),
+    Reverse,
+    StillPending,
+    Error(String),
+}
+
+// Catches pending payments whose callback never arrived. Each due payment's status is fetched from the PSP
+// and applied through the same transition the callback uses; one still pending past the maximum age is
+// reversed with the PSP and released back to the payer once the reversal completes. Every poll is recorded,
+// and a payment that cannot be reconciled is recorded as an error without holding up the rest. Ages are measured
+// against the database clock, the same one that stamped updated_at; `now` only paces the polls themselves.
+pub async fn run_due(
+    db: &PgPool,
+    upi: &UpiClient,
+    config: &ReconciliationConfig,
+    now: NaiveDateTime,
+) -> Result<ReconciliationRun, AppError> {
+    let mut run = ReconciliationRun::default();
+
+    let pending = sqlx::query_as::<_, PendingPayment>(
+        r#"
+        SELECT t.id, t.amount - t.discount_amount AS payable, t.updated_at AS pending_since,
+               t.updated_at <= LOCALTIMESTAMP - make_interval(secs => $2) AS expired,
+               COUNT(p.id)::INT AS attempts, MAX(p.polled_at) AS last_polled_at
+        FROM transactions t
+        LEFT JOIN psp_status_polls p ON p.transaction_id = t.id
+        WHERE t.status = 'pending' AND t.updated_at <= LOCALTIMESTAMP - make_interval(secs => $1)
+        GROUP BY t.id
+        ORDER BY t.updated_at
+        "#,
+    )
+    .bind(config.poll_after_secs as f64)
+    .bind(config.max_age_secs as f64)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    for payment in pending {
+        if payment.last_polled_at.is_some_and(|at| now < at + backoff(config, payment.attempts)) {
+            continue;
+        }
+        run.polled += 1;
+        let outcome = match poll(db, upi, &payment, now).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                log::error!("reconciling payment {} failed: {}", payment.id, e);
+                record_poll(db, &payment, None, PollOutcome::Error, Some(&e.to_string()), now).await?;
+                PollOutcome::Error
+            }
+        };
+        match outcome {
+            PollOutcome::Success => run.settled += 1,
+            PollOutcome::Failed => run.failed += 1,
+            PollOutcome::Reversed => run.reversed += 1,
+            PollOutcome::Error => run.errors += 1,
+            PollOutcome::Pending => {}
+        }
+    }
+
+    Ok(run)
+}
+
+pub async fn polls_for_transaction(db: &PgPool, transaction_id: Uuid) -> Result<Vec<StatusPoll>, AppError> {
+    sqlx::query_as::<_, StatusPoll>(
+        r#"
+        SELECT id, transaction_id, attempt, psp_status, outcome, error_message, polled_at
+        FROM psp_status_polls
+        WHERE transaction_id = $1
+        ORDER BY attempt
+        "#,
+    )
+    .bind(transaction_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+// The PSP calls happen before the database transaction opens so no row lock is held across the network.
+// An error applying the result rolls the whole poll back, and run_due records it on its own.
+async fn poll(
+    db: &PgPool,
+    upi: &UpiClient,
+    payment: &PendingPayment,
+    now: NaiveDateTime,
+) -> Result<PollOutcome, AppError> {
+    let status = upi.status(payment.id).await;
+    let psp_status = status.as_ref().ok().map(|p| format!("{:?}", p.status).to_lowercase());
+    let resolution = match status {
+        Ok(psp_payment) if psp_payment.status != PspStatus::Pending => Resolution::Settle(psp_payment),
+        // Only a completed reversal releases the hold; one the PSP is still working on is asked about again.
+        Ok(_) if payment.expired => match upi.reverse(payment.id, payment.payable, REVERSAL_REASON).await {
+            Ok(reversal) => match reversal.status {
+                PspStatus::Success => Resolution::Reverse,
+                PspStatus::Pending => Resolution::StillPending,
+                PspStatus::Failed => Resolution::Error(format!("reversal {} was refused", reversal.reversal_id)),
+            },
+            Err(e) => Resolution::Error(format!("reversal failed: {}", e)),
+        },
+        Ok(_) => Resolution::StillPending,
+        // A payment whose request timed out may never have reached the switch; if it does not know the
+        // reference, nothing was debited and the hold can go back.
+        Err(UpiError::Rejected { code, .. }) if code == NOT_FOUND_CODE => Resolution::Settle(PspPayment {
+            reference: payment.id,
+            upi_txn_id: None,
+            status: PspStatus::Failed,
+            reason: Some(NOT_RECEIVED_REASON.to_string()),
+        }),
+        Err(e) => Resolution::Error(e.to_string()),
+    };
+
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let (outcome, error_message) = match resolution {
+        Resolution::Settle(psp_payment) => (outcome_for(payment::settle_pending(&mut tx, &psp_payment).await?), None),
+        Resolution::Reverse => {
+            let reversal = PspPayment {
+                reference: payment.id,
+                upi_txn_id: None,
+                status: PspStatus::Failed,
+                reason: Some(REVERSAL_REASON.to_string()),
+            };
+            let outcome = match payment::settle_pending(&mut tx, &reversal).await? {
+                TransactionStatus::Failed => PollOutcome::Reversed,
+                other => outcome_for(other),
+            };
+            log::info!("reversed payment {} left pending since {}", payment.id, payment.pending_since);
+            (outcome, None)
+        }
+        Resolution::StillPending => (PollOutcome::Pending, None),
+        Resolution::Error(message) => (PollOutcome::Error, Some(message)),
+    };
+
+    record_poll(&mut *tx, payment, psp_status.as_deref(), outcome, error_message.as_deref(), now).await?;
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(outcome)
+}
+
+async fn record_poll<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    payment: &PendingPayment,
+    psp_status: Option<&str>,
+    outcome: PollOutcome,
+    error_message: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO psp_status_polls (transaction_id, attempt, psp_status, outcome, error_message, polled_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(payment.id)
+    .bind(payment.attempts + 1)
+    .bind(psp_status)
+    .bind(outcome)
+    .bind(error_message)
+    .bind(now)
+    .execute(executor)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    Ok(())
+}
+
+// A callback may have settled the payment between the query and the poll; report whatever it is now.
+fn outcome_for(status: TransactionStatus) -> PollOutcome {
+    match status {
+        TransactionStatus::Initiated | TransactionStatus::Pending => PollOutcome::Pending,
+        TransactionStatus::Escrowed | TransactionStatus::Success | TransactionStatus::Refunded => PollOutcome::Success,
+        TransactionStatus::Failed => PollOutcome::Failed,
+    }
+}
+
+fn backoff(config: &ReconciliationConfig, attempts: i32) -> Duration {
+    let doublings = (attempts - 1).clamp(0, 30) as u32;
+    Duration::seconds(config.backoff_secs.saturating_mul(1 << doublings).min(config.max_backoff_secs))
+}
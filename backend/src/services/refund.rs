@@ -0,0 +1,183 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::ledger::{LedgerAccount, Posting};
+use crate::models::money::Money;
+use crate::models::payment::{Transaction, TransactionStatus};
+use crate::models::refund::{Refund, RefundInitiator, RefundRequest, RefundResponse};
+use crate::models::user::UserRole;
+use crate::services::{fee, ledger, reward};
+
+pub async fn refund_payment(
+    db: &PgPool,
+    transaction_id: Uuid,
+    requested_by: Uuid,
+    req: RefundRequest,
+) -> Result<RefundResponse, AppError> {
+    if req.refund_key.trim().is_empty() {
+        return Err(AppError::bad_request("refund_key is required"));
+    }
+    if req.amount.is_some_and(|amount| !amount.is_positive()) {
+        return Err(AppError::bad_request("amount must be greater than 0"));
+    }
+
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+
+    let transaction: Transaction = sqlx::query_as::<_, Transaction>(
+        r#"
+        SELECT id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
+        FROM transactions
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(transaction_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    let initiated_by = initiator(&mut tx, &transaction, requested_by).await?;
+
+    // Replays are resolved under the row lock so two requests with the same key cannot both refund.
+    if let Some(existing) = find_by_key(&mut tx, &req.refund_key).await? {
+        if existing.transaction_id != transaction_id {
+            return Err(AppError::Conflict("refund key already used".to_string()));
+        }
+        tx.commit().await.map_err(AppError::from_sqlx)?;
+        return Ok(refund_response(existing, &transaction));
+    }
+
+    if !matches!(transaction.status, TransactionStatus::Success | TransactionStatus::Refunded) {
+        return Err(AppError::bad_request("only successful payments can be refunded"));
+    }
+    let remaining = transaction.amount - transaction.refunded_amount;
+    let amount = req.amount.unwrap_or(remaining);
+    if !amount.is_positive() {
+        return Err(AppError::Conflict("payment has already been fully refunded".to_string()));
+    }
+    if amount > remaining {
+        return Err(AppError::bad_request(format!("refund exceeds the refundable amount of {}", remaining)));
+    }
+
+    let refund = sqlx::query_as::<_, Refund>(
+        r#"
+        INSERT INTO refunds (transaction_id, amount, reason, initiated_by, requested_by, refund_key)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, transaction_id, amount, reason, initiated_by, requested_by, refund_key, created_at
+        "#,
+    )
+    .bind(transaction.id)
+    .bind(amount)
+    .bind(&req.reason)
+    .bind(initiated_by)
+    .bind(requested_by)
+    .bind(req.refund_key.trim())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let refunded_amount = transaction.refunded_amount + amount;
+    let status = if refunded_amount == transaction.amount {
+        TransactionStatus::Refunded
+    } else {
+        transaction.status
+    };
+    // The merchant gets back the share of the fee that was charged on the refunded part of the payment.
+    let refunded_fee = fee::prorate(transaction.fee_amount, refunded_amount.paise(), transaction.amount.paise());
+    let fee_reversal = refunded_fee - transaction.refunded_fee;
+    // Likewise the promo discount on the refunded part goes back to the platform rather than the payer.
+    let discount_share = |refunded: Money| fee::prorate(transaction.discount_amount, refunded.paise(), transaction.amount.paise());
+    let discount_reversal = discount_share(refunded_amount) - discount_share(transaction.refunded_amount);
+    let transaction = sqlx::query_as::<_, Transaction>(
+        r#"
+        UPDATE transactions
+        SET refunded_amount = $1, refunded_fee = $2, status = $3, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $4
+        RETURNING id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
+        "#,
+    )
+    .bind(refunded_amount)
+    .bind(refunded_fee)
+    .bind(status)
+    .bind(transaction.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let payee = match (transaction.merchant_id, transaction.payee_user_id) {
+        (Some(merchant_id), _) => LedgerAccount::merchant_payable(merchant_id),
+        (None, Some(payee_user_id)) => LedgerAccount::user_wallet(payee_user_id),
+        (None, None) => return Err(AppError::internal("transaction has no payee")),
+    };
+    let postings = [
+        Posting::debit(payee, amount - fee_reversal),
+        Posting::debit(LedgerAccount::fee_revenue(), fee_reversal),
+        Posting::credit(LedgerAccount::user_wallet(transaction.user_id), amount - discount_reversal),
+        Posting::credit(LedgerAccount::rewards_expense(), discount_reversal),
+    ];
+    let postings: Vec<Posting> = postings.into_iter().filter(|p| p.amount.is_positive()).collect();
+    ledger::post(&mut tx, Some(transaction.id), "refund", &postings).await?;
+    reward::reverse_for_refund(&mut tx, &transaction).await?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(refund_response(refund, &transaction))
+}
+
+// A refund pays the payer back out of the payee's balance, so only the payee side may give one: the merchant's
+// owner, or the user who received a P2P payment. Admins can refund anything.
+async fn initiator(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    transaction: &Transaction,
+    requested_by: Uuid,
+) -> Result<RefundInitiator, AppError> {
+    let role: Option<UserRole> = sqlx::query_scalar("SELECT role FROM users WHERE id = $1")
+        .bind(requested_by)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    if role == Some(UserRole::Admin) {
+        return Ok(RefundInitiator::Admin);
+    }
+
+    let is_payee = match (transaction.merchant_id, transaction.payee_user_id) {
+        (Some(merchant_id), _) => {
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM merchants WHERE id = $1 AND owner_id = $2)")
+                .bind(merchant_id)
+                .bind(requested_by)
+                .fetch_one(&mut **tx)
+                .await
+                .map_err(AppError::from_sqlx)?
+        }
+        (None, Some(payee_user_id)) => payee_user_id == requested_by,
+        (None, None) => false,
+    };
+    if !is_payee {
+        return Err(AppError::forbidden("only the payee or an admin can refund this payment"));
+    }
+    Ok(RefundInitiator::Merchant)
+}
+
+async fn find_by_key(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, refund_key: &str) -> Result<Option<Refund>, AppError> {
+    sqlx::query_as::<_, Refund>(
+        r#"
+        SELECT id, transaction_id, amount, reason, initiated_by, requested_by, refund_key, created_at
+        FROM refunds
+        WHERE refund_key = $1
+        "#,
+    )
+    .bind(refund_key.trim())
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+fn refund_response(refund: Refund, transaction: &Transaction) -> RefundResponse {
+    RefundResponse {
+        refund_id: refund.id,
+        transaction_id: refund.transaction_id,
+        amount: refund.amount,
+        refunded_amount: transaction.refunded_amount,
+        transaction_status: format!("{:?}", transaction.status).to_lowercase(),
+        created_at: refund.created_at,
+    }
+}
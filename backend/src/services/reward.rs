@@ -0,0 +1,255 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::ledger::{LedgerAccount, Posting};
+use crate::models::money::Money;
+use crate::models::payment::Transaction;
+use crate::models::reward::{CreateRewardCampaignRequest, Reward, RewardCampaign, RewardCohort};
+use crate::services::{fee, ledger};
+
+#[derive(FromRow)]
+struct EligibleCampaign {
+    #[sqlx(flatten)]
+    campaign: RewardCampaign,
+    used: Money,
+}
+
+pub async fn create_campaign(db: &PgPool, req: CreateRewardCampaignRequest) -> Result<RewardCampaign, AppError> {
+    let category = req.category.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    if req.name.trim().is_empty() {
+        return Err(AppError::bad_request("name is required"));
+    }
+    if !(0..=10_000).contains(&req.percentage_bps) {
+        return Err(AppError::bad_request("percentage_bps must be between 0 and 10000"));
+    }
+    if req.min_amount < Money::ZERO || req.flat_amount < Money::ZERO {
+        return Err(AppError::bad_request("amounts must not be negative"));
+    }
+    if req.percentage_bps == 0 && !req.flat_amount.is_positive() {
+        return Err(AppError::bad_request("campaign must give a percentage or a flat amount"));
+    }
+    if req.max_amount.is_some_and(|max| max < req.min_amount) {
+        return Err(AppError::bad_request("max_amount must not be below min_amount"));
+    }
+    if req.max_reward.is_some_and(|max| !max.is_positive()) || req.per_user_cap.is_some_and(|cap| !cap.is_positive()) {
+        return Err(AppError::bad_request("reward caps must be greater than 0"));
+    }
+    if req.per_user_limit.is_some_and(|limit| limit <= 0) {
+        return Err(AppError::bad_request("per_user_limit must be greater than 0"));
+    }
+    if req.ends_at <= req.starts_at {
+        return Err(AppError::bad_request("ends_at must be after starts_at"));
+    }
+    if req.cohort == RewardCohort::FirstInCategory && category.is_none() {
+        return Err(AppError::bad_request("first_in_category campaigns need a category"));
+    }
+
+    sqlx::query_as::<_, RewardCampaign>(
+        r#"
+        INSERT INTO reward_campaigns (name, category, cohort, min_amount, max_amount, percentage_bps, flat_amount,
+                                      max_reward, per_user_limit, per_user_cap, starts_at, ends_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id, name, category, cohort, min_amount, max_amount, percentage_bps, flat_amount, max_reward,
+                  per_user_limit, per_user_cap, starts_at, ends_at, active, created_at
+        "#,
+    )
+    .bind(req.name.trim())
+    .bind(category)
+    .bind(req.cohort)
+    .bind(req.min_amount)
+    .bind(req.max_amount)
+    .bind(req.percentage_bps)
+    .bind(req.flat_amount)
+    .bind(req.max_reward)
+    .bind(req.per_user_limit)
+    .bind(req.per_user_cap)
+    .bind(req.starts_at)
+    .bind(req.ends_at)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn list_campaigns(db: &PgPool) -> Result<Vec<RewardCampaign>, AppError> {
+    sqlx::query_as::<_, RewardCampaign>(
+        r#"
+        SELECT id, name, category, cohort, min_amount, max_amount, percentage_bps, flat_amount, max_reward,
+               per_user_limit, per_user_cap, starts_at, ends_at, active, created_at
+        FROM reward_campaigns
+        WHERE active
+        ORDER BY starts_at DESC
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn rewards_for_user(db: &PgPool, user_id: Uuid) -> Result<Vec<Reward>, AppError> {
+    sqlx::query_as::<_, Reward>(
+        r#"
+        SELECT id, campaign_id, transaction_id, user_id, amount, reversed_amount, status, created_at, updated_at
+        FROM rewards
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+// Runs inside the payment's transaction, after the payer's row has been locked by the limit check,
+// so per-user counts and caps cannot be raced. Only the most generous eligible campaign pays out.
+pub async fn award_cashback(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transaction: &Transaction,
+) -> Result<Option<Reward>, AppError> {
+    let Some(merchant_id) = transaction.merchant_id else {
+        return Ok(None);
+    };
+
+    let eligible = sqlx::query_as::<_, EligibleCampaign>(
+        r#"
+        SELECT c.id, c.name, c.category, c.cohort, c.min_amount, c.max_amount, c.percentage_bps, c.flat_amount,
+               c.max_reward, c.per_user_limit, c.per_user_cap, c.starts_at, c.ends_at, c.active, c.created_at,
+               COALESCE(u.used, 0)::BIGINT AS used
+        FROM reward_campaigns c
+        JOIN merchants m ON m.id = $2
+        CROSS JOIN LATERAL (
+            SELECT COUNT(*) AS count, SUM(r.amount - r.reversed_amount) AS used
+            FROM rewards r
+            WHERE r.campaign_id = c.id AND r.user_id = $1 AND r.status = 'credited'
+        ) u
+        WHERE c.active AND c.starts_at <= $5 AND c.ends_at > $5
+          AND (c.category IS NULL OR LOWER(c.category) = LOWER(m.category))
+          AND c.min_amount <= $4 AND (c.max_amount IS NULL OR c.max_amount >= $4)
+          AND (c.per_user_limit IS NULL OR u.count < c.per_user_limit)
+          AND (c.cohort <> 'new_user' OR NOT EXISTS (
+              SELECT 1 FROM transactions t
+              WHERE t.user_id = $1 AND t.id <> $3 AND t.status IN ('success', 'refunded')
+          ))
+          AND (c.cohort <> 'first_in_category' OR NOT EXISTS (
+              SELECT 1 FROM transactions t
+              JOIN merchants tm ON tm.id = t.merchant_id
+              WHERE t.user_id = $1 AND t.id <> $3 AND t.status IN ('success', 'refunded')
+                AND LOWER(tm.category) = LOWER(c.category)
+          ))
+        ORDER BY c.created_at
+        "#,
+    )
+    .bind(transaction.user_id)
+    .bind(merchant_id)
+    .bind(transaction.id)
+    .bind(transaction.amount)
+    .bind(Utc::now().naive_utc())
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let best = eligible
+        .iter()
+        .map(|e| (e, cashback(&e.campaign, transaction.amount, e.used)))
+        .filter(|(_, amount)| amount.is_positive())
+        .max_by_key(|(_, amount)| *amount);
+    let Some((eligible, amount)) = best else {
+        return Ok(None);
+    };
+
+    let reward = sqlx::query_as::<_, Reward>(
+        r#"
+        INSERT INTO rewards (campaign_id, transaction_id, user_id, amount)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, campaign_id, transaction_id, user_id, amount, reversed_amount, status, created_at, updated_at
+        "#,
+    )
+    .bind(eligible.campaign.id)
+    .bind(transaction.id)
+    .bind(transaction.user_id)
+    .bind(amount)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    ledger::post(
+        tx,
+        Some(transaction.id),
+        &format!("cashback {}", reward.id),
+        &[
+            Posting::debit(LedgerAccount::rewards_expense(), amount),
+            Posting::credit(LedgerAccount::user_wallet(transaction.user_id), amount),
+        ],
+    )
+    .await?;
+
+    Ok(Some(reward))
+}
+
+// Claws back the share of the cashback that belongs to the refunded part of the payment.
+pub async fn reverse_for_refund(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transaction: &Transaction,
+) -> Result<(), AppError> {
+    let reward = sqlx::query_as::<_, Reward>(
+        r#"
+        SELECT id, campaign_id, transaction_id, user_id, amount, reversed_amount, status, created_at, updated_at
+        FROM rewards
+        WHERE transaction_id = $1 AND status = 'credited'
+        FOR UPDATE
+        "#,
+    )
+    .bind(transaction.id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    let Some(reward) = reward else {
+        return Ok(());
+    };
+
+    let reversed_amount = fee::prorate(reward.amount, transaction.refunded_amount.paise(), transaction.amount.paise());
+    let reversal = reversed_amount - reward.reversed_amount;
+    if !reversal.is_positive() {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE rewards
+        SET reversed_amount = $1,
+            status = CASE WHEN $1 = amount THEN 'reversed'::reward_status ELSE status END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $2
+        "#,
+    )
+    .bind(reversed_amount)
+    .bind(reward.id)
+    .execute(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    ledger::post(
+        tx,
+        Some(reward.transaction_id),
+        &format!("cashback reversal {}", reward.id),
+        &[
+            Posting::debit(LedgerAccount::user_wallet(reward.user_id), reversal),
+            Posting::credit(LedgerAccount::rewards_expense(), reversal),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+fn cashback(campaign: &RewardCampaign, amount: Money, used: Money) -> Money {
+    let mut reward = fee::prorate(amount, campaign.percentage_bps as i64, 10_000) + campaign.flat_amount;
+    if let Some(max_reward) = campaign.max_reward {
+        reward = reward.min(max_reward);
+    }
+    if let Some(cap) = campaign.per_user_cap {
+        reward = reward.min(cap - used);
+    }
+    reward.min(amount)
+}
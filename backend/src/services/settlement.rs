@@ -0,0 +1,287 @@
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::ledger::{AccountType, LedgerAccount, Posting};
+use crate::models::money::Money;
+use crate::models::settlement::{
+    MerchantSettlementSummary, SettlementBatch, SettlementReport, SettlementReportQuery, SettlementSchedule,
+    SettlementStatus, SettlementStatusRequest,
+};
+use crate::services::ledger;
+
+const SETTLEMENT_LOCK: i64 = 7_001;
+
+// T+0 merchants are settled up to `as_of`; T+1 merchants only up to the start of that day. Without `as_of` the
+// cutoff is the database clock's current time, the same clock that stamped completed_at and draws the day lines.
+// Each batch covers one calendar day of payments for one merchant by when they completed, net of fees and of refunds
+// made so far. Refunds of payments that were already batched are taken out of the merchant's next batches.
+pub async fn run_settlement(db: &PgPool, as_of: Option<NaiveDateTime>) -> Result<Vec<SettlementBatch>, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(SETTLEMENT_LOCK)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+    let windows: Vec<(Uuid, SettlementSchedule, NaiveDateTime, NaiveDateTime, Money)> = sqlx::query_as(
+        r#"
+        SELECT t.merchant_id, m.settlement_schedule, date_trunc('day', t.completed_at) AS window_start,
+               LEAST(date_trunc('day', t.completed_at) + INTERVAL '1 day', MAX(c.cutoff)) AS window_end,
+               SUM(t.net_amount - t.refunded_amount + t.refunded_fee)::BIGINT AS amount
+        FROM transactions t
+        JOIN merchants m ON m.id = t.merchant_id
+        CROSS JOIN LATERAL (SELECT COALESCE($1::TIMESTAMP, LOCALTIMESTAMP) AS as_of) n
+        CROSS JOIN LATERAL (
+            SELECT CASE m.settlement_schedule WHEN 't0' THEN n.as_of ELSE date_trunc('day', n.as_of) END AS cutoff
+        ) c
+        WHERE t.status = 'success' AND t.settlement_batch_id IS NULL AND t.completed_at < c.cutoff
+        GROUP BY t.merchant_id, m.settlement_schedule, date_trunc('day', t.completed_at)
+        ORDER BY t.merchant_id, window_start
+        "#,
+    )
+    .bind(as_of)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let mut batches = Vec::with_capacity(windows.len());
+    let mut owed = (None, Money::ZERO);
+    for (merchant_id, schedule, window_start, window_end, amount) in windows {
+        if owed.0 != Some(merchant_id) {
+            owed = (Some(merchant_id), refunds_owed(&mut tx, merchant_id).await?);
+        }
+        let deduction = owed.1.min(amount);
+        owed.1 = owed.1 - deduction;
+
+        let batch_id: (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO settlement_batches (merchant_id, schedule, window_start, window_end)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(merchant_id)
+        .bind(schedule)
+        .bind(window_start)
+        .bind(window_end)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+        let batch = sqlx::query_as::<_, SettlementBatch>(
+            r#"
+            WITH settled AS (
+                UPDATE transactions
+                SET settlement_batch_id = $1
+                WHERE merchant_id = $2 AND status = 'success' AND settlement_batch_id IS NULL
+                  AND completed_at >= $3 AND completed_at < $4
+                RETURNING net_amount - refunded_amount + refunded_fee AS amount
+            )
+            UPDATE settlement_batches
+            SET amount = GREATEST((SELECT COALESCE(SUM(amount), 0)::BIGINT FROM settled) - $5, 0),
+                transaction_count = (SELECT COUNT(*) FROM settled)
+            WHERE id = $1
+            RETURNING id, merchant_id, schedule, window_start, window_end, amount, transaction_count, status,
+                      payout_reference, failure_reason, paid_at, created_at, updated_at
+            "#,
+        )
+        .bind(batch_id.0)
+        .bind(merchant_id)
+        .bind(window_start)
+        .bind(window_end)
+        .bind(deduction)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+        batches.push(batch);
+    }
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(batches)
+}
+
+// The ledger is the record of what the merchant is owed: payments credit their payable account and every refund
+// debits it. Whatever is still unsettled or in a batch awaiting payout beyond that balance has been refunded after
+// it was batched, and is held back from what is paid next.
+async fn refunds_owed(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, merchant_id: Uuid) -> Result<Money, AppError> {
+    let (payable, in_flight, unsettled): (Money, Money, Money) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COALESCE(SUM(CASE WHEN direction = 'credit' THEN amount ELSE -amount END), 0)
+             FROM ledger_entries WHERE account_type = $2 AND account_id = $1)::BIGINT,
+            (SELECT COALESCE(SUM(amount), 0)
+             FROM settlement_batches WHERE merchant_id = $1 AND status IN ('pending', 'processing'))::BIGINT,
+            (SELECT COALESCE(SUM(net_amount - refunded_amount + refunded_fee), 0)
+             FROM transactions WHERE merchant_id = $1 AND status = 'success' AND settlement_batch_id IS NULL)::BIGINT
+        "#,
+    )
+    .bind(merchant_id)
+    .bind(AccountType::MerchantPayable)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    Ok((in_flight + unsettled - payable).max(Money::ZERO))
+}
+
+pub async fn get_batch(db: &PgPool, batch_id: Uuid) -> Result<SettlementBatch, AppError> {
+    sqlx::query_as::<_, SettlementBatch>(
+        r#"
+        SELECT id, merchant_id, schedule, window_start, window_end, amount, transaction_count, status,
+               payout_reference, failure_reason, paid_at, created_at, updated_at
+        FROM settlement_batches
+        WHERE id = $1
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn update_batch_status(
+    db: &PgPool,
+    batch_id: Uuid,
+    req: SettlementStatusRequest,
+) -> Result<SettlementBatch, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+
+    let current: (SettlementStatus, Uuid, Money) = sqlx::query_as(
+        r#"
+        SELECT status, merchant_id, amount
+        FROM settlement_batches
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+    let (status, merchant_id, amount) = current;
+
+    let allowed = matches!(
+        (status, req.status),
+        (SettlementStatus::Pending, SettlementStatus::Processing)
+            | (SettlementStatus::Pending | SettlementStatus::Processing, SettlementStatus::Paid)
+            | (SettlementStatus::Pending | SettlementStatus::Processing, SettlementStatus::Failed)
+    );
+    if !allowed {
+        return Err(AppError::Conflict(format!(
+            "cannot move settlement from {:?} to {:?}",
+            status, req.status
+        )
+        .to_lowercase()));
+    }
+    if req.status == SettlementStatus::Paid && req.payout_reference.as_deref().is_none_or(str::is_empty) {
+        return Err(AppError::bad_request("payout_reference is required"));
+    }
+
+    match req.status {
+        SettlementStatus::Paid if amount.is_positive() => {
+            ledger::post(
+                &mut tx,
+                None,
+                "settlement payout",
+                &[
+                    Posting::debit(LedgerAccount::merchant_payable(merchant_id), amount),
+                    Posting::credit(LedgerAccount::funding(), amount),
+                ],
+            )
+            .await?;
+        }
+        SettlementStatus::Failed => {
+            // Release the payments so the next run picks them up again.
+            sqlx::query("UPDATE transactions SET settlement_batch_id = NULL WHERE settlement_batch_id = $1")
+                .bind(batch_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::from_sqlx)?;
+        }
+        _ => {}
+    }
+
+    let batch = sqlx::query_as::<_, SettlementBatch>(
+        r#"
+        UPDATE settlement_batches
+        SET status = $1,
+            payout_reference = COALESCE($2, payout_reference),
+            failure_reason = $3,
+            paid_at = CASE WHEN $1 = 'paid'::settlement_status THEN CURRENT_TIMESTAMP ELSE paid_at END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $4
+        RETURNING id, merchant_id, schedule, window_start, window_end, amount, transaction_count, status,
+                  payout_reference, failure_reason, paid_at, created_at, updated_at
+        "#,
+    )
+    .bind(req.status)
+    .bind(req.payout_reference)
+    .bind(req.failure_reason)
+    .bind(batch_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(batch)
+}
+
+pub async fn set_schedule(db: &PgPool, merchant_id: Uuid, schedule: SettlementSchedule) -> Result<(), AppError> {
+    let updated = sqlx::query("UPDATE merchants SET settlement_schedule = $1 WHERE id = $2")
+        .bind(schedule)
+        .bind(merchant_id)
+        .execute(db)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::NotFound("merchant not found".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn report(db: &PgPool, query: SettlementReportQuery) -> Result<SettlementReport, AppError> {
+    let merchants = sqlx::query_as::<_, MerchantSettlementSummary>(
+        r#"
+        SELECT m.id AS merchant_id, m.settlement_schedule AS schedule,
+               COALESCE(SUM(b.amount) FILTER (WHERE b.status = 'paid'), 0)::BIGINT AS paid,
+               COALESCE(SUM(b.amount) FILTER (WHERE b.status IN ('pending', 'processing')), 0)::BIGINT AS in_flight,
+               (COALESCE((
+                   SELECT SUM(CASE WHEN le.direction = 'credit' THEN le.amount ELSE -le.amount END)
+                   FROM ledger_entries le
+                   WHERE le.account_type = 'merchant_payable' AND le.account_id = m.id
+               ), 0) - COALESCE(SUM(b.amount) FILTER (WHERE b.status IN ('pending', 'processing')), 0))::BIGINT
+                   AS outstanding
+        FROM merchants m
+        LEFT JOIN settlement_batches b ON b.merchant_id = m.id
+        WHERE $1::UUID IS NULL OR m.id = $1
+        GROUP BY m.id, m.settlement_schedule
+        ORDER BY m.id
+        "#,
+    )
+    .bind(query.merchant_id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let batches = sqlx::query_as::<_, SettlementBatch>(
+        r#"
+        SELECT id, merchant_id, schedule, window_start, window_end, amount, transaction_count, status,
+               payout_reference, failure_reason, paid_at, created_at, updated_at
+        FROM settlement_batches
+        WHERE ($1::UUID IS NULL OR merchant_id = $1)
+          AND ($2::DATE IS NULL OR window_start >= $2)
+          AND ($3::DATE IS NULL OR window_start < $3 + 1)
+        ORDER BY window_start DESC, merchant_id
+        "#,
+    )
+    .bind(query.merchant_id)
+    .bind(query.from)
+    .bind(query.to)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    Ok(SettlementReport { merchants, batches })
+}
@@ -0,0 +1,534 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::cache::redis_client::RedisClient;
+use crate::handlers::errors::AppError;
+use crate::models::ledger::{LedgerAccount, Posting};
+use crate::models::limits::SpendingLimits;
+use crate::models::money::Money;
+use crate::models::payee::Payee;
+use crate::models::payment::{Transaction, TransactionStatus};
+use crate::models::split::{
+    CreateSplitRequest, PaySplitShareRequest, SplitBill, SplitResponse, SplitShareResponse, SplitStatus,
+};
+use crate::services::{fee, ledger, limits, merchant, order, payee, payment};
+use crate::utils::upi_client::{PspPayment, PspStatus, UpiClient};
+
+const DEFAULT_SPLIT_TTL_SECS: i64 = 30 * 60;
+const MAX_SPLIT_TTL_SECS: i64 = 24 * 60 * 60;
+const MAX_PARTICIPANTS: usize = 20;
+const SPLIT_EXPIRED: &str = "split expired";
+
+// Every share is its own transaction, debited through the PSP like any other payment. Debited shares are
+// escrowed in the split's escrow account and only reach the merchant once the last share is in; an expired
+// split hands them back to their payers.
+pub async fn create_split(
+    db: &PgPool,
+    redis: &RedisClient,
+    user_id: Uuid,
+    req: CreateSplitRequest,
+) -> Result<SplitResponse, AppError> {
+    if req.participants.is_empty() || req.participants.len() > MAX_PARTICIPANTS {
+        return Err(AppError::bad_request(format!(
+            "a split needs between 1 and {} participants",
+            MAX_PARTICIPANTS
+        )));
+    }
+    if req.participants.iter().any(|p| !p.amount.is_positive()) {
+        return Err(AppError::bad_request("every share must be greater than 0"));
+    }
+    let ttl = req.expires_in_secs.unwrap_or(DEFAULT_SPLIT_TTL_SECS);
+    if !(1..=MAX_SPLIT_TTL_SECS).contains(&ttl) {
+        return Err(AppError::bad_request(format!(
+            "expires_in_secs must be between 1 and {}",
+            MAX_SPLIT_TTL_SECS
+        )));
+    }
+
+    let intent = merchant::parse_qr(&req.qr_data)?;
+    let total = payment::payable_amount(&intent, req.amount)?.amount;
+    let merchant = match payee::resolve_payee(db, redis, &intent.payee_address).await? {
+        Payee::Merchant(m) => m,
+        Payee::User(_) => return Err(AppError::bad_request("only merchant payments can be split")),
+    };
+    let order = match &intent.transaction_ref {
+        Some(transaction_ref) => order::find_payable_order(db, merchant.id, transaction_ref).await?,
+        None => None,
+    };
+    if order.as_ref().is_some_and(|o| o.amount != total) {
+        return Err(AppError::bad_request("amount does not match the order"));
+    }
+
+    let mut shares = Vec::with_capacity(req.participants.len() + 1);
+    let mut seen = HashSet::from([user_id]);
+    for participant in &req.participants {
+        let handle = participant.payer.trim().to_lowercase();
+        let user = payee::get_user_by_vpa(db, &handle)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("participant {} not found", handle)))?;
+        if !seen.insert(user.id) {
+            return Err(AppError::bad_request("each participant can only appear once"));
+        }
+        shares.push((user.id, participant.amount));
+    }
+    let invited = shares
+        .iter()
+        .try_fold(Money::ZERO, |sum, (_, amount)| sum.checked_add(*amount))
+        .filter(|invited| *invited <= total)
+        .ok_or_else(|| AppError::bad_request("shares add up to more than the bill"))?;
+    if invited < total {
+        shares.insert(0, (user_id, total - invited));
+    }
+
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let split = sqlx::query_as::<_, SplitBill>(
+        r#"
+        INSERT INTO split_bills (initiator_id, merchant_id, order_id, amount, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, initiator_id, merchant_id, order_id, amount, status, expires_at, completed_at, created_at, updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(merchant.id)
+    .bind(order.map(|o| o.id))
+    .bind(total)
+    .bind(Utc::now().naive_utc() + Duration::seconds(ttl))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    for (payer_id, amount) in shares {
+        sqlx::query(
+            r#"
+            INSERT INTO transactions (user_id, merchant_id, amount, status, idempotency_key, split_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(payer_id)
+        .bind(merchant.id)
+        .bind(amount)
+        .bind(TransactionStatus::Initiated)
+        .bind(format!("split:{}:{}", split.id, payer_id))
+        .bind(split.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    }
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+
+    split_response(db, split).await
+}
+
+pub async fn get_split(db: &PgPool, user_id: Uuid, split_id: Uuid) -> Result<SplitResponse, AppError> {
+    let split = sqlx::query_as::<_, SplitBill>(
+        r#"
+        SELECT s.id, s.initiator_id, s.merchant_id, s.order_id, s.amount, s.status, s.expires_at, s.completed_at,
+               s.created_at, s.updated_at
+        FROM split_bills s
+        WHERE s.id = $1
+          AND (s.initiator_id = $2 OR EXISTS (SELECT 1 FROM transactions t WHERE t.split_id = s.id AND t.user_id = $2))
+        "#,
+    )
+    .bind(split_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("split not found".to_string()))?;
+
+    split_response(db, split).await
+}
+
+// A share the PSP leaves pending keeps its funds in escrow until the callback or reconciliation settles it.
+// A declined share fails on its own, which keeps the split from completing until it expires.
+pub async fn pay_share(
+    db: &PgPool,
+    upi: &UpiClient,
+    spending_limits: &SpendingLimits,
+    user_id: Uuid,
+    split_id: Uuid,
+    req: PaySplitShareRequest,
+) -> Result<SplitResponse, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    let split = lock(&mut tx, split_id).await?;
+
+    let share = sqlx::query_as::<_, Transaction>(
+        r#"
+        SELECT id, user_id, merchant_id, payee_user_id, amount, refunded_amount, fee_amount, net_amount, refunded_fee, status, idempotency_key, upi_txn_id, error_message, order_id, promo_code_id, discount_amount, split_id, created_at, updated_at
+        FROM transactions
+        WHERE split_id = $1 AND user_id = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(split.id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("split not found".to_string()))?;
+
+    if split.status != SplitStatus::Open || split.expires_at <= Utc::now().naive_utc() {
+        return Err(AppError::Conflict("split is no longer open".to_string()));
+    }
+    if share.status != TransactionStatus::Initiated {
+        return Err(AppError::Conflict("share has already been paid".to_string()));
+    }
+
+    payment::verify_pin(&mut tx, user_id, &req.pin).await?;
+    limits::enforce(&mut tx, spending_limits, user_id, share.amount).await?;
+    payment::ensure_balance(&mut tx, user_id, share.amount).await?;
+
+    let psp_payment = match payment::send_to_psp(&mut tx, upi, &share, share.amount).await {
+        Ok(psp_payment) => psp_payment,
+        Err(AppError::PaymentDeclined(reason)) => {
+            tx.rollback().await.map_err(AppError::from_sqlx)?;
+            payment::mark_failed(db, share.id, &reason).await?;
+            return Err(AppError::PaymentDeclined(reason));
+        }
+        Err(e) => return Err(e),
+    };
+    let status = match psp_payment.status {
+        PspStatus::Pending => TransactionStatus::Pending,
+        _ => TransactionStatus::Escrowed,
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE transactions
+        SET status = $1, upi_txn_id = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $3
+        "#,
+    )
+    .bind(status)
+    .bind(&psp_payment.upi_txn_id)
+    .bind(share.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    ledger::post(
+        &mut tx,
+        Some(share.id),
+        "split share",
+        &[
+            Posting::debit(LedgerAccount::user_wallet(user_id), share.amount),
+            Posting::credit(LedgerAccount::split_escrow(split.id), share.amount),
+        ],
+    )
+    .await?;
+
+    let split = if status == TransactionStatus::Escrowed && all_escrowed(&mut tx, split.id).await? {
+        complete(&mut tx, split, share.id).await?
+    } else {
+        split
+    };
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    split_response(db, split).await
+}
+
+// The split row lock orders concurrent share payments and settlements so exactly one of them completes the
+// bill. Anything that also locks a share takes the split first.
+pub async fn lock(tx: &mut sqlx::Transaction<'_, Postgres>, split_id: Uuid) -> Result<SplitBill, AppError> {
+    sqlx::query_as::<_, SplitBill>(
+        r#"
+        SELECT id, initiator_id, merchant_id, order_id, amount, status, expires_at, completed_at, created_at, updated_at
+        FROM split_bills
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(split_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::NotFound("split not found".to_string()))
+}
+
+// Applies the PSP's final status to a share it left pending. A debited share joins the escrow and may be the
+// one that completes the bill; if its split expired meanwhile, the next expiry sweep reverses it. A declined
+// share never left the payer's bank, so it goes straight back to their wallet.
+pub async fn settle_share(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    split: SplitBill,
+    share: &Transaction,
+    psp_payment: &PspPayment,
+) -> Result<TransactionStatus, AppError> {
+    match psp_payment.status {
+        PspStatus::Pending => Ok(TransactionStatus::Pending),
+        PspStatus::Success => {
+            sqlx::query(
+                r#"
+                UPDATE transactions
+                SET status = $1, upi_txn_id = COALESCE($2, upi_txn_id), updated_at = CURRENT_TIMESTAMP
+                WHERE id = $3
+                "#,
+            )
+            .bind(TransactionStatus::Escrowed)
+            .bind(&psp_payment.upi_txn_id)
+            .bind(share.id)
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::from_sqlx)?;
+
+            if split.status != SplitStatus::Open || !all_escrowed(tx, split.id).await? {
+                return Ok(TransactionStatus::Escrowed);
+            }
+            complete(tx, split, share.id).await?;
+            Ok(TransactionStatus::Success)
+        }
+        PspStatus::Failed => {
+            let reason = psp_payment.reason.as_deref().unwrap_or("payment declined");
+            return_to_payer(tx, split.id, share.id, share.user_id, share.amount, reason).await?;
+            Ok(TransactionStatus::Failed)
+        }
+    }
+}
+
+async fn return_to_payer(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    split_id: Uuid,
+    share_id: Uuid,
+    payer_id: Uuid,
+    amount: Money,
+    reason: &str,
+) -> Result<(), AppError> {
+    ledger::post(
+        tx,
+        Some(share_id),
+        "split reversal",
+        &[
+            Posting::debit(LedgerAccount::split_escrow(split_id), amount),
+            Posting::credit(LedgerAccount::user_wallet(payer_id), amount),
+        ],
+    )
+    .await?;
+    payment::mark_failed(&mut **tx, share_id, reason).await
+}
+
+async fn all_escrowed(tx: &mut sqlx::Transaction<'_, Postgres>, split_id: Uuid) -> Result<bool, AppError> {
+    let outstanding: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE split_id = $1 AND status <> $2")
+        .bind(split_id)
+        .bind(TransactionStatus::Escrowed)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    Ok(outstanding == 0)
+}
+
+// Sweeps open splits past their expiry, failing the shares nobody paid. The PSP has already paid escrowed
+// shares out, so each is reversed with the PSP and only returned to its payer once the reversal completes;
+// one that does not stays escrowed and is tried again on the next sweep. Shares still pending with the PSP
+// wait for settle_share to escrow or fail them first.
+pub async fn expire_due(db: &PgPool, upi: &UpiClient, now: NaiveDateTime) -> Result<usize, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+
+    let split_ids: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id
+        FROM split_bills
+        WHERE status = 'open' AND expires_at <= $1
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    for split_id in &split_ids {
+        sqlx::query(
+            r#"
+            UPDATE transactions
+            SET status = $1, error_message = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE split_id = $3 AND status = 'initiated'
+            "#,
+        )
+        .bind(TransactionStatus::Failed)
+        .bind(SPLIT_EXPIRED)
+        .bind(split_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+        sqlx::query("UPDATE split_bills SET status = 'expired', updated_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(split_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::from_sqlx)?;
+    }
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+
+    // The PSP calls happen outside any database transaction so no split lock is held across the network.
+    let escrowed: Vec<(Uuid, Uuid, Money)> = sqlx::query_as(
+        r#"
+        SELECT t.id, t.split_id, t.amount
+        FROM transactions t
+        JOIN split_bills s ON s.id = t.split_id
+        WHERE s.status = 'expired' AND t.status = $1
+        "#,
+    )
+    .bind(TransactionStatus::Escrowed)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    for (share_id, split_id, amount) in escrowed {
+        match upi.reverse(share_id, amount, SPLIT_EXPIRED).await {
+            Ok(reversal) if reversal.status == PspStatus::Success => {
+                if let Err(e) = refund_share(db, split_id, share_id).await {
+                    log::error!("returning split share {} failed: {}", share_id, e);
+                }
+            }
+            Ok(reversal) => log::warn!(
+                "reversal {} of split share {} is {:?}",
+                reversal.reversal_id,
+                share_id,
+                reversal.status
+            ),
+            Err(e) => log::warn!("reversing split share {} failed: {}", share_id, e),
+        }
+    }
+
+    Ok(split_ids.len())
+}
+
+async fn refund_share(db: &PgPool, split_id: Uuid, share_id: Uuid) -> Result<(), AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+    lock(&mut tx, split_id).await?;
+    let share: Option<(Uuid, Money)> =
+        sqlx::query_as("SELECT user_id, amount FROM transactions WHERE id = $1 AND status = $2 FOR UPDATE")
+            .bind(share_id)
+            .bind(TransactionStatus::Escrowed)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(AppError::from_sqlx)?;
+    if let Some((payer_id, amount)) = share {
+        return_to_payer(&mut tx, split_id, share_id, payer_id, amount, SPLIT_EXPIRED).await?;
+    }
+    tx.commit().await.map_err(AppError::from_sqlx)
+}
+
+// The MDR is worked out on the whole bill, so splitting cannot move a payment into a cheaper slab,
+// then spread over the shares in proportion to their size.
+async fn complete(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    split: SplitBill,
+    last_share_id: Uuid,
+) -> Result<SplitBill, AppError> {
+    if let Some(order_id) = split.order_id {
+        order::claim_order(tx, order_id, last_share_id).await?;
+    }
+
+    let total_fee = fee::merchant_fee(tx, split.merchant_id, split.amount).await?;
+    let shares: Vec<(Uuid, Money)> =
+        sqlx::query_as("SELECT id, amount FROM transactions WHERE split_id = $1 ORDER BY created_at, id")
+            .bind(split.id)
+            .fetch_all(&mut **tx)
+            .await
+            .map_err(AppError::from_sqlx)?;
+
+    let mut fee_left = total_fee;
+    for (i, (share_id, amount)) in shares.iter().enumerate() {
+        let share_fee = if i + 1 == shares.len() {
+            fee_left
+        } else {
+            fee::prorate(total_fee, amount.paise(), split.amount.paise())
+        };
+        fee_left = fee_left - share_fee;
+
+        sqlx::query(
+            r#"
+            UPDATE transactions
+            SET status = $1, fee_amount = $2, updated_at = CURRENT_TIMESTAMP, completed_at = CURRENT_TIMESTAMP
+            WHERE id = $3
+            "#,
+        )
+        .bind(TransactionStatus::Success)
+        .bind(share_fee)
+        .bind(share_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+        let postings = [
+            Posting::debit(LedgerAccount::split_escrow(split.id), *amount),
+            Posting::credit(LedgerAccount::merchant_payable(split.merchant_id), *amount - share_fee),
+            Posting::credit(LedgerAccount::fee_revenue(), share_fee),
+        ];
+        let postings: Vec<Posting> = postings.into_iter().filter(|p| p.amount.is_positive()).collect();
+        ledger::post(tx, Some(*share_id), "split payment", &postings).await?;
+    }
+
+    sqlx::query_as::<_, SplitBill>(
+        r#"
+        UPDATE split_bills
+        SET status = 'completed', completed_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        RETURNING id, initiator_id, merchant_id, order_id, amount, status, expires_at, completed_at, created_at, updated_at
+        "#,
+    )
+    .bind(split.id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+async fn split_response(db: &PgPool, split: SplitBill) -> Result<SplitResponse, AppError> {
+    let merchant_name: String = sqlx::query_scalar("SELECT name FROM merchants WHERE id = $1")
+        .bind(split.merchant_id)
+        .fetch_one(db)
+        .await
+        .map_err(AppError::from_sqlx)?;
+
+    let rows: Vec<(Uuid, Uuid, String, String, Money, TransactionStatus)> = sqlx::query_as(
+        r#"
+        SELECT t.id, t.user_id, u.name, u.upi_id, t.amount, t.status
+        FROM transactions t
+        JOIN users u ON u.id = t.user_id
+        WHERE t.split_id = $1
+        ORDER BY t.created_at, t.id
+        "#,
+    )
+    .bind(split.id)
+    .fetch_all(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let paid_amount = rows
+        .iter()
+        .filter(|row| matches!(
+                row.5,
+                TransactionStatus::Pending
+                    | TransactionStatus::Escrowed
+                    | TransactionStatus::Success
+                    | TransactionStatus::Refunded
+            ))
+        .fold(Money::ZERO, |sum, row| sum + row.4);
+    let shares = rows
+        .into_iter()
+        .map(|(transaction_id, user_id, name, upi_id, amount, status)| SplitShareResponse {
+            transaction_id,
+            user_id,
+            name,
+            upi_id,
+            amount,
+            status: format!("{:?}", status).to_lowercase(),
+        })
+        .collect();
+
+    Ok(SplitResponse {
+        split_id: split.id,
+        merchant_id: split.merchant_id,
+        merchant_name,
+        amount: split.amount,
+        paid_amount,
+        status: split.status,
+        expires_at: split.expires_at,
+        shares,
+    })
+}
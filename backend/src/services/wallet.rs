@@ -0,0 +1,191 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::handlers::errors::AppError;
+use crate::models::ledger::{LedgerAccount, Posting};
+use crate::models::money::Money;
+use crate::models::wallet::{TopUpRequest, TopUpResponse, TopUpStatus, WalletTopUp};
+use crate::services::ledger;
+use crate::utils::funding_source::{FundingOutcome, FundingRequest, FundingSource};
+
+const MAX_TOPUP: Money = Money::from_rupees(200_000);
+
+pub async fn top_up(
+    db: &PgPool,
+    funding: &dyn FundingSource,
+    user_id: Uuid,
+    req: TopUpRequest,
+) -> Result<TopUpResponse, AppError> {
+    if !req.amount.is_positive() {
+        return Err(AppError::bad_request("amount must be greater than 0"));
+    }
+    if req.amount > MAX_TOPUP {
+        return Err(AppError::bad_request(format!("amount must not exceed {}", MAX_TOPUP)));
+    }
+    if req.instrument.trim().is_empty() {
+        return Err(AppError::bad_request("instrument is required"));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO wallet_topups (user_id, amount, funding_source, instrument, idempotency_key)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (idempotency_key) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(req.amount)
+    .bind(funding.name())
+    .bind(req.instrument.trim())
+    .bind(&req.idempotency_key)
+    .execute(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let topup = sqlx::query_as::<_, WalletTopUp>(
+        r#"
+        SELECT id, user_id, amount, status, funding_source, instrument, idempotency_key, provider_reference,
+               failure_reason, created_at, updated_at
+        FROM wallet_topups
+        WHERE idempotency_key = $1 AND user_id = $2
+        "#,
+    )
+    .bind(&req.idempotency_key)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)?
+    .ok_or_else(|| AppError::Conflict("idempotency key already used".to_string()))?;
+
+    // Finished top-ups are replayed; initiated and pending ones (re)try the charge.
+    if matches!(topup.status, TopUpStatus::Success | TopUpStatus::Failed) {
+        return topup_response(db, topup).await;
+    }
+
+    let request = FundingRequest {
+        topup_id: topup.id,
+        user_id,
+        amount: topup.amount,
+        instrument: topup.instrument.clone(),
+    };
+    let topup = match funding.charge(&request).await {
+        Ok(FundingOutcome::Captured { provider_reference }) => credit_topup(db, topup.id, &provider_reference).await?,
+        Ok(FundingOutcome::Declined { reason }) => {
+            finish_topup(db, topup.id, TopUpStatus::Failed, &reason).await?
+        }
+        Err(e) => finish_topup(db, topup.id, TopUpStatus::Pending, &e.to_string()).await?,
+    };
+
+    topup_response(db, topup).await
+}
+
+async fn credit_topup(db: &PgPool, topup_id: Uuid, provider_reference: &str) -> Result<WalletTopUp, AppError> {
+    let mut tx = db.begin().await.map_err(AppError::from_sqlx)?;
+
+    let topup = sqlx::query_as::<_, WalletTopUp>(
+        r#"
+        UPDATE wallet_topups
+        SET status = $1, provider_reference = $2, failure_reason = NULL, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $3 AND status IN ('initiated', 'pending')
+        RETURNING id, user_id, amount, status, funding_source, instrument, idempotency_key, provider_reference,
+                  failure_reason, created_at, updated_at
+        "#,
+    )
+    .bind(TopUpStatus::Success)
+    .bind(provider_reference)
+    .bind(topup_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    let Some(topup) = topup else {
+        // Another request already settled this top-up.
+        tx.commit().await.map_err(AppError::from_sqlx)?;
+        return get_topup_by_id(db, topup_id).await;
+    };
+
+    ledger::post(
+        &mut tx,
+        None,
+        &format!("wallet top-up {}", topup.id),
+        &[
+            Posting::debit(LedgerAccount::funding(), topup.amount),
+            Posting::credit(LedgerAccount::user_wallet(topup.user_id), topup.amount),
+        ],
+    )
+    .await?;
+
+    tx.commit().await.map_err(AppError::from_sqlx)?;
+    Ok(topup)
+}
+
+async fn finish_topup(
+    db: &PgPool,
+    topup_id: Uuid,
+    status: TopUpStatus,
+    failure_reason: &str,
+) -> Result<WalletTopUp, AppError> {
+    let topup = sqlx::query_as::<_, WalletTopUp>(
+        r#"
+        UPDATE wallet_topups
+        SET status = $1, failure_reason = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $3 AND status IN ('initiated', 'pending')
+        RETURNING id, user_id, amount, status, funding_source, instrument, idempotency_key, provider_reference,
+                  failure_reason, created_at, updated_at
+        "#,
+    )
+    .bind(status)
+    .bind(failure_reason)
+    .bind(topup_id)
+    .fetch_optional(db)
+    .await
+    .map_err(AppError::from_sqlx)?;
+
+    match topup {
+        Some(topup) => Ok(topup),
+        None => get_topup_by_id(db, topup_id).await,
+    }
+}
+
+async fn get_topup_by_id(db: &PgPool, topup_id: Uuid) -> Result<WalletTopUp, AppError> {
+    sqlx::query_as::<_, WalletTopUp>(
+        r#"
+        SELECT id, user_id, amount, status, funding_source, instrument, idempotency_key, provider_reference,
+               failure_reason, created_at, updated_at
+        FROM wallet_topups
+        WHERE id = $1
+        "#,
+    )
+    .bind(topup_id)
+    .fetch_one(db)
+    .await
+    .map_err(AppError::from_sqlx)
+}
+
+pub async fn get_topup(db: &PgPool, user_id: Uuid, topup_id: Uuid) -> Result<TopUpResponse, AppError> {
+    let topup = get_topup_by_id(db, topup_id).await?;
+    if topup.user_id != user_id {
+        return Err(AppError::NotFound("not found".to_string()));
+    }
+    topup_response(db, topup).await
+}
+
+pub async fn balance(db: &PgPool, user_id: Uuid) -> Result<Money, AppError> {
+    let balance: (Money,) = sqlx::query_as("SELECT balance FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+        .map_err(AppError::from_sqlx)?;
+    Ok(balance.0)
+}
+
+async fn topup_response(db: &PgPool, topup: WalletTopUp) -> Result<TopUpResponse, AppError> {
+    Ok(TopUpResponse {
+        balance: balance(db, topup.user_id).await?,
+        topup_id: topup.id,
+        amount: topup.amount,
+        status: topup.status,
+        provider_reference: topup.provider_reference,
+        failure_reason: topup.failure_reason,
+    })
+}
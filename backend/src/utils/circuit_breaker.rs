@@ -1 +1,242 @@
-pub struct CircuitBreaker;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+// The circuit opens on either a run of consecutive failures or a failure rate over the last
+// `window_size` calls (once at least `minimum_calls` have been seen), stays open for `cool_down_ms`,
+// then admits `half_open_max_calls` probes which must all succeed before it closes again.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitBreakerConfig {
+    pub consecutive_failures: u32,
+    pub failure_rate: f64,
+    pub window_size: usize,
+    pub minimum_calls: usize,
+    pub cool_down_ms: u64,
+    pub half_open_max_calls: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            consecutive_failures: 5,
+            failure_rate: 0.5,
+            window_size: 20,
+            minimum_calls: 10,
+            cool_down_ms: 30_000,
+            half_open_max_calls: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitSnapshot {
+    pub name: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub failure_rate: f64,
+    pub calls_in_window: usize,
+    pub times_opened: u64,
+    pub retry_after_ms: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CircuitError<E> {
+    Open,
+    Inner(E),
+}
+
+pub struct CircuitBreaker {
+    name: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    state: CircuitState,
+    outcomes: VecDeque<bool>,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    half_open_in_flight: u32,
+    half_open_successes: u32,
+    times_opened: u64,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: impl Into<String>, config: CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            name: name.into(),
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                outcomes: VecDeque::new(),
+                consecutive_failures: 0,
+                opened_at: None,
+                half_open_in_flight: 0,
+                half_open_successes: 0,
+                times_opened: 0,
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.lock().state
+    }
+
+    pub fn snapshot(&self) -> CircuitSnapshot {
+        let inner = self.lock();
+        let failures = inner.outcomes.iter().filter(|failed| **failed).count();
+        let retry_after_ms = match (inner.state, inner.opened_at) {
+            (CircuitState::Open, Some(opened_at)) => {
+                Some(self.cool_down().saturating_sub(opened_at.elapsed()).as_millis() as u64)
+            }
+            _ => None,
+        };
+        CircuitSnapshot {
+            name: self.name.clone(),
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            failure_rate: if inner.outcomes.is_empty() {
+                0.0
+            } else {
+                failures as f64 / inner.outcomes.len() as f64
+            },
+            calls_in_window: inner.outcomes.len(),
+            times_opened: inner.times_opened,
+            retry_after_ms,
+        }
+    }
+
+    // Runs `call` unless the circuit is open. Only errors for which `is_failure` holds count against
+    // the circuit, so a caller can keep business rejections from tripping it.
+    pub async fn call<T, E, F>(&self, call: F, is_failure: impl Fn(&E) -> bool) -> Result<T, CircuitError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let permit = self.acquire().ok_or(CircuitError::Open)?;
+        let result = call.await;
+        permit.record(matches!(&result, Err(e) if is_failure(e)));
+        result.map_err(CircuitError::Inner)
+    }
+
+    fn acquire(&self) -> Option<Permit<'_>> {
+        let mut inner = self.lock();
+        let probe = match inner.state {
+            CircuitState::Closed => false,
+            CircuitState::Open => {
+                if inner.opened_at.is_none_or(|at| at.elapsed() >= self.cool_down()) {
+                    inner.state = CircuitState::HalfOpen;
+                    inner.half_open_in_flight = 0;
+                    inner.half_open_successes = 0;
+                    true
+                } else {
+                    return None;
+                }
+            }
+            CircuitState::HalfOpen if inner.half_open_in_flight < self.config.half_open_max_calls => true,
+            CircuitState::HalfOpen => return None,
+        };
+        if probe {
+            inner.half_open_in_flight += 1;
+        }
+        Some(Permit {
+            breaker: self,
+            probe,
+            recorded: false,
+        })
+    }
+
+    fn on_result(&self, probe: bool, failed: bool) {
+        let mut inner = self.lock();
+        if probe {
+            inner.half_open_in_flight = inner.half_open_in_flight.saturating_sub(1);
+            // Another probe may already have reopened or closed the circuit.
+            if inner.state != CircuitState::HalfOpen {
+                return;
+            }
+            if failed {
+                self.trip(&mut inner);
+            } else {
+                inner.half_open_successes += 1;
+                if inner.half_open_successes >= self.config.half_open_max_calls {
+                    inner.state = CircuitState::Closed;
+                    inner.outcomes.clear();
+                    inner.consecutive_failures = 0;
+                    inner.opened_at = None;
+                }
+            }
+            return;
+        }
+
+        if inner.state != CircuitState::Closed {
+            return;
+        }
+        inner.outcomes.push_back(failed);
+        while inner.outcomes.len() > self.config.window_size {
+            inner.outcomes.pop_front();
+        }
+        inner.consecutive_failures = if failed { inner.consecutive_failures + 1 } else { 0 };
+
+        let failures = inner.outcomes.iter().filter(|failed| **failed).count();
+        let rate_exceeded = inner.outcomes.len() >= self.config.minimum_calls
+            && failures as f64 >= self.config.failure_rate * inner.outcomes.len() as f64;
+        if inner.consecutive_failures >= self.config.consecutive_failures || (failed && rate_exceeded) {
+            self.trip(&mut inner);
+        }
+    }
+
+    fn trip(&self, inner: &mut Inner) {
+        inner.state = CircuitState::Open;
+        inner.opened_at = Some(Instant::now());
+        inner.outcomes.clear();
+        inner.half_open_successes = 0;
+        inner.times_opened += 1;
+        log::warn!("circuit {} opened", self.name);
+    }
+
+    fn cool_down(&self) -> Duration {
+        Duration::from_millis(self.config.cool_down_ms)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// A call admitted by the breaker. If the caller's future is dropped before it finishes, the permit
+// gives its half-open slot back without counting as a success or a failure.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Permit<'_> {
+    fn record(mut self, failed: bool) {
+        self.recorded = true;
+        self.breaker.on_result(self.probe, failed);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.recorded && self.probe {
+            let mut inner = self.breaker.lock();
+            inner.half_open_in_flight = inner.half_open_in_flight.saturating_sub(1);
+        }
+    }
+}
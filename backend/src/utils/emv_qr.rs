@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+
+use crate::models::money::Money;
+use crate::utils::upi_intent::{canonical_vpa, UpiIntent};
+
+const PAYLOAD_FORMAT_INDICATOR: &str = "000201";
+const CRC_TAG: &str = "63";
+const INR_NUMERIC: &str = "356";
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum EmvQrError {
+    #[error("malformed emv qr payload")]
+    InvalidTlv,
+    #[error("emv qr payload is missing {0}")]
+    MissingField(&'static str),
+    #[error("invalid {0} in emv qr payload")]
+    InvalidField(&'static str),
+    #[error("emv qr checksum mismatch")]
+    CrcMismatch,
+    #[error("emv qr payload has no upi merchant account")]
+    NoUpiAccount,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerchantAccount {
+    pub tag: String,
+    pub globally_unique_id: Option<String>,
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmvQr {
+    pub point_of_initiation: Option<String>,
+    pub merchant_accounts: Vec<MerchantAccount>,
+    pub merchant_category_code: String,
+    pub currency: String,
+    pub amount: Option<Money>,
+    pub country_code: String,
+    pub merchant_name: String,
+    pub merchant_city: String,
+    pub postal_code: Option<String>,
+    pub additional_data: BTreeMap<String, String>,
+}
+
+pub fn is_emv_payload(raw: &str) -> bool {
+    raw.trim().starts_with(PAYLOAD_FORMAT_INDICATOR)
+}
+
+impl EmvQr {
+    pub fn parse(raw: &str) -> Result<Self, EmvQrError> {
+        let raw = raw.trim();
+        if !is_emv_payload(raw) {
+            return Err(EmvQrError::MissingField("payload format indicator (00)"));
+        }
+
+        let fields = parse_tlv(raw)?;
+        verify_crc(raw, &fields)?;
+
+        let mut merchant_accounts = Vec::new();
+        let mut top: BTreeMap<String, String> = BTreeMap::new();
+        for (tag, value) in fields {
+            let id: u8 = tag.parse().map_err(|_| EmvQrError::InvalidTlv)?;
+            if (2..=51).contains(&id) {
+                merchant_accounts.push(merchant_account(tag, &value)?);
+            } else if top.insert(tag, value).is_some() {
+                return Err(EmvQrError::InvalidTlv);
+            }
+        }
+        if merchant_accounts.is_empty() {
+            return Err(EmvQrError::MissingField("merchant account information (02-51)"));
+        }
+
+        let mut required = |tag: &str, name: &'static str| top.remove(tag).ok_or(EmvQrError::MissingField(name));
+
+        let merchant_category_code = required("52", "merchant category code (52)")?;
+        if merchant_category_code.len() != 4 || !merchant_category_code.chars().all(|c| c.is_ascii_digit()) {
+            return Err(EmvQrError::InvalidField("merchant category code (52)"));
+        }
+
+        let currency = required("53", "transaction currency (53)")?;
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_digit()) {
+            return Err(EmvQrError::InvalidField("transaction currency (53)"));
+        }
+
+        let country_code = required("58", "country code (58)")?;
+        let merchant_name = required("59", "merchant name (59)")?;
+        let merchant_city = required("60", "merchant city (60)")?;
+
+        let amount = top
+            .remove("54")
+            .map(|am| parse_amount(&am, "transaction amount (54)"))
+            .transpose()?;
+
+        let point_of_initiation = top.remove("01");
+        if point_of_initiation.as_deref().is_some_and(|p| p != "11" && p != "12") {
+            return Err(EmvQrError::InvalidField("point of initiation method (01)"));
+        }
+
+        let additional_data = match top.remove("62") {
+            Some(value) => parse_tlv(&value)?.into_iter().collect(),
+            None => BTreeMap::new(),
+        };
+
+        Ok(EmvQr {
+            point_of_initiation,
+            merchant_accounts,
+            merchant_category_code,
+            currency,
+            amount,
+            country_code,
+            merchant_name,
+            merchant_city,
+            postal_code: top.remove("61"),
+            additional_data,
+        })
+    }
+
+    pub fn upi_vpa(&self) -> Option<String> {
+        self.upi_account().map(|(vpa, _)| vpa)
+    }
+
+    fn upi_account(&self) -> Option<(String, &MerchantAccount)> {
+        self.merchant_accounts.iter().find_map(|account| {
+            let vpa = account.fields.values().find_map(|value| canonical_vpa(value))?;
+            Some((vpa, account))
+        })
+    }
+
+    pub fn into_upi_intent(self) -> Result<UpiIntent, EmvQrError> {
+        let (payee_address, account) = self.upi_account().ok_or(EmvQrError::NoUpiAccount)?;
+        let min_amount = account
+            .fields
+            .get("02")
+            .map(|mam| parse_amount(mam, "minimum amount"))
+            .transpose()?;
+
+        let currency = if self.currency == INR_NUMERIC {
+            "INR".to_string()
+        } else {
+            self.currency
+        };
+
+        Ok(UpiIntent {
+            payee_address,
+            payee_name: Some(self.merchant_name),
+            amount: self.amount,
+            min_amount,
+            currency: Some(currency),
+            transaction_note: self.additional_data.get("08").cloned(),
+            transaction_ref: self
+                .additional_data
+                .get("05")
+                .or_else(|| self.additional_data.get("01"))
+                .cloned(),
+            merchant_code: Some(self.merchant_category_code),
+            mode: None,
+            org_id: None,
+            url: None,
+            signature: None,
+        })
+    }
+}
+
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn parse_tlv(raw: &str) -> Result<Vec<(String, String)>, EmvQrError> {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut fields = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        if pos + 4 > chars.len() {
+            return Err(EmvQrError::InvalidTlv);
+        }
+        let tag: String = chars[pos..pos + 2].iter().collect();
+        let len: String = chars[pos + 2..pos + 4].iter().collect();
+        if !tag.chars().chain(len.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(EmvQrError::InvalidTlv);
+        }
+        let len: usize = len.parse().map_err(|_| EmvQrError::InvalidTlv)?;
+        let start = pos + 4;
+        if len == 0 || start + len > chars.len() {
+            return Err(EmvQrError::InvalidTlv);
+        }
+        fields.push((tag, chars[start..start + len].iter().collect()));
+        pos = start + len;
+    }
+
+    Ok(fields)
+}
+
+fn verify_crc(raw: &str, fields: &[(String, String)]) -> Result<(), EmvQrError> {
+    let (tag, value) = fields.last().ok_or(EmvQrError::InvalidTlv)?;
+    if tag != CRC_TAG {
+        return Err(EmvQrError::MissingField("crc (63)"));
+    }
+    if value.len() != 4 || fields.iter().filter(|(t, _)| t == CRC_TAG).count() != 1 {
+        return Err(EmvQrError::InvalidField("crc (63)"));
+    }
+    let expected = u16::from_str_radix(value, 16).map_err(|_| EmvQrError::InvalidField("crc (63)"))?;
+
+    let signed = &raw[..raw.len() - value.len()];
+    if crc16_ccitt(signed.as_bytes()) != expected {
+        return Err(EmvQrError::CrcMismatch);
+    }
+    Ok(())
+}
+
+fn merchant_account(tag: String, value: &str) -> Result<MerchantAccount, EmvQrError> {
+    let id: u8 = tag.parse().map_err(|_| EmvQrError::InvalidTlv)?;
+    // 02-25 carry a raw network identifier (card PAN, merchant id); 26-51 are nested templates.
+    if id < 26 {
+        return Ok(MerchantAccount {
+            tag,
+            globally_unique_id: None,
+            fields: BTreeMap::from([("00".to_string(), value.to_string())]),
+        });
+    }
+
+    let fields: BTreeMap<String, String> = parse_tlv(value)?.into_iter().collect();
+    Ok(MerchantAccount {
+        tag,
+        globally_unique_id: fields.get("00").cloned(),
+        fields,
+    })
+}
+
+fn parse_amount(raw: &str, field: &'static str) -> Result<Money, EmvQrError> {
+    if raw.len() > 13 {
+        return Err(EmvQrError::InvalidField(field));
+    }
+    match raw.parse::<Money>() {
+        Ok(amount) if amount.is_positive() => Ok(amount),
+        _ => Err(EmvQrError::InvalidField(field)),
+    }
+}
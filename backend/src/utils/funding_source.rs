@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum FundingError {
+    #[error("funding provider unavailable: {0}")]
+    Unavailable(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FundingRequest {
+    pub topup_id: Uuid,
+    pub user_id: Uuid,
+    pub amount: Money,
+    pub instrument: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FundingOutcome {
+    Captured { provider_reference: String },
+    Declined { reason: String },
+}
+
+// A bank account, card or other rail the wallet can pull money from. Charges must be idempotent on `topup_id`.
+#[async_trait]
+pub trait FundingSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn charge(&self, request: &FundingRequest) -> Result<FundingOutcome, FundingError>;
+}
+
+// The providers the app can be configured with through FUNDING_SOURCE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FundingProvider {
+    SimulatedBank,
+}
+
+impl FundingProvider {
+    pub fn build(self) -> Arc<dyn FundingSource> {
+        match self {
+            FundingProvider::SimulatedBank => Arc::new(SimulatedBank),
+        }
+    }
+}
+
+// Stand-in provider for dev and tests: instruments starting with "decline" are refused and "unavailable" ones time out.
+pub struct SimulatedBank;
+
+const SIMULATED_LIMIT: Money = Money::from_rupees(100_000);
+
+#[async_trait]
+impl FundingSource for SimulatedBank {
+    fn name(&self) -> &'static str {
+        "simulated_bank"
+    }
+
+    async fn charge(&self, request: &FundingRequest) -> Result<FundingOutcome, FundingError> {
+        if request.instrument.starts_with("unavailable") {
+            return Err(FundingError::Unavailable("simulated timeout".to_string()));
+        }
+        if request.instrument.starts_with("decline") {
+            return Ok(FundingOutcome::Declined {
+                reason: "declined by issuer".to_string(),
+            });
+        }
+        if request.amount > SIMULATED_LIMIT {
+            return Ok(FundingOutcome::Declined {
+                reason: "amount exceeds instrument limit".to_string(),
+            });
+        }
+        Ok(FundingOutcome::Captured {
+            provider_reference: format!("SIM{}", request.topup_id.simple()).to_uppercase(),
+        })
+    }
+}
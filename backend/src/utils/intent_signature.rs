@@ -0,0 +1,44 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum SignatureError {
+    #[error("malformed qr signature")]
+    MalformedSignature,
+    #[error("malformed merchant public key")]
+    MalformedKey,
+}
+
+pub fn decode_public_key(raw: &str) -> Result<VerifyingKey, SignatureError> {
+    let bytes: [u8; 32] = decode_base64(raw)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(SignatureError::MalformedKey)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| SignatureError::MalformedKey)
+}
+
+pub fn verify(message: &str, signature: &str, public_keys: &[String]) -> Result<bool, SignatureError> {
+    // An unencoded '+' in the query string decodes to a space.
+    let signature = signature.replace(' ', "+");
+    let bytes: [u8; 64] = decode_base64(&signature)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(SignatureError::MalformedSignature)?;
+    let signature = Signature::from_bytes(&bytes);
+
+    // One bad key on file must not stop the merchant's other keys from verifying.
+    for key in public_keys {
+        match decode_public_key(key) {
+            Ok(key) if key.verify(message.as_bytes(), &signature).is_ok() => return Ok(true),
+            Ok(_) => {}
+            Err(e) => log::warn!("skipping merchant key {:?}: {}", key, e),
+        }
+    }
+    Ok(false)
+}
+
+fn decode_base64(raw: &str) -> Option<Vec<u8>> {
+    let raw = raw.trim();
+    [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(raw).ok())
+}
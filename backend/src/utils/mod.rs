@@ -1,2 +1,8 @@
 pub mod circuit_breaker;
+pub mod emv_qr;
+pub mod funding_source;
+pub mod intent_signature;
+pub mod qr_decoder;
+pub mod qr_render;
 pub mod upi_client;
+pub mod upi_intent;
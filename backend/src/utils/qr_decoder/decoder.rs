@@ -0,0 +1,445 @@
+use super::reed_solomon;
+use super::{BitGrid, QrDecodeError};
+
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [
+        0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28,
+        28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    ],
+    [
+        0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+];
+
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [
+        0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18,
+        19, 19, 20, 21, 22, 24, 25,
+    ],
+    [
+        0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29,
+        31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+    ],
+    [
+        0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38,
+        40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68,
+    ],
+    [
+        0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45,
+        48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81,
+    ],
+];
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl EcLevel {
+    fn from_bits(bits: u32) -> Self {
+        match bits & 0b11 {
+            0b01 => EcLevel::L,
+            0b00 => EcLevel::M,
+            0b11 => EcLevel::Q,
+            _ => EcLevel::H,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            EcLevel::L => 0,
+            EcLevel::M => 1,
+            EcLevel::Q => 2,
+            EcLevel::H => 3,
+        }
+    }
+}
+
+pub fn version_for_dimension(dimension: usize) -> Option<usize> {
+    if !(21..=177).contains(&dimension) || dimension % 4 != 1 {
+        return None;
+    }
+    Some((dimension - 17) / 4)
+}
+
+pub fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let last = version * 4 + 17 - 7;
+    let mut positions = vec![6; count];
+    for (i, slot) in positions.iter_mut().skip(1).rev().enumerate() {
+        *slot = last - i * step;
+    }
+    positions
+}
+
+pub fn decode_grid(grid: &BitGrid) -> Result<Vec<u8>, QrDecodeError> {
+    let dimension = grid.dimension;
+    let version = version_for_dimension(dimension).ok_or(QrDecodeError::NotFound)?;
+    if version >= 7 && read_version(grid) != Some(version) {
+        return Err(QrDecodeError::NotFound);
+    }
+
+    let (ec_level, mask) = read_format(grid).ok_or(QrDecodeError::NotFound)?;
+    let function = function_patterns(version);
+    let raw = read_codewords(grid, &function, mask, total_codewords(version));
+    let data = correct_blocks(&raw, version, ec_level)?;
+    parse_segments(&data, version)
+}
+
+fn bch_remainder(value: u32, generator: u32) -> u32 {
+    let degree = 31 - generator.leading_zeros();
+    let mut rem = value;
+    while rem != 0 && 31 - rem.leading_zeros() >= degree {
+        rem ^= generator << (31 - rem.leading_zeros() - degree);
+    }
+    rem
+}
+
+fn format_codeword(data: u32) -> u32 {
+    ((data << 10) | bch_remainder(data << 10, 0x537)) ^ 0x5412
+}
+
+fn version_codeword(version: u32) -> u32 {
+    (version << 12) | bch_remainder(version << 12, 0x1F25)
+}
+
+fn read_format(grid: &BitGrid) -> Option<(EcLevel, u8)> {
+    let dim = grid.dimension;
+    let mut first = 0u32;
+    let mut second = 0u32;
+    let push = |bits: &mut u32, x: usize, y: usize| *bits = (*bits << 1) | grid.get(x, y) as u32;
+
+    for x in 0..6 {
+        push(&mut first, x, 8);
+    }
+    push(&mut first, 7, 8);
+    push(&mut first, 8, 8);
+    push(&mut first, 8, 7);
+    for y in (0..6).rev() {
+        push(&mut first, 8, y);
+    }
+
+    for y in (dim - 7..dim).rev() {
+        push(&mut second, 8, y);
+    }
+    for x in dim - 8..dim {
+        push(&mut second, x, 8);
+    }
+
+    let mut best: Option<(u32, u32)> = None;
+    for data in 0..32u32 {
+        let codeword = format_codeword(data);
+        for read in [first, second] {
+            let distance = (codeword ^ read).count_ones();
+            if best.is_none_or(|(_, d)| distance < d) {
+                best = Some((data, distance));
+            }
+        }
+    }
+
+    match best {
+        Some((data, distance)) if distance <= 3 => Some((EcLevel::from_bits(data >> 3), (data & 0b111) as u8)),
+        _ => None,
+    }
+}
+
+fn read_version(grid: &BitGrid) -> Option<usize> {
+    let dim = grid.dimension;
+    let mut first = 0u32;
+    let mut second = 0u32;
+    for j in (0..6).rev() {
+        for i in (dim - 11..=dim - 9).rev() {
+            first = (first << 1) | grid.get(i, j) as u32;
+            second = (second << 1) | grid.get(j, i) as u32;
+        }
+    }
+
+    (7..=40u32)
+        .map(|version| {
+            let codeword = version_codeword(version);
+            let distance = (codeword ^ first).count_ones().min((codeword ^ second).count_ones());
+            (version, distance)
+        })
+        .min_by_key(|&(_, distance)| distance)
+        .filter(|&(_, distance)| distance <= 3)
+        .map(|(version, _)| version as usize)
+}
+
+struct FunctionMask {
+    dimension: usize,
+    cells: Vec<bool>,
+}
+
+impl FunctionMask {
+    fn set_region(&mut self, left: usize, top: usize, width: usize, height: usize) {
+        for y in top..top + height {
+            for x in left..left + width {
+                self.cells[y * self.dimension + x] = true;
+            }
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.dimension + x]
+    }
+}
+
+fn function_patterns(version: usize) -> FunctionMask {
+    let dim = version * 4 + 17;
+    let mut mask = FunctionMask {
+        dimension: dim,
+        cells: vec![false; dim * dim],
+    };
+
+    mask.set_region(0, 0, 9, 9);
+    mask.set_region(dim - 8, 0, 8, 9);
+    mask.set_region(0, dim - 8, 9, 8);
+
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    for (i, &x) in positions.iter().enumerate() {
+        for (j, &y) in positions.iter().enumerate() {
+            let overlaps_finder = (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+            if !overlaps_finder {
+                mask.set_region(x - 2, y - 2, 5, 5);
+            }
+        }
+    }
+
+    mask.set_region(6, 9, 1, dim - 17);
+    mask.set_region(9, 6, dim - 17, 1);
+
+    if version >= 7 {
+        mask.set_region(dim - 11, 0, 3, 6);
+        mask.set_region(0, dim - 11, 6, 3);
+    }
+    mask
+}
+
+fn masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (y + x).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (y + x).is_multiple_of(3),
+        4 => (y / 2 + x / 3).is_multiple_of(2),
+        5 => (y * x) % 2 + (y * x) % 3 == 0,
+        6 => ((y * x) % 2 + (y * x) % 3).is_multiple_of(2),
+        _ => ((y + x) % 2 + (y * x) % 3).is_multiple_of(2),
+    }
+}
+
+fn total_codewords(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let count = version / 7 + 2;
+        modules -= (25 * count - 10) * count - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules / 8
+}
+
+fn read_codewords(grid: &BitGrid, function: &FunctionMask, mask: u8, total: usize) -> Vec<u8> {
+    let dim = grid.dimension;
+    let mut codewords = Vec::with_capacity(total);
+    let mut current = 0u8;
+    let mut bits = 0;
+    let mut upward = true;
+
+    let mut right = dim - 1;
+    while right > 0 {
+        if right == 6 {
+            right -= 1;
+        }
+        for count in 0..dim {
+            let y = if upward { dim - 1 - count } else { count };
+            for col in 0..2 {
+                let x = right - col;
+                if function.get(x, y) {
+                    continue;
+                }
+                current = (current << 1) | (grid.get(x, y) ^ masked(mask, x, y)) as u8;
+                bits += 1;
+                if bits == 8 {
+                    codewords.push(current);
+                    current = 0;
+                    bits = 0;
+                }
+            }
+        }
+        upward = !upward;
+        if right < 2 {
+            break;
+        }
+        right -= 2;
+    }
+
+    codewords.truncate(total);
+    codewords
+}
+
+fn correct_blocks(raw: &[u8], version: usize, ec_level: EcLevel) -> Result<Vec<u8>, QrDecodeError> {
+    let total = total_codewords(version);
+    if raw.len() != total {
+        return Err(QrDecodeError::Corrupted);
+    }
+
+    let ec_len = ECC_CODEWORDS_PER_BLOCK[ec_level.index()][version] as usize;
+    let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[ec_level.index()][version] as usize;
+    let short_len = total / num_blocks;
+    let num_short = num_blocks - total % num_blocks;
+    let short_data = short_len - ec_len;
+
+    let mut blocks: Vec<Vec<u8>> = (0..num_blocks)
+        .map(|i| Vec::with_capacity(if i < num_short { short_len } else { short_len + 1 }))
+        .collect();
+
+    let mut next = raw.iter();
+    for _ in 0..short_data {
+        for block in blocks.iter_mut() {
+            block.push(*next.next().ok_or(QrDecodeError::Corrupted)?);
+        }
+    }
+    for block in blocks.iter_mut().skip(num_short) {
+        block.push(*next.next().ok_or(QrDecodeError::Corrupted)?);
+    }
+    for _ in 0..ec_len {
+        for block in blocks.iter_mut() {
+            block.push(*next.next().ok_or(QrDecodeError::Corrupted)?);
+        }
+    }
+
+    let mut data = Vec::with_capacity(total - ec_len * num_blocks);
+    for mut block in blocks {
+        reed_solomon::correct(&mut block, ec_len).map_err(|_| QrDecodeError::Corrupted)?;
+        let data_len = block.len() - ec_len;
+        data.extend_from_slice(&block[..data_len]);
+    }
+    Ok(data)
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl BitReader<'_> {
+    fn available(&self) -> usize {
+        self.bytes.len() * 8 - self.offset
+    }
+
+    fn read(&mut self, count: usize) -> Result<u32, QrDecodeError> {
+        if count > self.available() {
+            return Err(QrDecodeError::Corrupted);
+        }
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = self.bytes[self.offset / 8];
+            let bit = (byte >> (7 - self.offset % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.offset += 1;
+        }
+        Ok(value)
+    }
+}
+
+fn count_bits(mode: u32, version: usize) -> usize {
+    let band = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    match mode {
+        0b0001 => [10, 12, 14][band],
+        0b0010 => [9, 11, 13][band],
+        0b0100 => [8, 16, 16][band],
+        _ => [8, 10, 12][band],
+    }
+}
+
+fn parse_segments(data: &[u8], version: usize) -> Result<Vec<u8>, QrDecodeError> {
+    let mut reader = BitReader { bytes: data, offset: 0 };
+    let mut out = Vec::new();
+
+    while reader.available() >= 4 {
+        let mode = reader.read(4)?;
+        match mode {
+            0b0000 => break,
+            0b0001 => {
+                let mut count = reader.read(count_bits(mode, version))? as usize;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = reader.read([0, 4, 7, 10][digits])?;
+                    let text = format!("{:0width$}", value, width = digits);
+                    if text.len() != digits {
+                        return Err(QrDecodeError::Corrupted);
+                    }
+                    out.extend_from_slice(text.as_bytes());
+                    count -= digits;
+                }
+            }
+            0b0010 => {
+                let mut count = reader.read(count_bits(mode, version))? as usize;
+                while count > 0 {
+                    if count >= 2 {
+                        let value = reader.read(11)? as usize;
+                        if value >= 45 * 45 {
+                            return Err(QrDecodeError::Corrupted);
+                        }
+                        out.push(ALPHANUMERIC[value / 45]);
+                        out.push(ALPHANUMERIC[value % 45]);
+                        count -= 2;
+                    } else {
+                        let value = reader.read(6)? as usize;
+                        out.push(*ALPHANUMERIC.get(value).ok_or(QrDecodeError::Corrupted)?);
+                        count -= 1;
+                    }
+                }
+            }
+            0b0100 => {
+                let count = reader.read(count_bits(mode, version))?;
+                for _ in 0..count {
+                    out.push(reader.read(8)? as u8);
+                }
+            }
+            0b0111 => {
+                let first = reader.read(8)?;
+                if first & 0x80 != 0 {
+                    let extra = if first & 0xC0 == 0x80 { 8 } else { 16 };
+                    reader.read(extra)?;
+                }
+            }
+            0b0011 => {
+                reader.read(16)?;
+            }
+            0b0101 => {}
+            0b1001 => {
+                reader.read(8)?;
+            }
+            0b1000 => return Err(QrDecodeError::UnsupportedEncoding),
+            _ => return Err(QrDecodeError::Corrupted),
+        }
+    }
+
+    Ok(out)
+}
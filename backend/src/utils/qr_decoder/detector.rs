@@ -0,0 +1,589 @@
+use image::GrayImage;
+
+use super::decoder::version_for_dimension;
+use super::BitGrid;
+
+pub struct Binarized {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Binarized {
+    pub fn global(image: &GrayImage) -> Self {
+        let mut histogram = [0u64; 256];
+        for pixel in image.pixels() {
+            histogram[pixel.0[0] as usize] += 1;
+        }
+        let threshold = otsu_threshold(&histogram);
+
+        Binarized {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            dark: image.pixels().map(|p| p.0[0] <= threshold).collect(),
+        }
+    }
+
+    pub fn adaptive(image: &GrayImage) -> Self {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+        let radius = (width.max(height) / 16).max(8);
+
+        let mut integral = vec![0u64; (width + 1) * (height + 1)];
+        for y in 0..height {
+            let mut row_sum = 0u64;
+            for x in 0..width {
+                row_sum += image.get_pixel(x as u32, y as u32).0[0] as u64;
+                integral[(y + 1) * (width + 1) + x + 1] = integral[y * (width + 1) + x + 1] + row_sum;
+            }
+        }
+
+        let mut dark = vec![false; width * height];
+        for y in 0..height {
+            let top = y.saturating_sub(radius);
+            let bottom = (y + radius + 1).min(height);
+            for x in 0..width {
+                let left = x.saturating_sub(radius);
+                let right = (x + radius + 1).min(width);
+                let sum = integral[bottom * (width + 1) + right] + integral[top * (width + 1) + left]
+                    - integral[top * (width + 1) + right]
+                    - integral[bottom * (width + 1) + left];
+                let area = ((bottom - top) * (right - left)) as u64;
+                let pixel = image.get_pixel(x as u32, y as u32).0[0] as u64;
+                dark[y * width + x] = pixel * area * 100 < sum * 90;
+            }
+        }
+
+        Binarized { width, height, dark }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+
+    fn sample(&self, x: f64, y: f64) -> Option<bool> {
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return None;
+        }
+        Some(self.get(x as usize, y as usize))
+    }
+}
+
+fn otsu_threshold(histogram: &[u64; 256]) -> u8 {
+    let total: u64 = histogram.iter().sum();
+    let weighted_total: f64 = histogram.iter().enumerate().map(|(i, &c)| i as f64 * c as f64).sum();
+
+    let mut background = 0u64;
+    let mut background_sum = 0.0;
+    let mut best = (0u8, 0.0f64);
+    for (i, &count) in histogram.iter().enumerate() {
+        background += count;
+        if background == 0 {
+            continue;
+        }
+        let foreground = total - background;
+        if foreground == 0 {
+            break;
+        }
+        background_sum += i as f64 * count as f64;
+        let mean_background = background_sum / background as f64;
+        let mean_foreground = (weighted_total - background_sum) / foreground as f64;
+        let variance = background as f64 * foreground as f64 * (mean_background - mean_foreground).powi(2);
+        if variance > best.1 {
+            best = (i as u8, variance);
+        }
+    }
+    best.0
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FinderPattern {
+    x: f64,
+    y: f64,
+    module: f64,
+    count: usize,
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+impl FinderPattern {
+    fn center(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+}
+
+fn is_finder_ratio(counts: &[usize; 5]) -> bool {
+    if counts.contains(&0) {
+        return false;
+    }
+    let total: usize = counts.iter().sum();
+    if total < 7 {
+        return false;
+    }
+    let module = total as f64 / 7.0;
+    let tolerance = module / 2.0;
+    (module - counts[0] as f64).abs() < tolerance
+        && (module - counts[1] as f64).abs() < tolerance
+        && (3.0 * module - counts[2] as f64).abs() < 3.0 * tolerance
+        && (module - counts[3] as f64).abs() < tolerance
+        && (module - counts[4] as f64).abs() < tolerance
+}
+
+pub fn find_finder_patterns(image: &Binarized) -> Vec<FinderPattern> {
+    let mut patterns: Vec<FinderPattern> = Vec::new();
+
+    for y in 0..image.height {
+        let mut counts = [0usize; 5];
+        let mut state = 0;
+        for x in 0..image.width {
+            if image.get(x, y) {
+                if state % 2 == 1 {
+                    state += 1;
+                }
+                counts[state] += 1;
+            } else if state % 2 == 1 {
+                counts[state] += 1;
+            } else if state == 4 {
+                if is_finder_ratio(&counts) {
+                    check_candidate(image, &counts, x, y, &mut patterns);
+                }
+                counts = [counts[2], counts[3], counts[4], 1, 0];
+                state = 3;
+            } else {
+                state += 1;
+                counts[state] += 1;
+            }
+        }
+        if state == 4 && is_finder_ratio(&counts) {
+            check_candidate(image, &counts, image.width, y, &mut patterns);
+        }
+    }
+
+    patterns
+}
+
+fn check_candidate(image: &Binarized, counts: &[usize; 5], end_x: usize, y: usize, patterns: &mut Vec<FinderPattern>) {
+    let total: usize = counts.iter().sum();
+    let center_x = (end_x - counts[4] - counts[3]) as f64 - counts[2] as f64 / 2.0;
+
+    let Some((center_y, vertical_total)) = cross_check(image, center_x as usize, y, counts[2], total, true) else {
+        return;
+    };
+    let Some((center_x, horizontal_total)) =
+        cross_check(image, center_x as usize, center_y as usize, counts[2], total, false)
+    else {
+        return;
+    };
+
+    let module = (vertical_total + horizontal_total) as f64 / 14.0;
+    for existing in patterns.iter_mut() {
+        let close = (existing.x - center_x).abs() <= module && (existing.y - center_y).abs() <= module;
+        if close && (existing.module - module).abs() <= module.max(existing.module) * 0.5 {
+            let n = existing.count as f64;
+            existing.x = (existing.x * n + center_x) / (n + 1.0);
+            existing.y = (existing.y * n + center_y) / (n + 1.0);
+            existing.module = (existing.module * n + module) / (n + 1.0);
+            existing.count += 1;
+            return;
+        }
+    }
+    patterns.push(FinderPattern {
+        x: center_x,
+        y: center_y,
+        module,
+        count: 1,
+    });
+}
+
+// Walks outward from (x, y) along one axis and re-checks the 1:1:3:1:1 ratio, returning the refined centre.
+fn cross_check(
+    image: &Binarized,
+    x: usize,
+    y: usize,
+    max_count: usize,
+    original_total: usize,
+    vertical: bool,
+) -> Option<(f64, usize)> {
+    let limit = if vertical { image.height } else { image.width };
+    let start = if vertical { y } else { x };
+    let dark = |i: usize| if vertical { image.get(x, i) } else { image.get(i, y) };
+    if start >= limit || !dark(start) {
+        return None;
+    }
+
+    let mut counts = [0usize; 5];
+    let mut i = start as isize;
+    while i >= 0 && dark(i as usize) {
+        counts[2] += 1;
+        i -= 1;
+    }
+    while i >= 0 && !dark(i as usize) && counts[1] <= max_count {
+        counts[1] += 1;
+        i -= 1;
+    }
+    if i < 0 || counts[1] > max_count {
+        return None;
+    }
+    while i >= 0 && dark(i as usize) && counts[0] <= max_count {
+        counts[0] += 1;
+        i -= 1;
+    }
+    if counts[0] > max_count {
+        return None;
+    }
+
+    let mut i = start + 1;
+    while i < limit && dark(i) {
+        counts[2] += 1;
+        i += 1;
+    }
+    while i < limit && !dark(i) && counts[3] <= max_count {
+        counts[3] += 1;
+        i += 1;
+    }
+    if i >= limit || counts[3] > max_count {
+        return None;
+    }
+    while i < limit && dark(i) && counts[4] <= max_count {
+        counts[4] += 1;
+        i += 1;
+    }
+    if counts[4] > max_count {
+        return None;
+    }
+
+    let total: usize = counts.iter().sum();
+    if 5 * total.abs_diff(original_total) >= 2 * original_total || !is_finder_ratio(&counts) {
+        return None;
+    }
+    let center = (i - counts[4] - counts[3]) as f64 - counts[2] as f64 / 2.0;
+    Some((center, total))
+}
+
+pub fn candidate_triples(patterns: &[FinderPattern]) -> Vec<[usize; 3]> {
+    let mut indices: Vec<usize> = (0..patterns.len()).collect();
+    indices.sort_by(|&a, &b| patterns[b].count.cmp(&patterns[a].count));
+    indices.truncate(24);
+
+    let mut triples = Vec::new();
+    for (ai, &a) in indices.iter().enumerate() {
+        for (bi, &b) in indices.iter().enumerate().skip(ai + 1) {
+            for &c in indices.iter().skip(bi + 1) {
+                if let Some(score) = triple_score(&patterns[a], &patterns[b], &patterns[c]) {
+                    triples.push(([a, b, c], score));
+                }
+            }
+        }
+    }
+
+    triples.sort_by(|a, b| a.1.total_cmp(&b.1));
+    triples.into_iter().map(|(triple, _)| triple).collect()
+}
+
+fn triple_score(a: &FinderPattern, b: &FinderPattern, c: &FinderPattern) -> Option<f64> {
+    let modules = [a.module, b.module, c.module];
+    let min_module = modules.iter().cloned().fold(f64::MAX, f64::min);
+    let max_module = modules.iter().cloned().fold(0.0, f64::max);
+    if max_module > min_module * 1.5 {
+        return None;
+    }
+
+    let mut sides = [
+        distance(a.center(), b.center()),
+        distance(b.center(), c.center()),
+        distance(a.center(), c.center()),
+    ];
+    sides.sort_by(f64::total_cmp);
+    let [short, middle, long] = sides;
+    if short < middle * 0.7 {
+        return None;
+    }
+    let hypotenuse = (short * short + middle * middle).sqrt();
+    let skew = (long - hypotenuse).abs() / long;
+    if skew > 0.15 {
+        return None;
+    }
+
+    let module = (a.module + b.module + c.module) / 3.0;
+    let modules_between = middle / module;
+    if !(10.0..=180.0).contains(&modules_between) {
+        return None;
+    }
+
+    Some(skew + (1.0 - short / middle) + (max_module / min_module - 1.0))
+}
+
+#[derive(Clone, Copy)]
+struct Homography([f64; 9]);
+
+impl Homography {
+    // Maps the unit square (0,0), (1,0), (1,1), (0,1) onto the given quadrilateral.
+    fn square_to_quad(p: [(f64, f64); 4]) -> Self {
+        let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = p;
+        let dx3 = x0 - x1 + x2 - x3;
+        let dy3 = y0 - y1 + y2 - y3;
+        if dx3.abs() < 1e-9 && dy3.abs() < 1e-9 {
+            return Homography([x1 - x0, x2 - x1, x0, y1 - y0, y2 - y1, y0, 0.0, 0.0, 1.0]);
+        }
+        let dx1 = x1 - x2;
+        let dx2 = x3 - x2;
+        let dy1 = y1 - y2;
+        let dy2 = y3 - y2;
+        let denominator = dx1 * dy2 - dx2 * dy1;
+        let g = (dx3 * dy2 - dx2 * dy3) / denominator;
+        let h = (dx1 * dy3 - dx3 * dy1) / denominator;
+        Homography([
+            x1 - x0 + g * x1,
+            x3 - x0 + h * x3,
+            x0,
+            y1 - y0 + g * y1,
+            y3 - y0 + h * y3,
+            y0,
+            g,
+            h,
+            1.0,
+        ])
+    }
+
+    fn adjugate(&self) -> Self {
+        let [a, b, c, d, e, f, g, h, i] = self.0;
+        Homography([
+            e * i - f * h,
+            c * h - b * i,
+            b * f - c * e,
+            f * g - d * i,
+            a * i - c * g,
+            c * d - a * f,
+            d * h - e * g,
+            b * g - a * h,
+            a * e - b * d,
+        ])
+    }
+
+    fn then(&self, next: &Homography) -> Self {
+        let a = next.0;
+        let b = self.0;
+        let mut out = [0.0; 9];
+        for row in 0..3 {
+            for col in 0..3 {
+                out[row * 3 + col] = (0..3).map(|k| a[row * 3 + k] * b[k * 3 + col]).sum();
+            }
+        }
+        Homography(out)
+    }
+
+    fn quad_to_quad(from: [(f64, f64); 4], to: [(f64, f64); 4]) -> Self {
+        Homography::square_to_quad(from)
+            .adjugate()
+            .then(&Homography::square_to_quad(to))
+    }
+
+    fn map(&self, x: f64, y: f64) -> (f64, f64) {
+        let m = self.0;
+        let w = m[6] * x + m[7] * y + m[8];
+        ((m[0] * x + m[1] * y + m[2]) / w, (m[3] * x + m[4] * y + m[5]) / w)
+    }
+}
+
+fn order_patterns(a: FinderPattern, b: FinderPattern, c: FinderPattern) -> [FinderPattern; 3] {
+    let ab = distance(a.center(), b.center());
+    let bc = distance(b.center(), c.center());
+    let ac = distance(a.center(), c.center());
+
+    let (top_left, p, q) = if bc >= ab && bc >= ac {
+        (a, b, c)
+    } else if ac >= ab && ac >= bc {
+        (b, a, c)
+    } else {
+        (c, a, b)
+    };
+
+    let cross = (p.x - top_left.x) * (q.y - top_left.y) - (p.y - top_left.y) * (q.x - top_left.x);
+    if cross > 0.0 {
+        [top_left, p, q]
+    } else {
+        [top_left, q, p]
+    }
+}
+
+fn count_timing_runs(image: &Binarized, from: (f64, f64), to: (f64, f64)) -> usize {
+    let length = distance(from, to).ceil() as usize;
+    if length == 0 {
+        return 0;
+    }
+    let mut runs = 0;
+    let mut last = None;
+    for step in 0..=length {
+        let t = step as f64 / length as f64;
+        let x = from.0 + (to.0 - from.0) * t;
+        let y = from.1 + (to.1 - from.1) * t;
+        let Some(dark) = image.sample(x, y) else {
+            return 0;
+        };
+        if last != Some(dark) {
+            runs += 1;
+            last = Some(dark);
+        }
+    }
+    runs
+}
+
+fn candidate_dimensions(image: &Binarized, [top_left, top_right, bottom_left]: &[FinderPattern; 3]) -> Vec<usize> {
+    let module = (top_left.module + top_right.module + bottom_left.module) / 3.0;
+    let mut dimensions = Vec::new();
+
+    // The timing patterns run three modules inside the finder centres; the run count gives the size directly.
+    let down = unit(top_left.center(), bottom_left.center());
+    let right = unit(top_left.center(), top_right.center());
+    let offset = |p: (f64, f64), dir: (f64, f64)| (p.0 + dir.0 * 3.0 * module, p.1 + dir.1 * 3.0 * module);
+    for runs in [
+        count_timing_runs(image, offset(top_left.center(), down), offset(top_right.center(), down)),
+        count_timing_runs(image, offset(top_left.center(), right), offset(bottom_left.center(), right)),
+    ] {
+        dimensions.push(runs + 12);
+    }
+
+    let across = (distance(top_left.center(), top_right.center()) + distance(top_left.center(), bottom_left.center()))
+        / 2.0
+        / module;
+    let estimate = across.round() as usize + 7;
+    let base = match estimate % 4 {
+        0 => estimate + 1,
+        2 => estimate - 1,
+        3 => estimate + 2,
+        _ => estimate,
+    };
+    dimensions.extend([base, base.saturating_sub(4), base + 4]);
+    if estimate % 4 == 3 {
+        dimensions.push(estimate - 2);
+    }
+
+    let mut unique = Vec::new();
+    for dimension in dimensions {
+        if version_for_dimension(dimension).is_some() && !unique.contains(&dimension) {
+            unique.push(dimension);
+        }
+    }
+    unique
+}
+
+fn unit(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    let length = distance(from, to).max(f64::EPSILON);
+    ((to.0 - from.0) / length, (to.1 - from.1) / length)
+}
+
+fn find_alignment_pattern(image: &Binarized, estimate: (f64, f64), module: f64, radius_modules: f64) -> Option<(f64, f64)> {
+    let radius = (radius_modules * module).ceil() as isize;
+    let offsets: [(f64, f64, bool); 17] = [
+        (0.0, 0.0, true),
+        (1.0, 0.0, false),
+        (-1.0, 0.0, false),
+        (0.0, 1.0, false),
+        (0.0, -1.0, false),
+        (1.0, 1.0, false),
+        (1.0, -1.0, false),
+        (-1.0, 1.0, false),
+        (-1.0, -1.0, false),
+        (2.0, 0.0, true),
+        (-2.0, 0.0, true),
+        (0.0, 2.0, true),
+        (0.0, -2.0, true),
+        (2.0, 2.0, true),
+        (2.0, -2.0, true),
+        (-2.0, 2.0, true),
+        (-2.0, -2.0, true),
+    ];
+
+    let mut best_score = 0;
+    let mut matches: Vec<(f64, f64)> = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let x = estimate.0 + dx as f64;
+            let y = estimate.1 + dy as f64;
+            let score = offsets
+                .iter()
+                .filter(|(ox, oy, dark)| image.sample(x + ox * module, y + oy * module) == Some(*dark))
+                .count();
+            if score > best_score {
+                best_score = score;
+                matches.clear();
+            }
+            if score == best_score {
+                matches.push((x, y));
+            }
+        }
+    }
+    if best_score < 16 {
+        return None;
+    }
+
+    let closest = matches
+        .iter()
+        .cloned()
+        .min_by(|a, b| distance(*a, estimate).total_cmp(&distance(*b, estimate)))?;
+    let cluster: Vec<(f64, f64)> = matches
+        .into_iter()
+        .filter(|p| distance(*p, closest) <= module)
+        .collect();
+    let n = cluster.len() as f64;
+    Some((
+        cluster.iter().map(|p| p.0).sum::<f64>() / n,
+        cluster.iter().map(|p| p.1).sum::<f64>() / n,
+    ))
+}
+
+fn sample_grid(image: &Binarized, transform: &Homography, dimension: usize) -> BitGrid {
+    let mut cells = Vec::with_capacity(dimension * dimension);
+    for y in 0..dimension {
+        for x in 0..dimension {
+            let (px, py) = transform.map(x as f64 + 0.5, y as f64 + 0.5);
+            cells.push(image.sample(px, py).unwrap_or(false));
+        }
+    }
+    BitGrid { dimension, cells }
+}
+
+pub fn sample_grids(image: &Binarized, patterns: [FinderPattern; 3]) -> Vec<BitGrid> {
+    let ordered = order_patterns(patterns[0], patterns[1], patterns[2]);
+    let [top_left, top_right, bottom_left] = ordered;
+    let module = (top_left.module + top_right.module + bottom_left.module) / 3.0;
+
+    let bottom_right = (
+        top_right.x + bottom_left.x - top_left.x,
+        top_right.y + bottom_left.y - top_left.y,
+    );
+
+    let mut grids = Vec::new();
+    for dimension in candidate_dimensions(image, &ordered) {
+        let far = dimension as f64 - 3.5;
+        let finders = [top_left.center(), top_right.center(), bottom_left.center()];
+
+        if dimension > 21 {
+            let correction = 1.0 - 3.0 / (dimension as f64 - 7.0);
+            let estimate = (
+                top_left.x + correction * (bottom_right.0 - top_left.x),
+                top_left.y + correction * (bottom_right.1 - top_left.y),
+            );
+            for radius in [4.0, 8.0] {
+                if let Some(alignment) = find_alignment_pattern(image, estimate, module, radius) {
+                    let near = dimension as f64 - 6.5;
+                    let transform = Homography::quad_to_quad(
+                        [(3.5, 3.5), (far, 3.5), (near, near), (3.5, far)],
+                        [finders[0], finders[1], alignment, finders[2]],
+                    );
+                    grids.push(sample_grid(image, &transform, dimension));
+                    break;
+                }
+            }
+        }
+
+        let transform = Homography::quad_to_quad(
+            [(3.5, 3.5), (far, 3.5), (far, far), (3.5, far)],
+            [finders[0], finders[1], bottom_right, finders[2]],
+        );
+        grids.push(sample_grid(image, &transform, dimension));
+    }
+    grids
+}
@@ -0,0 +1,94 @@
+mod decoder;
+mod detector;
+mod reed_solomon;
+
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+
+use detector::Binarized;
+
+pub const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+const MAX_DIMENSION: u32 = 1600;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum QrDecodeError {
+    #[error("unsupported or corrupt image")]
+    InvalidImage,
+    #[error("no qr code found in image")]
+    NotFound,
+    #[error("qr code is too damaged to read")]
+    Corrupted,
+    #[error("qr code uses an unsupported text encoding")]
+    UnsupportedEncoding,
+}
+
+pub struct BitGrid {
+    dimension: usize,
+    cells: Vec<bool>,
+}
+
+impl BitGrid {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.dimension + x]
+    }
+}
+
+pub fn decode_image(bytes: &[u8]) -> Result<Vec<String>, QrDecodeError> {
+    let image = image::load_from_memory(bytes).map_err(|_| QrDecodeError::InvalidImage)?;
+    let mut gray = image.to_luma8();
+
+    let longest = gray.width().max(gray.height());
+    if longest > MAX_DIMENSION {
+        let scale = MAX_DIMENSION as f64 / longest as f64;
+        let width = ((gray.width() as f64 * scale) as u32).max(1);
+        let height = ((gray.height() as f64 * scale) as u32).max(1);
+        gray = imageops::resize(&gray, width, height, FilterType::Triangle);
+    }
+
+    decode_luma(&gray)
+}
+
+pub fn decode_luma(image: &GrayImage) -> Result<Vec<String>, QrDecodeError> {
+    let mut error = QrDecodeError::NotFound;
+    for binarized in [Binarized::global(image), Binarized::adaptive(image)] {
+        match decode_binarized(&binarized) {
+            Ok(results) if !results.is_empty() => return Ok(results),
+            Ok(_) => {}
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+fn decode_binarized(image: &Binarized) -> Result<Vec<String>, QrDecodeError> {
+    let patterns = detector::find_finder_patterns(image);
+    let mut used = vec![false; patterns.len()];
+    let mut results: Vec<String> = Vec::new();
+    let mut error = None;
+
+    for triple in detector::candidate_triples(&patterns) {
+        if triple.iter().any(|&i| used[i]) {
+            continue;
+        }
+        let grids = detector::sample_grids(image, triple.map(|i| patterns[i]));
+        for grid in grids {
+            match decoder::decode_grid(&grid) {
+                Ok(bytes) => {
+                    let text = String::from_utf8(bytes).unwrap_or_else(|e| e.into_bytes().iter().map(|&b| b as char).collect());
+                    if !results.contains(&text) {
+                        results.push(text);
+                    }
+                    triple.iter().for_each(|&i| used[i] = true);
+                    break;
+                }
+                Err(QrDecodeError::NotFound) => {}
+                Err(e) => error = Some(e),
+            }
+        }
+    }
+
+    match error {
+        Some(e) if results.is_empty() => Err(e),
+        _ => Ok(results),
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::OnceLock;
+
+// GF(256) with the QR primitive polynomial x^8 + x^4 + x^3 + x^2 + 1 and generator roots a^0..a^(n-1).
+struct Gf {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Gf {
+    fn new() -> Self {
+        let mut exp = [0u8; 512];
+        let mut log = [0u8; 256];
+        let mut x: u16 = 1;
+        for (i, slot) in exp.iter_mut().enumerate().take(255) {
+            *slot = x as u8;
+            log[x as usize] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= 0x11D;
+            }
+        }
+        for i in 255..512 {
+            exp[i] = exp[i - 255];
+        }
+        Gf { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            return 0;
+        }
+        self.exp[(self.log[a as usize] as usize + 255 - self.log[b as usize] as usize) % 255]
+    }
+
+    fn pow_alpha(&self, power: usize) -> u8 {
+        self.exp[power % 255]
+    }
+
+    // Coefficients in ascending order of power.
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, &c| self.mul(acc, x) ^ c)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TooManyErrors;
+
+fn gf() -> &'static Gf {
+    static GF: OnceLock<Gf> = OnceLock::new();
+    GF.get_or_init(Gf::new)
+}
+
+pub fn correct(block: &mut [u8], ec_len: usize) -> Result<usize, TooManyErrors> {
+    let gf = gf();
+    let n = block.len();
+
+    // block[0] is the coefficient of x^(n-1).
+    let syndromes: Vec<u8> = (0..ec_len)
+        .map(|j| {
+            let x = gf.pow_alpha(j);
+            block.iter().fold(0, |acc, &c| gf.mul(acc, x) ^ c)
+        })
+        .collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Ok(0);
+    }
+
+    let locator = berlekamp_massey(gf, &syndromes);
+    let errors = locator.len() - 1;
+    if errors == 0 || errors * 2 > ec_len {
+        return Err(TooManyErrors);
+    }
+
+    let positions: Vec<usize> = (0..n)
+        .filter(|&k| {
+            let power = n - 1 - k;
+            let x_inv = gf.pow_alpha(255 - power % 255);
+            gf.eval(&locator, x_inv) == 0
+        })
+        .collect();
+    if positions.len() != errors {
+        return Err(TooManyErrors);
+    }
+
+    let mut omega = vec![0u8; ec_len];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in locator.iter().enumerate() {
+            if i + j < ec_len {
+                omega[i + j] ^= gf.mul(s, l);
+            }
+        }
+    }
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+        .collect();
+
+    for &k in &positions {
+        let power = n - 1 - k;
+        let x = gf.pow_alpha(power);
+        let x_inv = gf.pow_alpha(255 - power % 255);
+        let denominator = gf.eval(&derivative, x_inv);
+        if denominator == 0 {
+            return Err(TooManyErrors);
+        }
+        let magnitude = gf.mul(x, gf.div(gf.eval(&omega, x_inv), denominator));
+        block[k] ^= magnitude;
+    }
+
+    let clean = (0..ec_len).all(|j| {
+        let x = gf.pow_alpha(j);
+        block.iter().fold(0, |acc, &c| gf.mul(acc, x) ^ c) == 0
+    });
+    if clean {
+        Ok(errors)
+    } else {
+        Err(TooManyErrors)
+    }
+}
+
+fn berlekamp_massey(gf: &Gf, syndromes: &[u8]) -> Vec<u8> {
+    let mut current = vec![1u8];
+    let mut previous = vec![1u8];
+    let mut length = 0;
+    let mut shift = 1;
+    let mut last_discrepancy = 1u8;
+
+    for n in 0..syndromes.len() {
+        let mut discrepancy = syndromes[n];
+        for i in 1..=length {
+            if i < current.len() {
+                discrepancy ^= gf.mul(current[i], syndromes[n - i]);
+            }
+        }
+
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let scale = gf.div(discrepancy, last_discrepancy);
+        let mut next = current.clone();
+        if next.len() < previous.len() + shift {
+            next.resize(previous.len() + shift, 0);
+        }
+        for (i, &p) in previous.iter().enumerate() {
+            next[i + shift] ^= gf.mul(scale, p);
+        }
+
+        if 2 * length <= n {
+            length = n + 1 - length;
+            previous = current;
+            last_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        current = next;
+    }
+
+    while current.len() > 1 && current.last() == Some(&0) {
+        current.pop();
+    }
+    current
+}
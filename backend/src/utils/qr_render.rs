@@ -0,0 +1,91 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use serde::Deserialize;
+
+pub const MIN_SIZE: u32 = 64;
+pub const MAX_SIZE: u32 = 2048;
+pub const DEFAULT_SIZE: u32 = 512;
+
+#[derive(Debug, thiserror::Error)]
+pub enum QrRenderError {
+    #[error("size must be between {MIN_SIZE} and {MAX_SIZE} pixels")]
+    InvalidSize,
+    #[error("qr payload is too long")]
+    DataTooLong,
+    #[error("failed to encode qr image")]
+    Encode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrImageFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl QrImageFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            QrImageFormat::Png => "image/png",
+            QrImageFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum QrErrorCorrection {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+impl From<QrErrorCorrection> for EcLevel {
+    fn from(ec: QrErrorCorrection) -> Self {
+        match ec {
+            QrErrorCorrection::L => EcLevel::L,
+            QrErrorCorrection::M => EcLevel::M,
+            QrErrorCorrection::Q => EcLevel::Q,
+            QrErrorCorrection::H => EcLevel::H,
+        }
+    }
+}
+
+pub fn render(
+    data: &str,
+    format: QrImageFormat,
+    size: u32,
+    ec: QrErrorCorrection,
+) -> Result<Vec<u8>, QrRenderError> {
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        return Err(QrRenderError::InvalidSize);
+    }
+
+    let code = QrCode::with_error_correction_level(data.as_bytes(), ec.into()).map_err(|_| QrRenderError::DataTooLong)?;
+
+    match format {
+        QrImageFormat::Png => {
+            let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+            let mut out = Cursor::new(Vec::new());
+            DynamicImage::ImageLuma8(image)
+                .write_to(&mut out, ImageFormat::Png)
+                .map_err(|_| QrRenderError::Encode)?;
+            Ok(out.into_inner())
+        }
+        QrImageFormat::Svg => {
+            let image = code
+                .render::<svg::Color<'_>>()
+                .min_dimensions(size, size)
+                .dark_color(svg::Color("#000000"))
+                .light_color(svg::Color("#ffffff"))
+                .build();
+            Ok(image.into_bytes())
+        }
+    }
+}
@@ -1 +1,254 @@
-pub struct UpiClient;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::models::money::Money;
+use crate::utils::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitError, CircuitSnapshot};
+
+pub const KEY_ID_HEADER: &str = "X-PSP-Key-Id";
+pub const TIMESTAMP_HEADER: &str = "X-PSP-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-PSP-Signature";
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum UpiError {
+    #[error("payment network timed out")]
+    Timeout,
+    #[error("payment network unavailable: {0}")]
+    Unavailable(String),
+    #[error("{message}")]
+    Rejected { code: String, message: String },
+    #[error("invalid response from payment network: {0}")]
+    InvalidResponse(String),
+    #[error("payment network unavailable")]
+    CircuitOpen,
+}
+
+impl UpiError {
+    // A rejection means the switch is up and answering, so it does not count against the circuit. Any other
+    // failure leaves it unknown whether the switch acted on the request.
+    pub fn is_network_failure(&self) -> bool {
+        matches!(self, UpiError::Timeout | UpiError::Unavailable(_) | UpiError::InvalidResponse(_))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PspConfig {
+    pub base_url: String,
+    pub key_id: String,
+    pub secret: String,
+    pub timeout_ms: u64,
+    pub breaker: CircuitBreakerConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PspStatus {
+    Success,
+    Pending,
+    Failed,
+}
+
+// Amounts go over the wire as rupee strings with two decimals, e.g. "100.00".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayRequest {
+    pub reference: Uuid,
+    pub payer_vpa: String,
+    pub payee_vpa: String,
+    pub amount: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PspPayment {
+    pub reference: Uuid,
+    pub upi_txn_id: Option<String>,
+    pub status: PspStatus,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReversalRequest {
+    pub amount: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PspReversal {
+    pub reversal_id: String,
+    pub status: PspStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct PspErrorBody {
+    code: String,
+    message: String,
+}
+
+// Client for the PSP switch. Every call is keyed by our transaction id, which the switch treats as
+// an idempotency key, so a call whose outcome was lost to a timeout can simply be repeated.
+// Each endpoint sits behind its own circuit breaker so a failing status API does not block payments.
+#[derive(Clone)]
+pub struct UpiClient {
+    http: reqwest::Client,
+    config: PspConfig,
+    breakers: Arc<Breakers>,
+}
+
+struct Breakers {
+    payments: CircuitBreaker,
+    status: CircuitBreaker,
+    reversals: CircuitBreaker,
+}
+
+impl UpiClient {
+    pub fn new(config: PspConfig) -> Result<Self, UpiError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .connect_timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| UpiError::Unavailable(e.to_string()))?;
+        let breakers = Arc::new(Breakers {
+            payments: CircuitBreaker::new("psp.payments", config.breaker.clone()),
+            status: CircuitBreaker::new("psp.status", config.breaker.clone()),
+            reversals: CircuitBreaker::new("psp.reversals", config.breaker.clone()),
+        });
+        Ok(UpiClient { http, config, breakers })
+    }
+
+    pub fn circuit_breakers(&self) -> Vec<CircuitSnapshot> {
+        [&self.breakers.payments, &self.breakers.status, &self.breakers.reversals]
+            .into_iter()
+            .map(CircuitBreaker::snapshot)
+            .collect()
+    }
+
+    pub async fn pay(
+        &self,
+        reference: Uuid,
+        payer_vpa: &str,
+        payee_vpa: &str,
+        amount: Money,
+        note: Option<&str>,
+    ) -> Result<PspPayment, UpiError> {
+        let body = PayRequest {
+            reference,
+            payer_vpa: payer_vpa.to_string(),
+            payee_vpa: payee_vpa.to_string(),
+            amount: amount.to_string(),
+            note: note.map(str::to_string),
+        };
+        guarded(&self.breakers.payments, self.send(Method::POST, "/v1/payments", Some(&body))).await
+    }
+
+    pub async fn status(&self, reference: Uuid) -> Result<PspPayment, UpiError> {
+        let path = format!("/v1/payments/{}", reference);
+        guarded(&self.breakers.status, self.send::<(), _>(Method::GET, &path, None)).await
+    }
+
+    pub async fn reverse(&self, reference: Uuid, amount: Money, reason: &str) -> Result<PspReversal, UpiError> {
+        let body = ReversalRequest {
+            amount: amount.to_string(),
+            reason: reason.to_string(),
+        };
+        let path = format!("/v1/payments/{}/reversals", reference);
+        guarded(&self.breakers.reversals, self.send(Method::POST, &path, Some(&body))).await
+    }
+
+    async fn send<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T, UpiError> {
+        let url = reqwest::Url::parse(&format!("{}{}", self.config.base_url.trim_end_matches('/'), path))
+            .map_err(|e| UpiError::Unavailable(format!("invalid psp url: {}", e)))?;
+        let body = match body {
+            Some(body) => serde_json::to_string(body).map_err(|e| UpiError::InvalidResponse(e.to_string()))?,
+            None => String::new(),
+        };
+        let timestamp = Utc::now().timestamp();
+        let signature = sign(&self.config.secret, timestamp, method.as_str(), url.path(), &body);
+
+        let mut request = self
+            .http
+            .request(method, url)
+            .header(KEY_ID_HEADER, &self.config.key_id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, signature);
+        if !body.is_empty() {
+            request = request.header(reqwest::header::CONTENT_TYPE, "application/json").body(body);
+        }
+
+        let response = request.send().await.map_err(map_transport_error)?;
+        let status = response.status();
+        let text = response.text().await.map_err(map_transport_error)?;
+
+        if status.is_success() {
+            return serde_json::from_str(&text).map_err(|e| UpiError::InvalidResponse(e.to_string()));
+        }
+        if status == StatusCode::GATEWAY_TIMEOUT {
+            return Err(UpiError::Timeout);
+        }
+        if status.is_server_error() {
+            return Err(UpiError::Unavailable(format!("psp returned {}", status)));
+        }
+        Err(match serde_json::from_str::<PspErrorBody>(&text) {
+            Ok(e) => UpiError::Rejected {
+                code: e.code,
+                message: e.message,
+            },
+            Err(_) => UpiError::Rejected {
+                code: status.as_u16().to_string(),
+                message: format!("psp rejected the request with {}", status),
+            },
+        })
+    }
+}
+
+// Hex HMAC-SHA256 over the timestamp, method, path and body, one per line. Shared with the PSP out of band.
+pub fn sign(secret: &str, timestamp: i64, method: &str, path: &str, body: &str) -> String {
+    hex::encode(mac(secret, timestamp, method, path, body).finalize().into_bytes())
+}
+
+// Checks a signature made with `sign`, comparing in constant time.
+pub fn verify(secret: &str, timestamp: i64, method: &str, path: &str, body: &str, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    mac(secret, timestamp, method, path, body).verify_slice(&signature).is_ok()
+}
+
+fn mac(secret: &str, timestamp: i64, method: &str, path: &str, body: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}\n{}", timestamp, method, path, body).as_bytes());
+    mac
+}
+
+async fn guarded<T>(
+    breaker: &CircuitBreaker,
+    call: impl Future<Output = Result<T, UpiError>>,
+) -> Result<T, UpiError> {
+    breaker
+        .call(call, UpiError::is_network_failure)
+        .await
+        .map_err(|e| match e {
+            CircuitError::Open => UpiError::CircuitOpen,
+            CircuitError::Inner(e) => e,
+        })
+}
+
+fn map_transport_error(e: reqwest::Error) -> UpiError {
+    if e.is_timeout() {
+        UpiError::Timeout
+    } else {
+        UpiError::Unavailable(e.to_string())
+    }
+}
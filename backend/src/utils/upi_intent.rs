@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::models::money::Money;
+
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'@')
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'~');
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum UpiIntentError {
+    #[error("qr data is not a upi payment link")]
+    NotUpiLink,
+    #[error("malformed upi link")]
+    Malformed,
+    #[error("upi link is missing payee address (pa)")]
+    MissingPayee,
+    #[error("invalid payee address in upi link")]
+    InvalidPayee,
+    #[error("duplicate parameter {0} in upi link")]
+    DuplicateParam(String),
+    #[error("invalid {0} in upi link")]
+    InvalidParam(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpiIntent {
+    pub payee_address: String,
+    pub payee_name: Option<String>,
+    pub amount: Option<Money>,
+    pub min_amount: Option<Money>,
+    pub currency: Option<String>,
+    pub transaction_note: Option<String>,
+    pub transaction_ref: Option<String>,
+    pub merchant_code: Option<String>,
+    pub mode: Option<String>,
+    pub org_id: Option<String>,
+    pub url: Option<String>,
+    pub signature: Option<String>,
+}
+
+impl UpiIntent {
+    pub fn parse(raw: &str) -> Result<Self, UpiIntentError> {
+        let raw = raw.trim();
+        let url = Url::parse(raw).map_err(|_| UpiIntentError::NotUpiLink)?;
+        if !url.scheme().eq_ignore_ascii_case("upi") {
+            return Err(UpiIntentError::NotUpiLink);
+        }
+        if !url.host_str().is_some_and(|h| h.eq_ignore_ascii_case("pay")) {
+            return Err(UpiIntentError::Malformed);
+        }
+        if !matches!(url.path(), "" | "/") {
+            return Err(UpiIntentError::Malformed);
+        }
+
+        let mut params: HashMap<String, String> = HashMap::new();
+        for (key, value) in url.query_pairs() {
+            let key = key.to_ascii_lowercase();
+            let value = value.trim().to_string();
+            if params.contains_key(&key) {
+                return Err(UpiIntentError::DuplicateParam(key));
+            }
+            params.insert(key, value);
+        }
+
+        let mut take = |key: &str| params.remove(key).filter(|v| !v.is_empty());
+
+        let payee_address = take("pa").ok_or(UpiIntentError::MissingPayee)?;
+        let payee_address = canonical_vpa(&payee_address).ok_or(UpiIntentError::InvalidPayee)?;
+
+        let amount = take("am")
+            .map(|am| parse_amount(&am, "amount (am)"))
+            .transpose()?;
+        let min_amount = take("mam")
+            .map(|mam| parse_amount(&mam, "minimum amount (mam)"))
+            .transpose()?;
+        if let (Some(am), Some(mam)) = (amount, min_amount) {
+            if mam > am {
+                return Err(UpiIntentError::InvalidParam("minimum amount (mam)"));
+            }
+        }
+
+        let currency = take("cu").map(|cu| cu.to_ascii_uppercase());
+        if let Some(cu) = &currency {
+            if cu.len() != 3 || !cu.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(UpiIntentError::InvalidParam("currency (cu)"));
+            }
+        }
+
+        let merchant_code = take("mc");
+        if let Some(mc) = &merchant_code {
+            if mc.len() != 4 || !mc.chars().all(|c| c.is_ascii_digit()) {
+                return Err(UpiIntentError::InvalidParam("merchant code (mc)"));
+            }
+        }
+
+        Ok(UpiIntent {
+            payee_address,
+            payee_name: take("pn"),
+            amount,
+            min_amount,
+            currency,
+            transaction_note: take("tn"),
+            transaction_ref: take("tr"),
+            merchant_code,
+            mode: take("mode"),
+            org_id: take("orgid"),
+            url: take("url"),
+            signature: take("sign"),
+        })
+    }
+
+    pub fn for_payee(payee_address: &str, payee_name: &str) -> Self {
+        UpiIntent {
+            payee_address: payee_address.to_ascii_lowercase(),
+            payee_name: Some(payee_name.to_string()),
+            amount: None,
+            min_amount: None,
+            currency: None,
+            transaction_note: None,
+            transaction_ref: None,
+            merchant_code: None,
+            mode: None,
+            org_id: None,
+            url: None,
+            signature: None,
+        }
+    }
+
+    pub fn to_uri(&self) -> String {
+        let amount = self.amount.map(|am| am.to_string());
+        let min_amount = self.min_amount.map(|mam| mam.to_string());
+        let params = [
+            ("pa", Some(self.payee_address.as_str())),
+            ("pn", self.payee_name.as_deref()),
+            ("am", amount.as_deref()),
+            ("mam", min_amount.as_deref()),
+            ("cu", self.currency.as_deref()),
+            ("tn", self.transaction_note.as_deref()),
+            ("tr", self.transaction_ref.as_deref()),
+            ("mc", self.merchant_code.as_deref()),
+            ("mode", self.mode.as_deref()),
+            ("orgid", self.org_id.as_deref()),
+            ("url", self.url.as_deref()),
+            ("sign", self.signature.as_deref()),
+        ];
+
+        let query: Vec<String> = params
+            .iter()
+            .filter_map(|(key, value)| value.map(|v| format!("{}={}", key, utf8_percent_encode(v, QUERY_VALUE))))
+            .collect();
+        format!("upi://pay?{}", query.join("&"))
+    }
+
+    // Merchants sign the canonical uri of the intent with the sign parameter left out.
+    pub fn signed_payload(&self) -> String {
+        UpiIntent {
+            signature: None,
+            ..self.clone()
+        }
+        .to_uri()
+    }
+}
+
+pub fn canonical_vpa(raw: &str) -> Option<String> {
+    let vpa = raw.trim().to_ascii_lowercase();
+    if vpa.len() > 255 {
+        return None;
+    }
+
+    let (handle, psp) = vpa.split_once('@')?;
+    let handle_ok = !handle.is_empty()
+        && handle
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    let psp_ok = psp.starts_with(|c: char| c.is_ascii_alphabetic())
+        && psp.chars().all(|c| c.is_ascii_alphanumeric() || c == '.');
+
+    if handle_ok && psp_ok {
+        Some(vpa)
+    } else {
+        None
+    }
+}
+
+fn parse_amount(raw: &str, param: &'static str) -> Result<Money, UpiIntentError> {
+    match raw.parse::<Money>() {
+        Ok(amount) if amount.is_positive() => Ok(amount),
+        _ => Err(UpiIntentError::InvalidParam(param)),
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use qr_payment_backend::utils::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitError, CircuitState};
+
+fn config() -> CircuitBreakerConfig {
+    CircuitBreakerConfig {
+        consecutive_failures: 3,
+        failure_rate: 0.5,
+        window_size: 10,
+        minimum_calls: 6,
+        cool_down_ms: 50,
+        half_open_max_calls: 2,
+    }
+}
+
+async fn succeed(breaker: &CircuitBreaker) -> Result<(), CircuitError<&'static str>> {
+    breaker.call(async { Ok::<_, &str>(()) }, |_| true).await
+}
+
+async fn fail(breaker: &CircuitBreaker) -> Result<(), CircuitError<&'static str>> {
+    breaker.call(async { Err::<(), _>("down") }, |_| true).await
+}
+
+#[tokio::test]
+async fn consecutive_failures_open_the_circuit() {
+    let breaker = CircuitBreaker::new("psp.payments", config());
+    fail(&breaker).await.unwrap_err();
+    fail(&breaker).await.unwrap_err();
+    succeed(&breaker).await.unwrap();
+    fail(&breaker).await.unwrap_err();
+    fail(&breaker).await.unwrap_err();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+
+    assert_eq!(fail(&breaker).await, Err(CircuitError::Inner("down")));
+    assert_eq!(breaker.state(), CircuitState::Open);
+
+    let mut called = false;
+    let result = breaker
+        .call(
+            async {
+                called = true;
+                Ok::<_, &str>(())
+            },
+            |_| true,
+        )
+        .await;
+    assert_eq!(result, Err(CircuitError::Open));
+    assert!(!called);
+
+    let snapshot = breaker.snapshot();
+    assert_eq!(snapshot.name, "psp.payments");
+    assert_eq!(snapshot.state, CircuitState::Open);
+    assert_eq!(snapshot.times_opened, 1);
+    assert!(snapshot.retry_after_ms.is_some());
+}
+
+#[tokio::test]
+async fn failure_rate_opens_the_circuit_once_enough_calls_are_seen() {
+    let breaker = CircuitBreaker::new("psp.status", config());
+    for _ in 0..2 {
+        succeed(&breaker).await.unwrap();
+        fail(&breaker).await.unwrap_err();
+    }
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert_eq!(breaker.snapshot().failure_rate, 0.5);
+
+    succeed(&breaker).await.unwrap();
+    fail(&breaker).await.unwrap_err();
+    assert_eq!(breaker.state(), CircuitState::Open);
+}
+
+#[tokio::test]
+async fn errors_that_are_not_failures_keep_the_circuit_closed() {
+    let breaker = CircuitBreaker::new("psp.payments", config());
+    for _ in 0..10 {
+        let result = breaker.call(async { Err::<(), _>("declined") }, |e| *e != "declined").await;
+        assert_eq!(result, Err(CircuitError::Inner("declined")));
+    }
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert_eq!(breaker.snapshot().consecutive_failures, 0);
+}
+
+#[tokio::test]
+async fn half_open_probes_close_or_reopen_the_circuit() {
+    let breaker = CircuitBreaker::new("psp.reversals", config());
+    for _ in 0..3 {
+        fail(&breaker).await.unwrap_err();
+    }
+    assert_eq!(breaker.state(), CircuitState::Open);
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    fail(&breaker).await.unwrap_err();
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert_eq!(succeed(&breaker).await, Err(CircuitError::Open));
+    assert_eq!(breaker.snapshot().times_opened, 2);
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    succeed(&breaker).await.unwrap();
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    succeed(&breaker).await.unwrap();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert_eq!(breaker.snapshot().consecutive_failures, 0);
+}
+
+#[tokio::test]
+async fn half_open_limits_concurrent_probes() {
+    let breaker = CircuitBreaker::new("psp.payments", CircuitBreakerConfig { half_open_max_calls: 1, ..config() });
+    for _ in 0..3 {
+        fail(&breaker).await.unwrap_err();
+    }
+    tokio::time::sleep(Duration::from_millis(60)).await;
+
+    let (release, wait) = tokio::sync::oneshot::channel::<()>();
+    let probe = breaker.call(
+        async {
+            wait.await.unwrap();
+            Ok::<_, &str>(())
+        },
+        |_| true,
+    );
+    let other = async {
+        tokio::task::yield_now().await;
+        let result = succeed(&breaker).await;
+        release.send(()).unwrap();
+        result
+    };
+    let (probe, other) = tokio::join!(probe, other);
+    assert_eq!(probe, Ok(()));
+    assert_eq!(other, Err(CircuitError::Open));
+    assert_eq!(breaker.state(), CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn abandoned_probe_frees_its_slot() {
+    let breaker = CircuitBreaker::new("psp.payments", CircuitBreakerConfig { half_open_max_calls: 1, ..config() });
+    for _ in 0..3 {
+        fail(&breaker).await.unwrap_err();
+    }
+    tokio::time::sleep(Duration::from_millis(60)).await;
+
+    let stuck = breaker.call(std::future::pending::<Result<(), &str>>(), |_| true);
+    assert!(tokio::time::timeout(Duration::from_millis(10), stuck).await.is_err());
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+    succeed(&breaker).await.unwrap();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+}
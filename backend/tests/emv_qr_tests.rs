@@ -0,0 +1,84 @@
+use qr_payment_backend::models::money::Money;
+use qr_payment_backend::utils::emv_qr::{crc16_ccitt, EmvQr, EmvQrError};
+
+fn tlv(tag: &str, value: &str) -> String {
+    format!("{}{:02}{}", tag, value.chars().count(), value)
+}
+
+fn with_crc(body: &str) -> String {
+    let unsigned = format!("{}6304", body);
+    format!("{}{:04X}", unsigned, crc16_ccitt(unsigned.as_bytes()))
+}
+
+fn bharat_qr(amount: Option<&str>) -> String {
+    let upi = format!("{}{}{}", tlv("00", "A000000524"), tlv("01", "CoffeeShop@upi"), tlv("02", "10.00"));
+    let mut body = format!(
+        "{}{}{}{}{}",
+        tlv("00", "01"),
+        tlv("01", "12"),
+        tlv("04", "5204000000000000"),
+        tlv("26", &upi),
+        tlv("52", "5814"),
+    );
+    body.push_str(&tlv("53", "356"));
+    if let Some(am) = amount {
+        body.push_str(&tlv("54", am));
+    }
+    body.push_str(&tlv("58", "IN"));
+    body.push_str(&tlv("59", "Coffee Shop"));
+    body.push_str(&tlv("60", "Bengaluru"));
+    body.push_str(&tlv("62", &tlv("05", "ORD-42")));
+    with_crc(&body)
+}
+
+#[test]
+fn crc_matches_reference_vector() {
+    assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+}
+
+#[test]
+fn decodes_bharat_qr_into_upi_intent() {
+    let qr = EmvQr::parse(&bharat_qr(Some("250.00"))).unwrap();
+    assert_eq!(qr.merchant_name, "Coffee Shop");
+    assert_eq!(qr.merchant_city, "Bengaluru");
+    assert_eq!(qr.merchant_category_code, "5814");
+    assert_eq!(qr.merchant_accounts.len(), 2);
+    assert_eq!(qr.upi_vpa().as_deref(), Some("coffeeshop@upi"));
+
+    let intent = qr.into_upi_intent().unwrap();
+    assert_eq!(intent.payee_address, "coffeeshop@upi");
+    assert_eq!(intent.amount, Some(Money::from_rupees(250)));
+    assert_eq!(intent.min_amount, Some(Money::from_rupees(10)));
+    assert_eq!(intent.currency.as_deref(), Some("INR"));
+    assert_eq!(intent.transaction_ref.as_deref(), Some("ORD-42"));
+}
+
+#[test]
+fn rejects_tampered_payloads() {
+    let original = bharat_qr(Some("250.00"));
+    let tampered = original.replace("5406250.00", "5406950.00");
+    assert_ne!(original, tampered);
+    assert_eq!(EmvQr::parse(&tampered), Err(EmvQrError::CrcMismatch));
+
+    let without_crc = &original[..original.len() - 8];
+    assert_eq!(EmvQr::parse(without_crc), Err(EmvQrError::MissingField("crc (63)")));
+
+    let truncated = &original[..original.len() - 2];
+    assert!(EmvQr::parse(truncated).is_err());
+}
+
+#[test]
+fn requires_a_upi_account_to_resolve_merchant() {
+    let body = format!(
+        "{}{}{}{}{}{}{}",
+        tlv("00", "01"),
+        tlv("04", "5204000000000000"),
+        tlv("52", "5814"),
+        tlv("53", "356"),
+        tlv("58", "IN"),
+        tlv("59", "Coffee Shop"),
+        tlv("60", "Bengaluru"),
+    );
+    let qr = EmvQr::parse(&with_crc(&body)).unwrap();
+    assert_eq!(qr.into_upi_intent(), Err(EmvQrError::NoUpiAccount));
+}
@@ -1,14 +1,45 @@
 use actix_web::{test, web, App};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
 use qr_payment_backend::cache::redis_client::RedisClient;
 use qr_payment_backend::config::Config;
 use qr_payment_backend::handlers;
 use qr_payment_backend::middleware::jwt_auth::JwtAuth;
+use qr_payment_backend::models::ledger::{AccountType, LedgerAccount, Posting};
+use qr_payment_backend::models::mandate::MandateRun;
+use qr_payment_backend::models::money::Money;
+use qr_payment_backend::models::payment::TransactionStatus;
+use qr_payment_backend::models::reconciliation::{PollOutcome, ReconciliationConfig, ReconciliationRun};
+use qr_payment_backend::services::{collect, ledger, mandate, payment, reconciliation, settlement, split};
+use qr_payment_backend::utils::funding_source::{FundingSource, SimulatedBank};
+use qr_payment_backend::utils::qr_render::{self, QrErrorCorrection, QrImageFormat};
+use qr_payment_backend::utils::upi_client::{self, PspPayment, PspStatus, UpiClient};
+use qr_payment_backend::utils::upi_intent::UpiIntent;
 use serde_json::json;
 use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard, OnceCell};
 use uuid::Uuid;
+use wiremock::MockServer;
 
-async fn setup() -> (Config, PgPool, RedisClient) {
-    let cfg = Config::from_env().expect("failed to load config");
+mod mock_psp;
+
+// Every test truncates the same tables and flushes the same redis, so they must not overlap.
+static DB_LOCK: Mutex<()> = Mutex::const_new(());
+static PSP: OnceCell<MockServer> = OnceCell::const_new();
+
+async fn psp_server() -> &'static MockServer {
+    PSP.get_or_init(MockServer::start).await
+}
+
+async fn setup() -> (MutexGuard<'static, ()>, Config, PgPool, RedisClient) {
+    let guard = DB_LOCK.lock().await;
+    let mut cfg = Config::from_env().expect("failed to load config");
+    let psp = psp_server().await;
+    psp.reset().await;
+    mock_psp::mount(psp, &cfg.psp.secret).await;
+    cfg.psp.base_url = psp.uri();
     let db = qr_payment_backend::db::pool::create_pool(&cfg.database_url)
         .await
         .expect("failed to create db pool");
@@ -18,20 +49,43 @@ async fn setup() -> (Config, PgPool, RedisClient) {
         .await
         .expect("failed to run migrations");
 
-    sqlx::query("TRUNCATE TABLE transactions, merchants, users CASCADE")
+    sqlx::query("TRUNCATE TABLE ledger_entries, psp_status_polls, psp_callbacks, collect_requests, mandate_executions, mandates, fee_rules, promo_redemptions, promo_codes, refunds, rewards, reward_campaigns, split_bills, settlement_batches, wallet_topups, transactions, merchants, users CASCADE")
         .execute(&db)
         .await
         .expect("failed to reset tables");
 
+    let mut conn = redis::Client::open(cfg.redis_url.as_str())
+        .expect("failed to open redis")
+        .get_multiplexed_async_connection()
+        .await
+        .expect("failed to connect to redis");
+    redis::cmd("FLUSHDB")
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .expect("failed to reset redis");
+
     let redis = RedisClient::new(&cfg.redis_url)
         .await
         .expect("failed to create redis client");
 
-    (cfg, db, redis)
+    (guard, cfg, db, redis)
 }
 
 async fn init_app(cfg: Config, db: PgPool, redis: RedisClient) -> impl actix_web::dev::Service<
-    actix_web::dev::ServiceRequest,
+    actix_http::Request,
+    Response = actix_web::dev::ServiceResponse<actix_web::body::BoxBody>,
+    Error = actix_web::Error,
+> {
+    init_app_with_funding(cfg, db, redis, Some(Arc::new(SimulatedBank))).await
+}
+
+async fn init_app_with_funding(
+    cfg: Config,
+    db: PgPool,
+    redis: RedisClient,
+    funding: Option<Arc<dyn FundingSource>>,
+) -> impl actix_web::dev::Service<
+    actix_http::Request,
     Response = actix_web::dev::ServiceResponse<actix_web::body::BoxBody>,
     Error = actix_web::Error,
 > {
@@ -39,6 +93,8 @@ async fn init_app(cfg: Config, db: PgPool, redis: RedisClient) -> impl actix_web
         config: cfg.clone(),
         db,
         redis,
+        funding,
+        upi: UpiClient::new(cfg.psp.clone()).unwrap(),
     };
 
     let jwt = JwtAuth { config: cfg };
@@ -47,6 +103,8 @@ async fn init_app(cfg: Config, db: PgPool, redis: RedisClient) -> impl actix_web
         App::new()
             .app_data(web::Data::new(state))
             .service(handlers::health)
+            .service(handlers::circuit_breakers)
+            .service(handlers::psp::psp_callback)
             .service(
                 web::scope("/auth")
                     .service(handlers::auth::register)
@@ -55,9 +113,52 @@ async fn init_app(cfg: Config, db: PgPool, redis: RedisClient) -> impl actix_web
             .service(
                 web::scope("/api")
                     .wrap(jwt)
+                    .app_data(web::PayloadConfig::new(qr_payment_backend::utils::qr_decoder::MAX_IMAGE_BYTES))
                     .service(handlers::merchant::resolve_merchant)
+                    .service(handlers::merchant::merchant_qr)
+                    .service(handlers::merchant::merchant_balance)
+                    .service(handlers::merchant::register_merchant_key)
+                    .service(handlers::merchant::list_merchant_keys)
+                    .service(handlers::merchant::revoke_merchant_key)
+                    .service(handlers::merchant::decode_qr_image)
+                    .service(handlers::merchant::create_order)
+                    .service(handlers::merchant::get_order)
                     .service(handlers::payment::initiate_payment)
-                    .service(handlers::payment::execute_payment),
+                    .service(handlers::payment::execute_payment)
+                    .service(handlers::payment::refund_payment)
+                    .service(handlers::settlement::run_settlement)
+                    .service(handlers::settlement::settlement_report)
+                    .service(handlers::settlement::get_settlement)
+                    .service(handlers::settlement::update_settlement_status)
+                    .service(handlers::settlement::set_settlement_schedule)
+                    .service(handlers::wallet::top_up)
+                    .service(handlers::wallet::get_top_up)
+                    .service(handlers::wallet::balance)
+                    .service(handlers::limits::get_limits)
+                    .service(handlers::limits::lower_limits)
+                    .service(handlers::limits::set_user_limits)
+                    .service(handlers::fee::create_fee_rule)
+                    .service(handlers::fee::list_fee_rules)
+                    .service(handlers::fee::deactivate_fee_rule)
+                    .service(handlers::reward::create_campaign)
+                    .service(handlers::reward::list_campaigns)
+                    .service(handlers::reward::my_rewards)
+                    .service(handlers::promo::create_promo_code)
+                    .service(handlers::promo::list_promo_codes)
+                    .service(handlers::split::create_split)
+                    .service(handlers::split::get_split)
+                    .service(handlers::split::pay_split_share)
+                    .service(handlers::collect::create_collect_request)
+                    .service(handlers::collect::get_collect_request)
+                    .service(handlers::collect::pending_collect_requests)
+                    .service(handlers::collect::approve_collect_request)
+                    .service(handlers::collect::decline_collect_request)
+                    .service(handlers::mandate::create_mandate)
+                    .service(handlers::mandate::update_mandate)
+                    .service(handlers::mandate::my_mandates)
+                    .service(handlers::mandate::approve_mandate)
+                    .service(handlers::mandate::revoke_mandate)
+                    .service(handlers::mandate::mandate_history),
             ),
     )
     .await
@@ -65,7 +166,7 @@ async fn init_app(cfg: Config, db: PgPool, redis: RedisClient) -> impl actix_web
 
 #[actix_web::test]
 async fn auth_and_payment_flow() {
-    let (cfg, db, redis) = setup().await;
+    let (_guard, cfg, db, redis) = setup().await;
 
     let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
     sqlx::query(
@@ -83,7 +184,7 @@ async fn auth_and_payment_flow() {
     .await
     .expect("failed to seed merchant");
 
-    let mut app = init_app(cfg.clone(), db.clone(), redis).await;
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
 
     let register_req = test::TestRequest::post()
         .uri("/auth/register")
@@ -95,18 +196,14 @@ async fn auth_and_payment_flow() {
         }))
         .to_request();
 
-    let register_resp: serde_json::Value = test::call_and_read_body_json(&mut app, register_req).await;
+    let register_resp: serde_json::Value = test::call_and_read_body_json(&app, register_req).await;
     let token = register_resp
         .get("token")
         .and_then(|v| v.as_str())
         .unwrap()
         .to_string();
 
-    sqlx::query("UPDATE users SET balance = 1000.0 WHERE phone_number = $1")
-        .bind("9876543210")
-        .execute(&db)
-        .await
-        .expect("failed to set balance");
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
 
     let init_req = test::TestRequest::post()
         .uri("/api/payment/initiate")
@@ -118,7 +215,7 @@ async fn auth_and_payment_flow() {
         }))
         .to_request();
 
-    let init_resp: serde_json::Value = test::call_and_read_body_json(&mut app, init_req).await;
+    let init_resp: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
     let session_id = init_resp
         .get("session_id")
         .and_then(|v| v.as_str())
@@ -134,13 +231,13 @@ async fn auth_and_payment_flow() {
         }))
         .to_request();
 
-    let exec_resp: serde_json::Value = test::call_and_read_body_json(&mut app, exec_req).await;
+    let exec_resp: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
     assert_eq!(exec_resp.get("status").and_then(|v| v.as_str()), Some("success"));
 }
 
 #[actix_web::test]
 async fn payment_idempotency() {
-    let (cfg, db, redis) = setup().await;
+    let (_guard, cfg, db, redis) = setup().await;
 
     let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
     sqlx::query(
@@ -158,7 +255,7 @@ async fn payment_idempotency() {
     .await
     .expect("failed to seed merchant");
 
-    let mut app = init_app(cfg.clone(), db.clone(), redis).await;
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
 
     let register_req = test::TestRequest::post()
         .uri("/auth/register")
@@ -170,18 +267,14 @@ async fn payment_idempotency() {
         }))
         .to_request();
 
-    let register_resp: serde_json::Value = test::call_and_read_body_json(&mut app, register_req).await;
+    let register_resp: serde_json::Value = test::call_and_read_body_json(&app, register_req).await;
     let token = register_resp
         .get("token")
         .and_then(|v| v.as_str())
         .unwrap()
         .to_string();
 
-    sqlx::query("UPDATE users SET balance = 1000.0 WHERE phone_number = $1")
-        .bind("9876543210")
-        .execute(&db)
-        .await
-        .expect("failed to set balance");
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
 
     let idempotency_key = "same-key";
     let init_req_1 = test::TestRequest::post()
@@ -194,7 +287,7 @@ async fn payment_idempotency() {
         }))
         .to_request();
 
-    let init_resp_1: serde_json::Value = test::call_and_read_body_json(&mut app, init_req_1).await;
+    let init_resp_1: serde_json::Value = test::call_and_read_body_json(&app, init_req_1).await;
     let session_id_1 = init_resp_1.get("session_id").and_then(|v| v.as_str()).unwrap().to_string();
 
     let init_req_2 = test::TestRequest::post()
@@ -207,8 +300,2332 @@ async fn payment_idempotency() {
         }))
         .to_request();
 
-    let init_resp_2: serde_json::Value = test::call_and_read_body_json(&mut app, init_req_2).await;
+    let init_resp_2: serde_json::Value = test::call_and_read_body_json(&app, init_req_2).await;
     let session_id_2 = init_resp_2.get("session_id").and_then(|v| v.as_str()).unwrap().to_string();
 
     assert_eq!(session_id_1, session_id_2);
 }
+
+async fn seed_merchant(db: &PgPool, upi_id: &str, qr_data: &str) -> Uuid {
+    let row: (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO merchants (name, upi_id, category, phone, qr_code_data)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind("Coffee Shop")
+    .bind(upi_id)
+    .bind("food")
+    .bind("9999999999")
+    .bind(qr_data)
+    .fetch_one(db)
+    .await
+    .expect("failed to seed merchant");
+    row.0
+}
+
+async fn grant_role(db: &PgPool, phone_number: &str, role: &str) {
+    sqlx::query("UPDATE users SET role = $2::user_role WHERE phone_number = $1")
+        .bind(phone_number)
+        .bind(role)
+        .execute(db)
+        .await
+        .expect("failed to grant role");
+}
+
+async fn own_merchant(db: &PgPool, merchant_id: Uuid, phone_number: &str) {
+    sqlx::query("UPDATE merchants SET owner_id = (SELECT id FROM users WHERE phone_number = $2) WHERE id = $1")
+        .bind(merchant_id)
+        .bind(phone_number)
+        .execute(db)
+        .await
+        .expect("failed to set merchant owner");
+}
+
+async fn fund_user(db: &PgPool, phone_number: &str, amount: Money) {
+    let user_id: (Uuid,) = sqlx::query_as("SELECT id FROM users WHERE phone_number = $1")
+        .bind(phone_number)
+        .fetch_one(db)
+        .await
+        .expect("failed to find user");
+    let mut tx = db.begin().await.unwrap();
+    ledger::post(
+        &mut tx,
+        None,
+        "test funding",
+        &[
+            Posting::debit(LedgerAccount::funding(), amount),
+            Posting::credit(LedgerAccount::user_wallet(user_id.0), amount),
+        ],
+    )
+    .await
+    .expect("failed to fund user");
+    tx.commit().await.unwrap();
+}
+
+async fn register_user<S>(app: &S, phone_number: &str, upi_id: &str) -> String
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<actix_web::body::BoxBody>,
+        Error = actix_web::Error,
+    >,
+{
+    let register_req = test::TestRequest::post()
+        .uri("/auth/register")
+        .set_json(json!({
+            "phone_number": phone_number,
+            "upi_id": upi_id,
+            "name": "Test User",
+            "pin": "1234"
+        }))
+        .to_request();
+
+    let register_resp: serde_json::Value = test::call_and_read_body_json(app, register_req).await;
+    register_resp
+        .get("token")
+        .and_then(|v| v.as_str())
+        .unwrap()
+        .to_string()
+}
+
+#[actix_web::test]
+async fn resolve_merchant_by_canonical_vpa() {
+    let (_guard, cfg, db, redis) = setup().await;
+    seed_merchant(&db, "coffeeshop@upi", "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100").await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+
+    let resolve_req = test::TestRequest::post()
+        .uri("/api/merchant/resolve")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({
+            "qr_data": "upi://pay?tn=Latte&am=100&pn=Coffee+Shop&pa=CoffeeShop%40upi&tr=ORD-1"
+        }))
+        .to_request();
+
+    let resolve_resp: serde_json::Value = test::call_and_read_body_json(&app, resolve_req).await;
+    assert_eq!(resolve_resp.get("upi_id").and_then(|v| v.as_str()), Some("coffeeshop@upi"));
+
+    let bharat_qr = "00020101021126320010A0000005240114coffeeshop@upi5204581453033565802IN5911Coffee Shop6009Bengaluru6304";
+    let bharat_qr = format!(
+        "{}{:04X}",
+        bharat_qr,
+        qr_payment_backend::utils::emv_qr::crc16_ccitt(bharat_qr.as_bytes())
+    );
+    let emv_req = test::TestRequest::post()
+        .uri("/api/merchant/resolve")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "qr_data": bharat_qr }))
+        .to_request();
+
+    let emv_resp: serde_json::Value = test::call_and_read_body_json(&app, emv_req).await;
+    assert_eq!(emv_resp.get("upi_id").and_then(|v| v.as_str()), Some("coffeeshop@upi"));
+
+    let malformed_req = test::TestRequest::post()
+        .uri("/api/merchant/resolve")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "qr_data": "upi://pay?pn=Coffee%20Shop&am=100" }))
+        .to_request();
+
+    let malformed_resp = test::call_service(&app, malformed_req).await;
+    assert_eq!(malformed_resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn qr_amount_and_currency_are_enforced() {
+    let (_guard, cfg, db, redis) = setup().await;
+    seed_merchant(&db, "coffeeshop@upi", "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100").await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+
+    let initiate = |qr_data: &str, amount: Option<f64>, key: &str| {
+        test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({
+                "qr_data": qr_data,
+                "amount": amount,
+                "idempotency_key": key
+            }))
+            .to_request()
+    };
+
+    let fixed_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    let locked: serde_json::Value = test::call_and_read_body_json(&app, initiate(fixed_qr, None, "fixed-1")).await;
+    assert_eq!(locked.get("amount").and_then(|v| v.as_f64()), Some(100.0));
+    assert_eq!(locked.get("amount_editable").and_then(|v| v.as_bool()), Some(false));
+
+    let mismatch = test::call_service(&app, initiate(fixed_qr, Some(1.0), "fixed-2")).await;
+    assert_eq!(mismatch.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let min_qr = "upi://pay?pa=coffeeshop@upi&am=100&mam=50";
+    let editable: serde_json::Value = test::call_and_read_body_json(&app, initiate(min_qr, Some(75.0), "min-1")).await;
+    assert_eq!(editable.get("amount").and_then(|v| v.as_f64()), Some(75.0));
+    assert_eq!(editable.get("amount_editable").and_then(|v| v.as_bool()), Some(true));
+
+    let below_min = test::call_service(&app, initiate(min_qr, Some(49.99), "min-2")).await;
+    assert_eq!(below_min.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let usd_qr = "upi://pay?pa=coffeeshop@upi&am=100&cu=USD";
+    let usd = test::call_service(&app, initiate(usd_qr, None, "usd-1")).await;
+    assert_eq!(usd.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn merchant_qr_is_rendered() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop").await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+
+    let png_req = test::TestRequest::get()
+        .uri(&format!("/api/merchant/{}/qr?amount=100&size=256&ec=Q", merchant_id))
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let png_resp = test::call_service(&app, png_req).await;
+    assert_eq!(png_resp.status(), actix_web::http::StatusCode::OK);
+    assert_eq!(
+        png_resp.headers().get("content-type").and_then(|v| v.to_str().ok()),
+        Some("image/png")
+    );
+
+    let svg_req = test::TestRequest::get()
+        .uri(&format!("/api/merchant/{}/qr?format=svg", merchant_id))
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let svg_body = test::call_and_read_body(&app, svg_req).await;
+    assert!(String::from_utf8_lossy(&svg_body).contains("<svg"));
+
+    let bad_size_req = test::TestRequest::get()
+        .uri(&format!("/api/merchant/{}/qr?size=1", merchant_id))
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let bad_size_resp = test::call_service(&app, bad_size_req).await;
+    assert_eq!(bad_size_resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn uploaded_qr_image_is_decoded_and_resolved() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop";
+    seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+
+    let png = qr_render::render(merchant_qr, QrImageFormat::Png, 256, QrErrorCorrection::M).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/qr/decode")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .insert_header(("Content-Type", "image/png"))
+        .set_payload(png)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["codes"][0]["payload"], merchant_qr);
+    assert_eq!(body["codes"][0]["merchant"]["upi_id"], "coffeeshop@upi");
+
+    let unknown = qr_render::render("upi://pay?pa=nobody@upi", QrImageFormat::Png, 256, QrErrorCorrection::M).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/qr/decode")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .insert_header(("Content-Type", "image/png"))
+        .set_payload(unknown)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["codes"][0]["merchant"].is_null());
+    assert!(body["codes"][0]["error"].is_string());
+
+    let req = test::TestRequest::post()
+        .uri("/api/qr/decode")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .insert_header(("Content-Type", "text/plain"))
+        .set_payload("upi://pay?pa=coffeeshop@upi")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let blank = image::GrayImage::from_pixel(64, 64, image::Luma([255]));
+    let mut blank_png = Vec::new();
+    blank
+        .write_to(&mut std::io::Cursor::new(&mut blank_png), image::ImageFormat::Png)
+        .unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/qr/decode")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .insert_header(("Content-Type", "image/png"))
+        .set_payload(blank_png)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn signed_qr_marks_merchant_verified() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop").await;
+    let key = SigningKey::from_bytes(&[7; 32]);
+    // A key that was stored malformed before registration validated keys must not break verification.
+    sqlx::query("INSERT INTO merchant_keys (merchant_id, public_key) VALUES ($1, 'not-a-key')")
+        .bind(merchant_id)
+        .execute(&db)
+        .await
+        .expect("failed to seed merchant key");
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+    let owner_token = register_user(&app, "9111111111", "owner@paytm").await;
+    own_merchant(&db, merchant_id, "9111111111").await;
+
+    let register_key = |token: &str, public_key: String| {
+        test::TestRequest::post()
+            .uri(&format!("/api/merchant/{}/keys", merchant_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "public_key": public_key }))
+            .to_request()
+    };
+    let public_key = URL_SAFE_NO_PAD.encode(key.verifying_key().to_bytes());
+    let resp = test::call_service(&app, register_key(&token, public_key.clone())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, register_key(&owner_token, "short".to_string())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let registered: serde_json::Value =
+        test::call_and_read_body_json(&app, register_key(&owner_token, public_key.clone())).await;
+    assert_eq!(registered["public_key"], STANDARD.encode(key.verifying_key().to_bytes()));
+    let resp = test::call_service(&app, register_key(&owner_token, public_key)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let mut intent = UpiIntent::for_payee("coffeeshop@upi", "Coffee Shop");
+    intent.amount = Some(Money::from_rupees(100));
+    intent.signature = Some(STANDARD.encode(key.sign(intent.signed_payload().as_bytes()).to_bytes()));
+    let signed = intent.to_uri();
+
+    let resolve = |qr_data: String| {
+        test::TestRequest::post()
+            .uri("/api/merchant/resolve")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": qr_data }))
+            .to_request()
+    };
+
+    let body: serde_json::Value = test::call_and_read_body_json(&app, resolve(signed.clone())).await;
+    assert_eq!(body["verified"], true);
+
+    let body: serde_json::Value =
+        test::call_and_read_body_json(&app, resolve("upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop".to_string())).await;
+    assert_eq!(body["verified"], false);
+
+    let tampered = signed.replace("am=100.00", "am=1.00");
+    let resp = test::call_service(&app, resolve(tampered.clone())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let init_req = test::TestRequest::post()
+        .uri("/api/payment/initiate")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "qr_data": signed, "idempotency_key": "signed-1" }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
+    assert_eq!(body["payee"]["verified"], true);
+
+    let init_req = test::TestRequest::post()
+        .uri("/api/payment/initiate")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "qr_data": tampered, "idempotency_key": "signed-2" }))
+        .to_request();
+    let resp = test::call_service(&app, init_req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let revoke_req = test::TestRequest::delete()
+        .uri(&format!("/api/merchant/{}/keys/{}", merchant_id, registered["id"].as_str().unwrap()))
+        .insert_header(("Authorization", format!("Bearer {}", owner_token)))
+        .to_request();
+    let revoked: serde_json::Value = test::call_and_read_body_json(&app, revoke_req).await;
+    assert!(revoked["revoked_at"].is_string());
+    let resp = test::call_service(&app, resolve(signed)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn dynamic_order_qr_is_single_use_and_expires() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop").await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+    let owner_token = register_user(&app, "9111111111", "owner@paytm").await;
+    own_merchant(&db, merchant_id, "9111111111").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+
+    let create_order_as = |token: &str, amount: f64| {
+        test::TestRequest::post()
+            .uri(&format!("/api/merchant/{}/orders", merchant_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "amount": amount, "note": "Table 4" }))
+            .to_request()
+    };
+    let create_order = |amount: f64| create_order_as(&owner_token, amount);
+    let initiate = |qr_data: &str, key: &str| {
+        test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": qr_data, "idempotency_key": key }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, create_order_as(&token, 250.0)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let order: serde_json::Value = test::call_and_read_body_json(&app, create_order(250.0)).await;
+    assert_eq!(order["status"], "open");
+    let qr_data = order["qr_data"].as_str().unwrap().to_string();
+    assert!(qr_data.contains(&format!("tr={}", order["transaction_ref"].as_str().unwrap())));
+
+    let init: serde_json::Value = test::call_and_read_body_json(&app, initiate(&qr_data, "order-1")).await;
+    assert_eq!(init["amount"], 250.0);
+    let exec_req = test::TestRequest::post()
+        .uri("/api/payment/execute")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+        .to_request();
+    let exec: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
+    assert_eq!(exec["status"], "success");
+
+    let get_req = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/merchant/{}/orders/{}", merchant_id, order["order_id"].as_str().unwrap()))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+    let resp = test::call_service(&app, get_req(&token)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let paid: serde_json::Value = test::call_and_read_body_json(&app, get_req(&owner_token)).await;
+    assert_eq!(paid["status"], "paid");
+    assert_eq!(paid["transaction_id"], exec["transaction_id"]);
+
+    let resp = test::call_service(&app, initiate(&qr_data, "order-2")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let expiring: serde_json::Value = test::call_and_read_body_json(&app, create_order(80.0)).await;
+    sqlx::query("UPDATE merchant_orders SET expires_at = expires_at - INTERVAL '1 day' WHERE id = $1")
+        .bind(Uuid::parse_str(expiring["order_id"].as_str().unwrap()).unwrap())
+        .execute(&db)
+        .await
+        .expect("failed to expire order");
+    let resp = test::call_service(&app, initiate(expiring["qr_data"].as_str().unwrap(), "order-3")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn p2p_payment_credits_payee_user() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "payer@paytm").await;
+    register_user(&app, "9123456780", "friend@okbank").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+
+    let initiate = |qr_data: &str, key: &str| {
+        test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": qr_data, "idempotency_key": key }))
+            .to_request()
+    };
+
+    let init: serde_json::Value =
+        test::call_and_read_body_json(&app, initiate("upi://pay?pa=Friend@OkBank&pn=Friend&am=150", "p2p-1")).await;
+    assert_eq!(init["payee"]["payee_type"], "user");
+    assert_eq!(init["payee"]["upi_id"], "friend@okbank");
+
+    let exec_req = test::TestRequest::post()
+        .uri("/api/payment/execute")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+        .to_request();
+    let exec: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
+    assert_eq!(exec["status"], "success");
+
+    let balances: Vec<(String, Money)> = sqlx::query_as("SELECT phone_number, balance FROM users ORDER BY phone_number")
+        .fetch_all(&db)
+        .await
+        .unwrap();
+    assert_eq!(
+        balances,
+        vec![
+            ("9123456780".to_string(), Money::from_rupees(150)),
+            ("9876543210".to_string(), Money::from_rupees(850)),
+        ]
+    );
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+
+    let init: serde_json::Value =
+        test::call_and_read_body_json(&app, initiate("upi://pay?pa=9123456780@upi&am=10", "p2p-2")).await;
+    assert_eq!(init["payee"]["upi_id"], "friend@okbank");
+
+    let resp = test::call_service(&app, initiate("upi://pay?pa=payer@paytm&am=10", "p2p-3")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, initiate("upi://pay?pa=stranger@upi&am=10", "p2p-4")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn payment_is_posted_to_the_ledger() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+    let owner_token = register_user(&app, "9111111111", "owner@paytm").await;
+    own_merchant(&db, merchant_id, "9111111111").await;
+    let finance_token = register_user(&app, "9000000002", "finance@paytm").await;
+    grant_role(&db, "9000000002", "finance").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+
+    let init_req = test::TestRequest::post()
+        .uri("/api/payment/initiate")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "qr_data": merchant_qr, "idempotency_key": "ledger-1" }))
+        .to_request();
+    let init: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
+    let exec_req = test::TestRequest::post()
+        .uri("/api/payment/execute")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+        .to_request();
+    let exec: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
+    assert_eq!(exec["status"], "success");
+
+    let transaction_id = Uuid::parse_str(exec["transaction_id"].as_str().unwrap()).unwrap();
+    let entries = ledger::entries_for_transaction(&db, transaction_id).await.unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| e.amount == Money::from_rupees(100) && e.journal_id == entries[0].journal_id));
+
+    let balance_req = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/merchant/{}/balance", merchant_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+    let resp = test::call_service(&app, balance_req(&token)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    for token in [&owner_token, &finance_token] {
+        let balance: serde_json::Value = test::call_and_read_body_json(&app, balance_req(token)).await;
+        assert_eq!(balance["payable"], 100.0);
+    }
+
+    let user_id = entries.iter().find_map(|e| (e.account_id != Some(merchant_id)).then_some(e.account_id)).flatten();
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::user_wallet(user_id.unwrap())).await.unwrap(),
+        Money::from_rupees(900)
+    );
+    assert_eq!(ledger::balance(&db, LedgerAccount::funding()).await.unwrap(), Money::from_rupees(1000));
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn settlement_batches_follow_merchant_schedules() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let next_day = seed_merchant(&db, "bakery@upi", "upi://pay?pa=bakery@upi").await;
+    let same_day = seed_merchant(&db, "florist@upi", "upi://pay?pa=florist@upi").await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+
+    let schedule_req = || {
+        test::TestRequest::put()
+            .uri(&format!("/api/merchant/{}/settlement-schedule", same_day))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "schedule": "t0" }))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, schedule_req()).await.status(), actix_web::http::StatusCode::FORBIDDEN);
+    grant_role(&db, "9876543210", "finance").await;
+    assert_eq!(test::call_service(&app, schedule_req()).await.status(), actix_web::http::StatusCode::NO_CONTENT);
+
+    let as_of = chrono::Utc::now().date_naive().and_hms_opt(12, 0, 0).unwrap();
+    let payments = [
+        (next_day, Money::from_rupees(100), as_of - chrono::Duration::days(1)),
+        (next_day, Money::from_rupees(50), as_of - chrono::Duration::hours(1)),
+        (same_day, Money::from_rupees(30), as_of - chrono::Duration::hours(2)),
+    ];
+    for (i, (merchant_id, amount, at)) in payments.iter().enumerate() {
+        sqlx::query(
+            r#"
+            INSERT INTO transactions (merchant_id, amount, status, idempotency_key, created_at, updated_at, completed_at)
+            VALUES ($1, $2, 'success', $3, $4, $4, $4)
+            "#,
+        )
+        .bind(merchant_id)
+        .bind(amount)
+        .bind(format!("settle-{}", i))
+        .bind(at)
+        .execute(&db)
+        .await
+        .expect("failed to seed transaction");
+    }
+    let mut tx = db.begin().await.unwrap();
+    ledger::post(
+        &mut tx,
+        None,
+        "test payments",
+        &[
+            Posting::debit(LedgerAccount::funding(), Money::from_rupees(180)),
+            Posting::credit(LedgerAccount::merchant_payable(next_day), Money::from_rupees(150)),
+            Posting::credit(LedgerAccount::merchant_payable(same_day), Money::from_rupees(30)),
+        ],
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+
+    let batches = settlement::run_settlement(&db, Some(as_of)).await.unwrap();
+    assert_eq!(batches.len(), 2);
+    let next_day_batch = batches.iter().find(|b| b.merchant_id == next_day).unwrap();
+    assert_eq!(next_day_batch.amount, Money::from_rupees(100));
+    assert_eq!(next_day_batch.transaction_count, 1);
+    let same_day_batch = batches.iter().find(|b| b.merchant_id == same_day).unwrap();
+    assert_eq!(same_day_batch.amount, Money::from_rupees(30));
+    assert!(settlement::run_settlement(&db, Some(as_of)).await.unwrap().is_empty());
+
+    let status_req = |batch_id: Uuid, body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&format!("/api/settlements/{}/status", batch_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(body)
+            .to_request()
+    };
+    let resp = test::call_service(&app, status_req(next_day_batch.id, json!({ "status": "paid" }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let paid: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        status_req(next_day_batch.id, json!({ "status": "paid", "payout_reference": "NEFT123" })),
+    )
+    .await;
+    assert_eq!(paid["status"], "paid");
+    let failed: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        status_req(same_day_batch.id, json!({ "status": "failed", "failure_reason": "account closed" })),
+    )
+    .await;
+    assert_eq!(failed["status"], "failed");
+    let resp = test::call_service(&app, status_req(next_day_batch.id, json!({ "status": "failed" }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(next_day)).await.unwrap(),
+        Money::from_rupees(50)
+    );
+
+    let report_req = test::TestRequest::get()
+        .uri("/api/settlements/report")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let report: serde_json::Value = test::call_and_read_body_json(&app, report_req).await;
+    let summary = |merchant_id: Uuid| {
+        report["merchants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["merchant_id"] == merchant_id.to_string())
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(summary(next_day)["paid"], 100.0);
+    assert_eq!(summary(next_day)["outstanding"], 50.0);
+    assert_eq!(summary(same_day)["schedule"], "t0");
+    assert_eq!(summary(same_day)["outstanding"], 30.0);
+    assert_eq!(report["batches"].as_array().unwrap().len(), 2);
+
+    let rerun = settlement::run_settlement(&db, Some(as_of)).await.unwrap();
+    assert_eq!(rerun.len(), 1);
+    assert_eq!(rerun[0].merchant_id, same_day);
+
+    // The endpoint settles up to the database clock, which also stamped the payment.
+    sqlx::query(
+        r#"
+        INSERT INTO transactions (merchant_id, amount, status, idempotency_key, completed_at)
+        VALUES ($1, $2, 'success', 'settle-now', LOCALTIMESTAMP - INTERVAL '1 second')
+        "#,
+    )
+    .bind(same_day)
+    .bind(Money::from_rupees(20))
+    .execute(&db)
+    .await
+    .expect("failed to seed transaction");
+    let run_req = test::TestRequest::post()
+        .uri("/api/settlements/run")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let run: serde_json::Value = test::call_and_read_body_json(&app, run_req).await;
+    let batches = run["batches"].as_array().unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0]["merchant_id"], same_day.to_string());
+    assert_eq!(batches[0]["transaction_count"], 1);
+}
+
+#[actix_web::test]
+async fn refunds_after_settlement_are_netted_from_later_batches() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+    let admin_token = register_user(&app, "9000000001", "admin@paytm").await;
+    grant_role(&db, "9000000001", "admin").await;
+
+    let pay = |amount: i64, key: &str| {
+        let init_req = test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": merchant_qr, "amount": amount, "idempotency_key": key }))
+            .to_request();
+        let token = token.clone();
+        let app = &app;
+        async move {
+            let init: serde_json::Value = test::call_and_read_body_json(app, init_req).await;
+            let exec_req = test::TestRequest::post()
+                .uri("/api/payment/execute")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+                .to_request();
+            let exec: serde_json::Value = test::call_and_read_body_json(app, exec_req).await;
+            assert_eq!(exec["status"], "success");
+            exec["transaction_id"].as_str().unwrap().to_string()
+        }
+    };
+    let pay_out = |batch_id: Uuid| {
+        test::TestRequest::post()
+            .uri(&format!("/api/settlements/{}/status", batch_id))
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .set_json(json!({ "status": "paid", "payout_reference": format!("NEFT-{}", batch_id) }))
+            .to_request()
+    };
+    let as_of = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+
+    let first = pay(100, "net-1").await;
+    let batches = settlement::run_settlement(&db, Some(as_of)).await.unwrap();
+    assert_eq!(batches[0].amount, Money::from_rupees(100));
+    assert_eq!(test::call_service(&app, pay_out(batches[0].id)).await.status(), actix_web::http::StatusCode::OK);
+
+    let refund_req = test::TestRequest::post()
+        .uri(&format!("/api/payment/{}/refund", first))
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .set_json(json!({ "amount": 40, "refund_key": "net-refund" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, refund_req).await.status(), actix_web::http::StatusCode::OK);
+
+    // The refund is more than the next day's takings, so that batch pays nothing and the rest waits.
+    pay(30, "net-2").await;
+    let batches = settlement::run_settlement(&db, Some(as_of)).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].amount, Money::ZERO);
+    assert_eq!(batches[0].transaction_count, 1);
+    assert_eq!(test::call_service(&app, pay_out(batches[0].id)).await.status(), actix_web::http::StatusCode::OK);
+
+    pay(50, "net-3").await;
+    let batches = settlement::run_settlement(&db, Some(as_of)).await.unwrap();
+    assert_eq!(batches[0].amount, Money::from_rupees(40));
+    assert_eq!(test::call_service(&app, pay_out(batches[0].id)).await.status(), actix_web::http::StatusCode::OK);
+
+    assert_eq!(ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(), Money::ZERO);
+    let completed: (chrono::NaiveDateTime, chrono::NaiveDateTime) =
+        sqlx::query_as("SELECT completed_at, updated_at FROM transactions WHERE idempotency_key = 'net-1'")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert!(completed.0 < completed.1);
+}
+
+#[actix_web::test]
+async fn wallet_top_up_credits_balance_once() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let app = init_app(cfg.clone(), db.clone(), redis.clone()).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+
+    let top_up = |amount: f64, instrument: &str, key: &str| {
+        test::TestRequest::post()
+            .uri("/api/wallet/topup")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "amount": amount, "instrument": instrument, "idempotency_key": key }))
+            .to_request()
+    };
+
+    let first: serde_json::Value = test::call_and_read_body_json(&app, top_up(500.0, "card-4242", "topup-1")).await;
+    assert_eq!(first["status"], "success");
+    assert_eq!(first["balance"], 500.0);
+    assert!(first["provider_reference"].as_str().unwrap().starts_with("SIM"));
+
+    let replay: serde_json::Value = test::call_and_read_body_json(&app, top_up(500.0, "card-4242", "topup-1")).await;
+    assert_eq!(replay["topup_id"], first["topup_id"]);
+    assert_eq!(replay["balance"], 500.0);
+
+    let declined: serde_json::Value =
+        test::call_and_read_body_json(&app, top_up(100.0, "decline-card", "topup-2")).await;
+    assert_eq!(declined["status"], "failed");
+    assert_eq!(declined["balance"], 500.0);
+
+    let pending: serde_json::Value =
+        test::call_and_read_body_json(&app, top_up(100.0, "unavailable-bank", "topup-3")).await;
+    assert_eq!(pending["status"], "pending");
+
+    let resp = test::call_service(&app, top_up(0.001, "card-4242", "topup-4")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let get_req = test::TestRequest::get()
+        .uri(&format!("/api/wallet/topup/{}", first["topup_id"].as_str().unwrap()))
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let fetched: serde_json::Value = test::call_and_read_body_json(&app, get_req).await;
+    assert_eq!(fetched["status"], "success");
+
+    let balance_req = test::TestRequest::get()
+        .uri("/api/wallet/balance")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let balance: serde_json::Value = test::call_and_read_body_json(&app, balance_req).await;
+    assert_eq!(balance["balance"], 500.0);
+    assert_eq!(ledger::balance(&db, LedgerAccount::funding()).await.unwrap(), Money::from_rupees(500));
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+
+    // Without a funding source the app still serves everything else, but refuses to top up.
+    let unfunded = init_app_with_funding(cfg, db.clone(), redis, None).await;
+    let resp = test::call_service(&unfunded, top_up(100.0, "card-4242", "topup-5")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let balance_req = test::TestRequest::get()
+        .uri("/api/wallet/balance")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let balance: serde_json::Value = test::call_and_read_body_json(&unfunded, balance_req).await;
+    assert_eq!(balance["balance"], 500.0);
+}
+
+#[actix_web::test]
+async fn refunds_are_partial_cumulative_and_idempotent() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+
+    let app = init_app(cfg.clone(), db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+    let owner_token = register_user(&app, "9111111111", "owner@paytm").await;
+    own_merchant(&db, merchant_id, "9111111111").await;
+    let admin_token = register_user(&app, "9000000001", "admin@paytm").await;
+    grant_role(&db, "9000000001", "admin").await;
+
+    let init_req = test::TestRequest::post()
+        .uri("/api/payment/initiate")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "qr_data": merchant_qr, "idempotency_key": "refund-pay-1" }))
+        .to_request();
+    let init: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
+    let transaction_id = init["session_id"].as_str().unwrap().to_string();
+
+    let refund_as = |token: &str, body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&format!("/api/payment/{}/refund", transaction_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(body)
+            .to_request()
+    };
+    let refund = |body: serde_json::Value| refund_as(&owner_token, body);
+
+    let resp = test::call_service(&app, refund(json!({ "refund_key": "r-0" }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let exec_req = test::TestRequest::post()
+        .uri("/api/payment/execute")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "session_id": transaction_id, "pin": "1234" }))
+        .to_request();
+    test::call_service(&app, exec_req).await;
+
+    let resp = test::call_service(&app, refund_as(&token, json!({ "amount": 30, "refund_key": "r-payer" }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    let partial: serde_json::Value =
+        test::call_and_read_body_json(&app, refund(json!({ "amount": 30, "refund_key": "r-1", "reason": "cold coffee" })))
+            .await;
+    assert_eq!(partial["refunded_amount"], 30.0);
+    assert_eq!(partial["transaction_status"], "success");
+
+    let replay: serde_json::Value =
+        test::call_and_read_body_json(&app, refund(json!({ "amount": 30, "refund_key": "r-1" }))).await;
+    assert_eq!(replay["refund_id"], partial["refund_id"]);
+
+    let resp = test::call_service(&app, refund_as(&admin_token, json!({ "amount": 80, "refund_key": "r-2" }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let rest: serde_json::Value =
+        test::call_and_read_body_json(&app, refund_as(&admin_token, json!({ "refund_key": "r-3" }))).await;
+    assert_eq!(rest["amount"], 70.0);
+    assert_eq!(rest["refunded_amount"], 100.0);
+    assert_eq!(rest["transaction_status"], "refunded");
+
+    let resp = test::call_service(&app, refund_as(&admin_token, json!({ "refund_key": "r-4" }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let initiators: Vec<String> = sqlx::query_scalar("SELECT initiated_by::TEXT FROM refunds ORDER BY created_at")
+        .fetch_all(&db)
+        .await
+        .unwrap();
+    assert_eq!(initiators, vec!["merchant", "admin"]);
+
+    let balance_req = test::TestRequest::get()
+        .uri("/api/wallet/balance")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let balance: serde_json::Value = test::call_and_read_body_json(&app, balance_req).await;
+    assert_eq!(balance["balance"], 1000.0);
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::ZERO
+    );
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn spending_limits_are_enforced_on_execute() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let monthly = cfg.spending_limits.monthly;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "payer@paytm").await;
+    register_user(&app, "9123456780", "friend@okbank").await;
+    fund_user(&db, "9876543210", Money::from_rupees(5000)).await;
+    let user_id: Uuid = sqlx::query_scalar("SELECT id FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+
+    let admin_limits_req = |token: &str, limits: serde_json::Value| {
+        test::TestRequest::put()
+            .uri(&format!("/api/users/{}/limits", user_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(limits)
+            .to_request()
+    };
+    let own_limits_req = |limits: serde_json::Value| {
+        test::TestRequest::put()
+            .uri("/api/limits")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(limits)
+            .to_request()
+    };
+    let resp = test::call_service(&app, admin_limits_req(&token, json!({ "per_transaction": 1000 }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    let limits: serde_json::Value =
+        test::call_and_read_body_json(&app, own_limits_req(json!({ "per_transaction": 1000, "daily": 1500 }))).await;
+    assert_eq!(limits["limits"]["per_transaction"], 1000.0);
+    assert_eq!(limits["remaining"]["daily"], 1500.0);
+    let resp = test::call_service(&app, own_limits_req(json!({ "daily": 1600 }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    let admin_token = register_user(&app, "9000000001", "admin@paytm").await;
+    grant_role(&db, "9000000001", "admin").await;
+    let limits: serde_json::Value =
+        test::call_and_read_body_json(&app, admin_limits_req(&admin_token, json!({ "per_transaction": 1000, "daily": 1500 })))
+            .await;
+    assert_eq!(limits["limits"]["per_transaction"], 1000.0);
+    assert_eq!(limits["remaining"]["daily"], 1500.0);
+
+    let pay = |amount: &str, key: &str| {
+        let init_req = test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": format!("upi://pay?pa=friend@okbank&am={}", amount), "idempotency_key": key }))
+            .to_request();
+        let token = token.clone();
+        let app = &app;
+        async move {
+            let init: serde_json::Value = test::call_and_read_body_json(app, init_req).await;
+            let exec_req = test::TestRequest::post()
+                .uri("/api/payment/execute")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+                .to_request();
+            let resp = test::call_service(app, exec_req).await;
+            let status = resp.status();
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            (status, body)
+        }
+    };
+
+    let (status, body) = pay("1200", "limit-1").await;
+    assert_eq!(status, actix_web::http::StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "per_transaction_limit_exceeded");
+    assert_eq!(body["remaining_limit"], 1000.0);
+
+    let (status, body) = pay("800", "limit-2").await;
+    assert_eq!(status, actix_web::http::StatusCode::OK);
+    assert_eq!(body["remaining_limits"]["daily"], 700.0);
+
+    let (status, body) = pay("800", "limit-3").await;
+    assert_eq!(status, actix_web::http::StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "daily_limit_exceeded");
+    assert_eq!(body["remaining_limit"], 700.0);
+
+    let get_req = test::TestRequest::get()
+        .uri("/api/limits")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let limits: serde_json::Value = test::call_and_read_body_json(&app, get_req).await;
+    assert_eq!(limits["remaining"]["daily"], 700.0);
+    assert_eq!(limits["remaining"]["monthly"], serde_json::to_value(monthly - Money::from_rupees(800)).unwrap());
+
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(4200));
+}
+
+#[actix_web::test]
+async fn merchant_fees_follow_category_and_slab_rules() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(5000)).await;
+
+    let create_rule = |rule: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/fee-rules")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(rule)
+            .to_request()
+    };
+    let resp = test::call_service(&app, create_rule(json!({ "max_amount": 2000 }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    grant_role(&db, "9876543210", "admin").await;
+    for rule in [
+        json!({ "max_amount": 2000 }),
+        json!({ "min_amount": 2000.01, "percentage_bps": 100, "max_fee": 15 }),
+        json!({ "category": "Food", "min_amount": 2000.01, "percentage_bps": 50, "flat_fee": 1 }),
+    ] {
+        let resp = test::call_service(&app, create_rule(rule)).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    }
+    let resp = test::call_service(&app, create_rule(json!({ "percentage_bps": 20000 }))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let mut transaction_ids = Vec::new();
+    for (amount, key) in [(2500, "fee-1"), (500, "fee-2")] {
+        let init_req = test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": merchant_qr, "amount": amount, "idempotency_key": key }))
+            .to_request();
+        let init: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
+        let exec_req = test::TestRequest::post()
+            .uri("/api/payment/execute")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+            .to_request();
+        let exec: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
+        assert_eq!(exec["status"], "success");
+        transaction_ids.push(Uuid::parse_str(exec["transaction_id"].as_str().unwrap()).unwrap());
+    }
+
+    let fees: Vec<(Money, Money)> =
+        sqlx::query_as("SELECT fee_amount, net_amount FROM transactions WHERE id = ANY($1) ORDER BY amount DESC")
+            .bind(&transaction_ids)
+            .fetch_all(&db)
+            .await
+            .unwrap();
+    assert_eq!(
+        fees,
+        vec![
+            ("13.50".parse().unwrap(), "2486.50".parse().unwrap()),
+            (Money::ZERO, Money::from_rupees(500)),
+        ]
+    );
+    assert_eq!(ledger::balance(&db, LedgerAccount::fee_revenue()).await.unwrap(), "13.50".parse().unwrap());
+
+    let refund_req = test::TestRequest::post()
+        .uri(&format!("/api/payment/{}/refund", transaction_ids[0]))
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "amount": 1250, "refund_key": "fee-refund-1" }))
+        .to_request();
+    let resp = test::call_service(&app, refund_req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    assert_eq!(ledger::balance(&db, LedgerAccount::fee_revenue()).await.unwrap(), "6.75".parse().unwrap());
+
+    let payable: Money = "1743.25".parse().unwrap();
+    assert_eq!(ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(), payable);
+
+    let as_of = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+    let batches = settlement::run_settlement(&db, Some(as_of)).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].amount, payable);
+}
+
+#[actix_web::test]
+async fn cashback_is_credited_once_and_clawed_back_on_refund() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop";
+    seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "testuser@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+    let admin_token = register_user(&app, "9000000001", "admin@paytm").await;
+    grant_role(&db, "9000000001", "admin").await;
+
+    let now = chrono::Utc::now().naive_utc();
+    for (name, starts_at, ends_at) in [
+        ("first food payment", now - chrono::Duration::days(1), now + chrono::Duration::days(1)),
+        ("last week", now - chrono::Duration::days(8), now - chrono::Duration::days(1)),
+    ] {
+        let campaign_req = test::TestRequest::post()
+            .uri("/api/reward-campaigns")
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .set_json(json!({
+                "name": name,
+                "category": "Food",
+                "cohort": "first_in_category",
+                "percentage_bps": 1000,
+                "max_reward": 50,
+                "starts_at": starts_at,
+                "ends_at": ends_at,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, campaign_req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    }
+    let campaign_req = test::TestRequest::post()
+        .uri("/api/reward-campaigns")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "name": "self-serve", "percentage_bps": 10000, "starts_at": now, "ends_at": now }))
+        .to_request();
+    assert_eq!(test::call_service(&app, campaign_req).await.status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    let mut transaction_ids = Vec::new();
+    for (amount, key, cashback) in [(300, "reward-1", 30.0), (600, "reward-2", 0.0)] {
+        let init_req = test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": merchant_qr, "amount": amount, "idempotency_key": key }))
+            .to_request();
+        let init: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
+        let exec_req = test::TestRequest::post()
+            .uri("/api/payment/execute")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+            .to_request();
+        let exec: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
+        assert_eq!(exec["status"], "success");
+        assert_eq!(exec["cashback"], cashback);
+        transaction_ids.push(exec["transaction_id"].as_str().unwrap().to_string());
+    }
+
+    let refund = |amount: Option<i64>, key: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/payment/{}/refund", transaction_ids[0]))
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .set_json(json!({ "amount": amount, "refund_key": key }))
+            .to_request()
+    };
+    let resp = test::call_service(&app, refund(Some(150), "reward-refund-1")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let rewards_req = test::TestRequest::get()
+        .uri("/api/rewards")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let rewards: serde_json::Value = test::call_and_read_body_json(&app, rewards_req).await;
+    assert_eq!(rewards.as_array().unwrap().len(), 1);
+    assert_eq!(rewards[0]["amount"], 30.0);
+    assert_eq!(rewards[0]["reversed_amount"], 15.0);
+    assert_eq!(rewards[0]["status"], "credited");
+
+    let resp = test::call_service(&app, refund(None, "reward-refund-2")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let status: String = sqlx::query_scalar("SELECT status::TEXT FROM rewards")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(status, "reversed");
+
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(400));
+    assert_eq!(ledger::balance(&db, LedgerAccount::rewards_expense()).await.unwrap(), Money::ZERO);
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+
+    let transaction_id = Uuid::parse_str(&transaction_ids[0]).unwrap();
+    let entries = ledger::entries_for_transaction(&db, transaction_id).await.unwrap();
+    assert_eq!(entries.iter().filter(|e| e.account_type == AccountType::RewardsExpense).count(), 3);
+
+    let limits_req = test::TestRequest::get()
+        .uri("/api/limits")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let limits: serde_json::Value = test::call_and_read_body_json(&app, limits_req).await;
+    let daily = limits["limits"]["daily"].as_f64().unwrap();
+    assert_eq!(limits["remaining"]["daily"].as_f64().unwrap(), daily - 900.0);
+}
+
+#[actix_web::test]
+async fn promo_codes_discount_and_cannot_be_overused() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let other_merchant_id = seed_merchant(&db, "bakery@upi", "upi://pay?pa=bakery@upi").await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let mut tokens = Vec::new();
+    for (phone, upi_id) in [("9876543210", "a@paytm"), ("9123456780", "b@paytm"), ("9988776655", "c@paytm")] {
+        tokens.push(register_user(&app, phone, upi_id).await);
+        fund_user(&db, phone, Money::from_rupees(2000)).await;
+    }
+    let admin_token = register_user(&app, "9000000001", "admin@paytm").await;
+    grant_role(&db, "9000000001", "admin").await;
+
+    let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::days(7);
+    let create_free = |token: &str| {
+        test::TestRequest::post()
+            .uri("/api/promo-codes")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "code": "FREE", "percentage_bps": 10000, "expires_at": expires_at }))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, create_free(&tokens[0])).await.status(), actix_web::http::StatusCode::FORBIDDEN);
+    assert_eq!(test::call_service(&app, create_free(&admin_token)).await.status(), actix_web::http::StatusCode::CREATED);
+    for (code, restricted_to) in [("save10", None), ("BAKERY", Some(other_merchant_id))] {
+        let create_req = test::TestRequest::post()
+            .uri("/api/promo-codes")
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .set_json(json!({
+                "code": code,
+                "percentage_bps": 1000,
+                "max_discount": 50,
+                "merchant_id": restricted_to,
+                "usage_limit": 2,
+                "per_user_limit": 1,
+                "expires_at": expires_at,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, create_req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    }
+
+    let initiate = |token: &str, amount: i64, promo_code: &str, key: &str| {
+        test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": merchant_qr, "amount": amount, "promo_code": promo_code, "idempotency_key": key }))
+            .to_request()
+    };
+    let execute = |token: &str, session_id: &serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/payment/execute")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "session_id": session_id, "pin": "1234" }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, initiate(&tokens[0], 300, "BAKERY", "promo-0")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let free: serde_json::Value = test::call_and_read_body_json(&app, initiate(&tokens[1], 100, "FREE", "promo-free")).await;
+    assert_eq!(free["discount"], 99.99);
+    assert_eq!(free["payable_amount"], 0.01);
+
+    let a1: serde_json::Value = test::call_and_read_body_json(&app, initiate(&tokens[0], 300, "Save10", "promo-a1")).await;
+    assert_eq!(a1["promo_code"], "SAVE10");
+    assert_eq!(a1["discount"], 30.0);
+    assert_eq!(a1["payable_amount"], 270.0);
+    let a2: serde_json::Value = test::call_and_read_body_json(&app, initiate(&tokens[0], 100, "SAVE10", "promo-a2")).await;
+    let b: serde_json::Value = test::call_and_read_body_json(&app, initiate(&tokens[1], 1000, "SAVE10", "promo-b")).await;
+    assert_eq!(b["discount"], 50.0);
+    let c: serde_json::Value = test::call_and_read_body_json(&app, initiate(&tokens[2], 200, "SAVE10", "promo-c")).await;
+
+    let resp = test::call_service(&app, execute(&tokens[0], &a1["session_id"])).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let resp = test::call_service(&app, execute(&tokens[0], &a2["session_id"])).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    // Only one of the two remaining sessions can take the last use of the code.
+    let (b_resp, c_resp) = tokio::join!(
+        test::call_service(&app, execute(&tokens[1], &b["session_id"])),
+        test::call_service(&app, execute(&tokens[2], &c["session_id"])),
+    );
+    let mut statuses = vec![b_resp.status().as_u16(), c_resp.status().as_u16()];
+    statuses.sort();
+    assert_eq!(statuses, vec![200, 409]);
+    let (winner_amount, winner_discount) = if b_resp.status().is_success() { (1000, 50) } else { (200, 20) };
+
+    let resp = test::call_service(&app, initiate(&tokens[2], 200, "SAVE10", "promo-c2")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let redemptions: i32 = sqlx::query_scalar("SELECT redemption_count FROM promo_codes WHERE code = 'SAVE10'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(redemptions, 2);
+
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(1730));
+
+    let transaction_id: Uuid = sqlx::query_scalar("SELECT id FROM transactions WHERE idempotency_key = 'promo-a1'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    let refund_req = test::TestRequest::post()
+        .uri(&format!("/api/payment/{}/refund", transaction_id))
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .set_json(json!({ "refund_key": "promo-refund" }))
+        .to_request();
+    let resp = test::call_service(&app, refund_req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(2000));
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(winner_amount)
+    );
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::rewards_expense()).await.unwrap(),
+        Money::from_rupees(winner_discount)
+    );
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn split_bill_completes_when_all_shares_are_paid_and_reverses_on_expiry() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let session_ttl_seconds = cfg.payment_session_ttl_seconds;
+    let upi = UpiClient::new(cfg.psp.clone()).unwrap();
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let mut tokens = Vec::new();
+    for (phone, upi_id) in [
+        ("9876543210", "a@paytm"),
+        ("9123456780", "b@paytm"),
+        ("9988776655", "c@paytm"),
+        ("9811122233", "pending.d@paytm"),
+    ] {
+        tokens.push(register_user(&app, phone, upi_id).await);
+        fund_user(&db, phone, Money::from_rupees(1000)).await;
+    }
+
+    let create = |amount: i64, participants: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/splits")
+            .insert_header(("Authorization", format!("Bearer {}", tokens[0])))
+            .set_json(json!({ "qr_data": merchant_qr, "amount": amount, "participants": participants }))
+            .to_request()
+    };
+    let pay = |token: &str, split_id: &serde_json::Value, pin: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/splits/{}/pay", split_id.as_str().unwrap()))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "pin": pin }))
+            .to_request()
+    };
+    let balance_of = |phone: &'static str| {
+        let db = db.clone();
+        async move {
+            sqlx::query_scalar::<_, Money>("SELECT balance FROM users WHERE phone_number = $1")
+                .bind(phone)
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        }
+    };
+
+    let resp = test::call_service(&app, create(900, json!([{ "payer": "b@paytm", "amount": 700 }, { "payer": "9988776655", "amount": 300 }]))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let split: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        create(900, json!([{ "payer": "B@Paytm", "amount": 300 }, { "payer": "9988776655", "amount": 300 }])),
+    )
+    .await;
+    assert_eq!(split["status"], "open");
+    assert_eq!(split["shares"].as_array().unwrap().len(), 3);
+
+    let resp = test::call_service(&app, pay(&tokens[1], &split["split_id"], "0000")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    let after_b: serde_json::Value = test::call_and_read_body_json(&app, pay(&tokens[1], &split["split_id"], "1234")).await;
+    assert_eq!(after_b["status"], "open");
+    assert_eq!(after_b["paid_amount"], 300.0);
+    assert_eq!(ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(), Money::ZERO);
+    let resp = test::call_service(&app, pay(&tokens[1], &split["split_id"], "1234")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let b_share = after_b["shares"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["upi_id"] == "b@paytm")
+        .unwrap()["transaction_id"]
+        .clone();
+    let b_share_status: TransactionStatus = sqlx::query_scalar("SELECT status FROM transactions WHERE id = $1")
+        .bind(Uuid::parse_str(b_share.as_str().unwrap()).unwrap())
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(b_share_status, TransactionStatus::Escrowed);
+
+    // Unpaid shares of an open split outlive the payment session TTL.
+    sqlx::query("UPDATE transactions SET created_at = created_at - make_interval(secs => $1) WHERE split_id = $2")
+        .bind(session_ttl_seconds as f64 + 1.0)
+        .bind(Uuid::parse_str(split["split_id"].as_str().unwrap()).unwrap())
+        .execute(&db)
+        .await
+        .unwrap();
+    assert_eq!(payment::expire_stale_sessions(&db, session_ttl_seconds).await.unwrap(), 0);
+
+    let exec_req = test::TestRequest::post()
+        .uri("/api/payment/execute")
+        .insert_header(("Authorization", format!("Bearer {}", tokens[1])))
+        .set_json(json!({ "session_id": b_share, "pin": "1234" }))
+        .to_request();
+    let resp = test::call_service(&app, exec_req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    test::call_and_read_body_json::<_, _, serde_json::Value>(&app, pay(&tokens[0], &split["split_id"], "1234")).await;
+    let done: serde_json::Value = test::call_and_read_body_json(&app, pay(&tokens[2], &split["split_id"], "1234")).await;
+    assert_eq!(done["status"], "completed");
+    assert!(done["shares"].as_array().unwrap().iter().all(|s| s["status"] == "success"));
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(900)
+    );
+
+    let partial: serde_json::Value =
+        test::call_and_read_body_json(&app, create(200, json!([{ "payer": "b@paytm", "amount": 100 }]))).await;
+    test::call_and_read_body_json::<_, _, serde_json::Value>(&app, pay(&tokens[1], &partial["split_id"], "1234")).await;
+    assert_eq!(balance_of("9123456780").await, Money::from_rupees(600));
+
+    // The escrowed share is reversed with the PSP, and only a completed reversal returns it to the payer.
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(
+            wiremock::ResponseTemplate::new(200).set_body_json(json!({ "reversal_id": "REVPENDING", "status": "pending" })),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(psp_server().await)
+        .await;
+    let later = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    assert_eq!(split::expire_due(&db, &upi, later).await.unwrap(), 1);
+    assert_eq!(balance_of("9123456780").await, Money::from_rupees(600));
+    assert_eq!(split::expire_due(&db, &upi, later).await.unwrap(), 0);
+    assert_eq!(balance_of("9123456780").await, Money::from_rupees(700));
+    let resp = test::call_service(&app, pay(&tokens[0], &partial["split_id"], "1234")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let get_req = test::TestRequest::get()
+        .uri(&format!("/api/splits/{}", partial["split_id"].as_str().unwrap()))
+        .insert_header(("Authorization", format!("Bearer {}", tokens[1])))
+        .to_request();
+    let partial: serde_json::Value = test::call_and_read_body_json(&app, get_req).await;
+    assert_eq!(partial["status"], "expired");
+    assert!(partial["shares"].as_array().unwrap().iter().all(|s| s["status"] == "failed"));
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(900)
+    );
+
+    // A share the PSP leaves pending holds the split open until the PSP confirms it.
+    let awaiting: serde_json::Value =
+        test::call_and_read_body_json(&app, create(200, json!([{ "payer": "pending.d@paytm", "amount": 100 }]))).await;
+    test::call_and_read_body_json::<_, _, serde_json::Value>(&app, pay(&tokens[0], &awaiting["split_id"], "1234")).await;
+    let awaiting: serde_json::Value =
+        test::call_and_read_body_json(&app, pay(&tokens[3], &awaiting["split_id"], "1234")).await;
+    assert_eq!(awaiting["status"], "open");
+    assert_eq!(awaiting["paid_amount"], 200.0);
+    assert_eq!(balance_of("9811122233").await, Money::from_rupees(900));
+    let d_share = awaiting["shares"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["upi_id"] == "pending.d@paytm")
+        .unwrap();
+    assert_eq!(d_share["status"], "pending");
+
+    let mut tx = db.begin().await.unwrap();
+    let settled = payment::settle_pending(
+        &mut tx,
+        &PspPayment {
+            reference: Uuid::parse_str(d_share["transaction_id"].as_str().unwrap()).unwrap(),
+            upi_txn_id: Some("PSPSHARE".to_string()),
+            status: PspStatus::Success,
+            reason: None,
+        },
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(settled, TransactionStatus::Success);
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(1100)
+    );
+
+    // A payer who cannot cover their share is refused before anything is sent to the PSP.
+    let unaffordable: serde_json::Value =
+        test::call_and_read_body_json(&app, create(1000, json!([{ "payer": "pending.d@paytm", "amount": 1000 }]))).await;
+    let requests_before = psp_server().await.received_requests().await.unwrap().len();
+    let resp = test::call_service(&app, pay(&tokens[3], &unaffordable["split_id"], "1234")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    assert_eq!(psp_server().await.received_requests().await.unwrap().len(), requests_before);
+    let get_req = test::TestRequest::get()
+        .uri(&format!("/api/splits/{}", unaffordable["split_id"].as_str().unwrap()))
+        .insert_header(("Authorization", format!("Bearer {}", tokens[3])))
+        .to_request();
+    let unaffordable: serde_json::Value = test::call_and_read_body_json(&app, get_req).await;
+    assert!(unaffordable["shares"].as_array().unwrap().iter().all(|s| s["status"] == "initiated"));
+    assert_eq!(balance_of("9811122233").await, Money::from_rupees(900));
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn collect_requests_are_approved_with_pin_declined_or_expired() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop").await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "alice@paytm").await;
+    let other_token = register_user(&app, "9123456780", "bob@paytm").await;
+    let owner_token = register_user(&app, "9111111111", "owner@paytm").await;
+    own_merchant(&db, merchant_id, "9111111111").await;
+    fund_user(&db, "9876543210", Money::from_rupees(500)).await;
+
+    let create_as = |token: &str, amount: i64, expires_in_secs: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/api/merchant/{}/collect-requests", merchant_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "payer": "Alice@Paytm", "amount": amount, "note": "invoice", "expires_in_secs": expires_in_secs }))
+            .to_request()
+    };
+    let create = |amount: i64, expires_in_secs: i64| create_as(&owner_token, amount, expires_in_secs);
+    let act = |token: &str, request_id: &serde_json::Value, action: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/collect-requests/{}/{}", request_id.as_str().unwrap(), action))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "pin": "1234" }))
+            .to_request()
+    };
+    let pending = |token: &str| {
+        test::TestRequest::get()
+            .uri("/api/collect-requests")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, create_as(&token, 300, 600)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let first: serde_json::Value = test::call_and_read_body_json(&app, create(300, 600)).await;
+    assert_eq!(first["status"], "pending");
+    assert_eq!(first["payer_upi_id"], "alice@paytm");
+    let second: serde_json::Value = test::call_and_read_body_json(&app, create(100, 600)).await;
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, pending(&token)).await;
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, pending(&other_token)).await;
+    assert!(listed.as_array().unwrap().is_empty());
+
+    let resp = test::call_service(&app, act(&other_token, &first["request_id"], "approve")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    let wrong_pin = test::TestRequest::post()
+        .uri(&format!("/api/collect-requests/{}/approve", first["request_id"].as_str().unwrap()))
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "pin": "0000" }))
+        .to_request();
+    let resp = test::call_service(&app, wrong_pin).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+
+    let paid: serde_json::Value = test::call_and_read_body_json(&app, act(&token, &first["request_id"], "approve")).await;
+    assert_eq!(paid["status"], "success");
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(300)
+    );
+    let resp = test::call_service(&app, act(&token, &first["request_id"], "approve")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let get_req = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/merchant/{}/collect-requests/{}", merchant_id, first["request_id"].as_str().unwrap()))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+    let resp = test::call_service(&app, get_req(&other_token)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let first: serde_json::Value = test::call_and_read_body_json(&app, get_req(&owner_token)).await;
+    assert_eq!(first["status"], "approved");
+    assert_eq!(first["transaction_id"], paid["transaction_id"]);
+
+    let declined: serde_json::Value = test::call_and_read_body_json(&app, act(&token, &second["request_id"], "decline")).await;
+    assert_eq!(declined["status"], "declined");
+    let resp = test::call_service(&app, act(&token, &second["request_id"], "approve")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    // A debit the PSP declines is kept as a failed payment and the request stays open to approve again.
+    let retried: serde_json::Value = test::call_and_read_body_json(&app, create(50, 600)).await;
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(
+            wiremock::ResponseTemplate::new(422)
+                .set_body_json(json!({ "code": "U30", "message": "debit declined by remitter bank" })),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(psp_server().await)
+        .await;
+    let resp = test::call_service(&app, act(&token, &retried["request_id"], "approve")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::PAYMENT_REQUIRED);
+    let failed: Vec<(TransactionStatus, Option<String>)> = sqlx::query_as(
+        "SELECT status, error_message FROM transactions WHERE idempotency_key LIKE 'collect:' || $1 || ':%'",
+    )
+    .bind(retried["request_id"].as_str().unwrap())
+    .fetch_all(&db)
+    .await
+    .unwrap();
+    assert_eq!(failed, vec![(TransactionStatus::Failed, Some("debit declined by remitter bank".to_string()))]);
+    let paid: serde_json::Value =
+        test::call_and_read_body_json(&app, act(&token, &retried["request_id"], "approve")).await;
+    assert_eq!(paid["status"], "success");
+
+    // A debit that times out still approves the request, against a payment held pending for reconciliation.
+    let timed_out: serde_json::Value = test::call_and_read_body_json(&app, create(100, 600)).await;
+    wiremock::Mock::given(wiremock::matchers::any())
+        .respond_with(wiremock::ResponseTemplate::new(504))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(psp_server().await)
+        .await;
+    let held: serde_json::Value =
+        test::call_and_read_body_json(&app, act(&token, &timed_out["request_id"], "approve")).await;
+    assert_eq!(held["status"], "pending");
+    let held_id = Uuid::parse_str(held["transaction_id"].as_str().unwrap()).unwrap();
+    assert_eq!(ledger::balance(&db, LedgerAccount::payment_hold(held_id)).await.unwrap(), Money::from_rupees(100));
+
+    let resp = test::call_service(&app, create(100, 0)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let stale: serde_json::Value = test::call_and_read_body_json(&app, create(100, 600)).await;
+    let expired = collect::expire_due(&db, chrono::Utc::now().naive_utc() + chrono::Duration::hours(1)).await.unwrap();
+    assert_eq!(expired, 1);
+    let resp = test::call_service(&app, act(&token, &stale["request_id"], "approve")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, pending(&token)).await;
+    assert!(listed.as_array().unwrap().is_empty());
+
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(50));
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn mandates_debit_once_per_cycle_after_pre_debit_notice() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let limits = cfg.spending_limits;
+    let upi = UpiClient::new(cfg.psp.clone()).unwrap();
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop").await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "alice@paytm").await;
+    let owner_token = register_user(&app, "9111111111", "owner@paytm").await;
+    own_merchant(&db, merchant_id, "9111111111").await;
+    fund_user(&db, "9876543210", Money::from_rupees(500)).await;
+    let today = chrono::Utc::now().date_naive();
+    let now = chrono::Utc::now().naive_utc();
+
+    let create_as = |token: &str, amount: i64, frequency: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/merchant/{}/mandates", merchant_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({
+                "payer": "alice@paytm",
+                "description": "subscription",
+                "amount": amount,
+                "max_amount": 400,
+                "frequency": frequency,
+                "start_date": today.to_string(),
+            }))
+            .to_request()
+    };
+    let create = |amount: i64, frequency: &str| create_as(&owner_token, amount, frequency);
+    let act = |mandate: &serde_json::Value, action: &str, pin: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/mandates/{}/{}", mandate["id"].as_str().unwrap(), action))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "pin": pin }))
+            .to_request()
+    };
+    let history = |mandate: &serde_json::Value| {
+        test::TestRequest::get()
+            .uri(&format!("/api/mandates/{}/history", mandate["id"].as_str().unwrap()))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, create_as(&token, 199, "monthly")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, create(500, "monthly")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let monthly: serde_json::Value = test::call_and_read_body_json(&app, create(199, "monthly")).await;
+    assert_eq!(monthly["status"], "pending");
+    assert_eq!(mandate::run_due(&db, &upi, &limits, now).await.unwrap(), MandateRun::default());
+
+    let resp = test::call_service(&app, act(&monthly, "approve", "0000")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    let monthly: serde_json::Value = test::call_and_read_body_json(&app, act(&monthly, "approve", "1234")).await;
+    assert_eq!(monthly["status"], "active");
+
+    let run = mandate::run_due(&db, &upi, &limits, now).await.unwrap();
+    assert_eq!(run, MandateRun { notified: 1, debited: 0, failed: 0 });
+    assert_eq!(mandate::run_due(&db, &upi, &limits, now).await.unwrap(), MandateRun::default());
+    let entries: serde_json::Value = test::call_and_read_body_json(&app, history(&monthly)).await;
+    assert_eq!(entries[0]["status"], "notified");
+    assert_eq!(entries[0]["amount"], 199.0);
+
+    let update_req = |token: &str, amount: i64| {
+        test::TestRequest::put()
+            .uri(&format!("/api/merchant/{}/mandates/{}", merchant_id, monthly["id"].as_str().unwrap()))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "amount": amount }))
+            .to_request()
+    };
+    let resp = test::call_service(&app, update_req(&token, 299)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, update_req(&owner_token, 401)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let updated: serde_json::Value = test::call_and_read_body_json(&app, update_req(&owner_token, 299)).await;
+    assert_eq!(updated["amount"], 299.0);
+
+    let later = now + chrono::Duration::hours(25);
+    let run = mandate::run_due(&db, &upi, &limits, later).await.unwrap();
+    assert_eq!(run, MandateRun { notified: 0, debited: 1, failed: 0 });
+    assert_eq!(mandate::run_due(&db, &upi, &limits, later).await.unwrap(), MandateRun::default());
+    let entries: serde_json::Value = test::call_and_read_body_json(&app, history(&monthly)).await;
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+    assert_eq!(entries[0]["status"], "success");
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(199)
+    );
+
+    let daily: serde_json::Value = test::call_and_read_body_json(&app, create(400, "daily")).await;
+    test::call_and_read_body_json::<_, _, serde_json::Value>(&app, act(&daily, "approve", "1234")).await;
+    let run = mandate::run_due(&db, &upi, &limits, later).await.unwrap();
+    assert_eq!(run, MandateRun { notified: 1, debited: 0, failed: 0 });
+    let run = mandate::run_due(&db, &upi, &limits, later + chrono::Duration::hours(25)).await.unwrap();
+    assert_eq!(run, MandateRun { notified: 1, debited: 0, failed: 1 });
+
+    let revoked: serde_json::Value = test::call_and_read_body_json(&app, act(&daily, "revoke", "")).await;
+    assert_eq!(revoked["status"], "revoked");
+    let entries: serde_json::Value = test::call_and_read_body_json(&app, history(&daily)).await;
+    assert_eq!(entries[0]["status"], "cancelled");
+    assert_eq!(entries[1]["status"], "failed");
+    assert_eq!(entries[1]["error_message"], "insufficient balance");
+    let failed_status: TransactionStatus = sqlx::query_scalar("SELECT status FROM transactions WHERE id = $1")
+        .bind(Uuid::parse_str(entries[1]["transaction_id"].as_str().unwrap()).unwrap())
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(failed_status, TransactionStatus::Failed);
+
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(301));
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn abandoned_payment_sessions_expire() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let session_ttl_seconds = cfg.payment_session_ttl_seconds;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "alice@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+
+    let initiate = |idempotency_key: &str| {
+        test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": merchant_qr, "idempotency_key": idempotency_key }))
+            .to_request()
+    };
+    let stale: serde_json::Value = test::call_and_read_body_json(&app, initiate("stale-session")).await;
+    let abandoned: serde_json::Value = test::call_and_read_body_json(&app, initiate("abandoned-session")).await;
+    sqlx::query("UPDATE transactions SET created_at = created_at - make_interval(secs => $1) WHERE id = $2")
+        .bind(session_ttl_seconds as f64 + 1.0)
+        .bind(Uuid::parse_str(stale["session_id"].as_str().unwrap()).unwrap())
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let exec_req = test::TestRequest::post()
+        .uri("/api/payment/execute")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "session_id": stale["session_id"], "pin": "1234" }))
+        .to_request();
+    let resp = test::call_service(&app, exec_req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::GONE);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "payment_session_expired");
+
+    let (status, error_message): (TransactionStatus, Option<String>) =
+        sqlx::query_as("SELECT status, error_message FROM transactions WHERE idempotency_key = 'stale-session'")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(status, TransactionStatus::Failed);
+    assert_eq!(error_message.as_deref(), Some("payment session expired"));
+
+    assert_eq!(payment::expire_stale_sessions(&db, session_ttl_seconds).await.unwrap(), 0);
+    sqlx::query("UPDATE transactions SET created_at = created_at - make_interval(secs => $1) WHERE id = $2")
+        .bind(session_ttl_seconds as f64 + 1.0)
+        .bind(Uuid::parse_str(abandoned["session_id"].as_str().unwrap()).unwrap())
+        .execute(&db)
+        .await
+        .unwrap();
+    assert_eq!(payment::expire_stale_sessions(&db, session_ttl_seconds).await.unwrap(), 1);
+
+    let exec_req = test::TestRequest::post()
+        .uri("/api/payment/execute")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({ "session_id": abandoned["session_id"], "pin": "1234" }))
+        .to_request();
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
+    assert_eq!(resp["status"], "failed");
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(1000));
+}
+
+#[actix_web::test]
+async fn payments_are_switched_through_the_psp() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let psp_secret = cfg.psp.secret.clone();
+    let upi = UpiClient::new(cfg.psp.clone()).unwrap();
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let alice = register_user(&app, "9876543210", "alice@paytm").await;
+    let bob = register_user(&app, "9876543211", "decline.bob@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+    fund_user(&db, "9876543211", Money::from_rupees(1000)).await;
+
+    let pay = |token: &str, idempotency_key: &str| {
+        let token = token.to_string();
+        let idempotency_key = idempotency_key.to_string();
+        let app = &app;
+        async move {
+            let init_req = test::TestRequest::post()
+                .uri("/api/payment/initiate")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({ "qr_data": merchant_qr, "idempotency_key": idempotency_key }))
+                .to_request();
+            let init: serde_json::Value = test::call_and_read_body_json(app, init_req).await;
+            let exec_req = test::TestRequest::post()
+                .uri("/api/payment/execute")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+                .to_request();
+            test::call_service(app, exec_req).await
+        }
+    };
+    let balance = |phone_number: &'static str| {
+        let db = db.clone();
+        async move {
+            sqlx::query_scalar::<_, Money>("SELECT balance FROM users WHERE phone_number = $1")
+                .bind(phone_number)
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        }
+    };
+
+    let resp = pay(&alice, "psp-success").await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], "success");
+    let transaction_id = body["transaction_id"].as_str().unwrap().replace('-', "").to_uppercase();
+    assert_eq!(body["upi_txn_id"].as_str(), Some(format!("PSP{}", transaction_id).as_str()));
+    assert_eq!(balance("9876543210").await, Money::from_rupees(900));
+
+    let resp = pay(&bob, "psp-declined").await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::PAYMENT_REQUIRED);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "payment_declined");
+    let (status, error_message): (TransactionStatus, Option<String>) =
+        sqlx::query_as("SELECT status, error_message FROM transactions WHERE idempotency_key = 'psp-declined'")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(status, TransactionStatus::Failed);
+    assert_eq!(error_message.as_deref(), Some("declined by remitter bank"));
+    assert_eq!(balance("9876543211").await, Money::from_rupees(1000));
+
+    // The switch may have acted on a request that timed out, so the payment is held as pending rather than
+    // left retryable under the same reference. Once the switch is back and has no record of it, it is released.
+    let psp = psp_server().await;
+    psp.reset().await;
+    wiremock::Mock::given(wiremock::matchers::any())
+        .respond_with(wiremock::ResponseTemplate::new(504))
+        .mount(psp)
+        .await;
+    let resp = pay(&alice, "psp-outage").await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], "pending");
+    let outage_id = Uuid::parse_str(body["transaction_id"].as_str().unwrap()).unwrap();
+    assert_eq!(balance("9876543210").await, Money::from_rupees(800));
+    assert_eq!(ledger::balance(&db, LedgerAccount::payment_hold(outage_id)).await.unwrap(), Money::from_rupees(100));
+    let resp = pay(&alice, "psp-outage").await;
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], "pending");
+
+    psp.reset().await;
+    mock_psp::mount(psp, &psp_secret).await;
+    let config = ReconciliationConfig {
+        poll_after_secs: 60,
+        backoff_secs: 30,
+        max_backoff_secs: 300,
+        max_age_secs: 3600,
+    };
+    sqlx::query("UPDATE transactions SET updated_at = updated_at - INTERVAL '61 seconds' WHERE id = $1")
+        .bind(outage_id)
+        .execute(&db)
+        .await
+        .unwrap();
+    let later = chrono::Utc::now().naive_utc() + chrono::Duration::seconds(61);
+    assert_eq!(
+        reconciliation::run_due(&db, &upi, &config, later).await.unwrap(),
+        ReconciliationRun { polled: 1, failed: 1, ..Default::default() }
+    );
+    let (status, error_message): (TransactionStatus, Option<String>) =
+        sqlx::query_as("SELECT status, error_message FROM transactions WHERE id = $1")
+            .bind(outage_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(status, TransactionStatus::Failed);
+    assert_eq!(error_message.as_deref(), Some("payment never reached the payment network"));
+    assert_eq!(balance("9876543210").await, Money::from_rupees(900));
+
+    let resp = pay(&alice, "psp-retry").await;
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], "success");
+    assert_eq!(balance("9876543210").await, Money::from_rupees(800));
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn payments_fail_fast_while_the_psp_circuit_is_open() {
+    let (_guard, mut cfg, db, redis) = setup().await;
+    cfg.psp.breaker.consecutive_failures = 1;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "alice@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+
+    let psp = psp_server().await;
+    psp.reset().await;
+    wiremock::Mock::given(wiremock::matchers::any())
+        .respond_with(wiremock::ResponseTemplate::new(503))
+        .mount(psp)
+        .await;
+
+    let mut codes = Vec::new();
+    for idempotency_key in ["outage-1", "outage-2"] {
+        let init_req = test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": merchant_qr, "idempotency_key": idempotency_key }))
+            .to_request();
+        let init: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
+        let exec_req = test::TestRequest::post()
+            .uri("/api/payment/execute")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+            .to_request();
+        let resp = test::call_service(&app, exec_req).await;
+        let status = resp.status();
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        codes.push((status, body["code"].as_str().or(body["status"].as_str()).unwrap().to_string()));
+    }
+    // The failed call is held as pending in case it reached the switch; the next one is never sent.
+    assert_eq!(
+        codes,
+        vec![
+            (actix_web::http::StatusCode::OK, "pending".to_string()),
+            (actix_web::http::StatusCode::SERVICE_UNAVAILABLE, "payment_network_unavailable".to_string()),
+        ]
+    );
+    assert_eq!(psp.received_requests().await.unwrap().len(), 1);
+
+    let metrics_req = test::TestRequest::get().uri("/metrics/circuit-breakers").to_request();
+    let metrics: serde_json::Value = test::call_and_read_body_json(&app, metrics_req).await;
+    assert_eq!(metrics[0]["name"], "psp.payments");
+    assert_eq!(metrics[0]["state"], "open");
+    assert_eq!(metrics[0]["times_opened"], 1);
+
+    let balance: Money = sqlx::query_scalar("SELECT balance FROM users WHERE phone_number = '9876543210'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(balance, Money::from_rupees(900));
+}
+
+#[actix_web::test]
+async fn pending_payments_settle_on_signed_psp_callbacks() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let psp_secret = cfg.psp.secret.clone();
+    let session_ttl_seconds = cfg.payment_session_ttl_seconds;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "pending.carol@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+
+    let execute = |idempotency_key: &'static str| {
+        let token = token.clone();
+        let app = &app;
+        async move {
+            let init_req = test::TestRequest::post()
+                .uri("/api/payment/initiate")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({ "qr_data": merchant_qr, "idempotency_key": idempotency_key }))
+                .to_request();
+            let init: serde_json::Value = test::call_and_read_body_json(app, init_req).await;
+            let exec_req = test::TestRequest::post()
+                .uri("/api/payment/execute")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+                .to_request();
+            test::call_and_read_body_json::<_, _, serde_json::Value>(app, exec_req).await
+        }
+    };
+    let callback = |timestamp: i64, secret: &str, body: serde_json::Value| {
+        let body = body.to_string();
+        let signature = upi_client::sign(secret, timestamp, "POST", "/psp/callbacks", &body);
+        test::TestRequest::post()
+            .uri("/psp/callbacks")
+            .insert_header((upi_client::TIMESTAMP_HEADER, timestamp.to_string()))
+            .insert_header((upi_client::SIGNATURE_HEADER, signature))
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(body)
+            .to_request()
+    };
+    let balance = || {
+        let db = db.clone();
+        async move {
+            sqlx::query_scalar::<_, Money>("SELECT balance FROM users WHERE phone_number = '9876543210'")
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        }
+    };
+
+    let paid = execute("pending-success").await;
+    assert_eq!(paid["status"], "pending");
+    let paid_id = Uuid::parse_str(paid["transaction_id"].as_str().unwrap()).unwrap();
+    assert_eq!(balance().await, Money::from_rupees(900));
+    assert_eq!(ledger::balance(&db, LedgerAccount::payment_hold(paid_id)).await.unwrap(), Money::from_rupees(100));
+    assert_eq!(ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(), Money::ZERO);
+
+    // Executing again reports the pending state, and the session sweeper leaves held payments alone.
+    let again = execute("pending-success").await;
+    assert_eq!(again["status"], "pending");
+    sqlx::query("UPDATE transactions SET created_at = created_at - make_interval(secs => $1) WHERE id = $2")
+        .bind(session_ttl_seconds as f64 + 1.0)
+        .bind(paid_id)
+        .execute(&db)
+        .await
+        .unwrap();
+    assert_eq!(payment::expire_stale_sessions(&db, session_ttl_seconds).await.unwrap(), 0);
+
+    let now = chrono::Utc::now().timestamp();
+    let success = json!({
+        "event_id": "evt-1",
+        "reference": paid_id,
+        "upi_txn_id": "NPCI0001",
+        "status": "success",
+        "reason": null
+    });
+    let resp = test::call_service(&app, callback(now, "wrong-secret", success.clone())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    let resp = test::call_service(&app, callback(now - 600, &psp_secret, success.clone())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    let status: TransactionStatus = sqlx::query_scalar("SELECT status FROM transactions WHERE id = $1")
+        .bind(paid_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(status, TransactionStatus::Pending);
+
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, callback(now, &psp_secret, success.clone())).await;
+    assert_eq!(resp["status"], "success");
+    assert_eq!(resp["duplicate"], false);
+    let replayed: serde_json::Value = test::call_and_read_body_json(&app, callback(now, &psp_secret, success)).await;
+    assert_eq!(replayed["status"], "success");
+    assert_eq!(replayed["duplicate"], true);
+
+    let (status, upi_txn_id): (TransactionStatus, Option<String>) =
+        sqlx::query_as("SELECT status, upi_txn_id FROM transactions WHERE id = $1")
+            .bind(paid_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(status, TransactionStatus::Success);
+    assert_eq!(upi_txn_id.as_deref(), Some("NPCI0001"));
+    assert_eq!(ledger::balance(&db, LedgerAccount::payment_hold(paid_id)).await.unwrap(), Money::ZERO);
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(100)
+    );
+    assert_eq!(balance().await, Money::from_rupees(900));
+
+    let declined = execute("pending-failure").await;
+    assert_eq!(declined["status"], "pending");
+    let declined_id = Uuid::parse_str(declined["transaction_id"].as_str().unwrap()).unwrap();
+    assert_eq!(balance().await, Money::from_rupees(800));
+
+    let failure = json!({
+        "event_id": "evt-2",
+        "reference": declined_id,
+        "upi_txn_id": null,
+        "status": "failed",
+        "reason": "beneficiary bank declined"
+    });
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, callback(now, &psp_secret, failure)).await;
+    assert_eq!(resp["status"], "failed");
+    let (status, error_message): (TransactionStatus, Option<String>) =
+        sqlx::query_as("SELECT status, error_message FROM transactions WHERE id = $1")
+            .bind(declined_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(status, TransactionStatus::Failed);
+    assert_eq!(error_message.as_deref(), Some("beneficiary bank declined"));
+    assert_eq!(balance().await, Money::from_rupees(900));
+    assert_eq!(ledger::balance(&db, LedgerAccount::payment_hold(declined_id)).await.unwrap(), Money::ZERO);
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(100)
+    );
+
+    // A late callback with a new event id cannot move a payment that has already settled.
+    let late = json!({
+        "event_id": "evt-3",
+        "reference": paid_id,
+        "upi_txn_id": null,
+        "status": "failed",
+        "reason": "late"
+    });
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, callback(now, &psp_secret, late)).await;
+    assert_eq!(resp["status"], "success");
+    assert_eq!(balance().await, Money::from_rupees(900));
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn reconciliation_polls_stuck_pending_payments_with_backoff() {
+    let (_guard, cfg, db, redis) = setup().await;
+    let upi = UpiClient::new(cfg.psp.clone()).unwrap();
+    let psp = psp_server().await;
+    psp.reset().await;
+    let switch = mock_psp::mount(psp, &cfg.psp.secret).await;
+    let merchant_qr = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100";
+    let merchant_id = seed_merchant(&db, "coffeeshop@upi", merchant_qr).await;
+    let app = init_app(cfg, db.clone(), redis).await;
+    let token = register_user(&app, "9876543210", "pending.carol@paytm").await;
+    fund_user(&db, "9876543210", Money::from_rupees(1000)).await;
+
+    let mut ids = Vec::new();
+    for idempotency_key in ["stuck-success", "stuck-failure", "stuck-timeout"] {
+        let init_req = test::TestRequest::post()
+            .uri("/api/payment/initiate")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "qr_data": merchant_qr, "idempotency_key": idempotency_key }))
+            .to_request();
+        let init: serde_json::Value = test::call_and_read_body_json(&app, init_req).await;
+        let exec_req = test::TestRequest::post()
+            .uri("/api/payment/execute")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "session_id": init["session_id"], "pin": "1234" }))
+            .to_request();
+        let exec: serde_json::Value = test::call_and_read_body_json(&app, exec_req).await;
+        assert_eq!(exec["status"], "pending");
+        ids.push(Uuid::parse_str(exec["transaction_id"].as_str().unwrap()).unwrap());
+    }
+    let (settles, fails, times_out) = (ids[0], ids[1], ids[2]);
+
+    let config = ReconciliationConfig {
+        poll_after_secs: 60,
+        backoff_secs: 30,
+        max_backoff_secs: 300,
+        max_age_secs: 3600,
+    };
+    let start = chrono::Utc::now().naive_utc();
+    let at = |secs: i64| start + chrono::Duration::seconds(secs);
+    let status_of = |id: Uuid| {
+        let db = db.clone();
+        async move {
+            sqlx::query_as::<_, (TransactionStatus, Option<String>)>(
+                "SELECT status, error_message FROM transactions WHERE id = $1",
+            )
+            .bind(id)
+            .fetch_one(&db)
+            .await
+            .unwrap()
+        }
+    };
+    let balance = || {
+        let db = db.clone();
+        async move {
+            sqlx::query_scalar::<_, Money>("SELECT balance FROM users WHERE phone_number = '9876543210'")
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        }
+    };
+    // How long a payment has been pending is read off the database clock, so age the rows themselves.
+    let age = |secs: f64| {
+        let db = db.clone();
+        async move {
+            sqlx::query(
+                "UPDATE transactions SET updated_at = updated_at - make_interval(secs => $1) WHERE status = 'pending'",
+            )
+            .bind(secs)
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+    };
+
+    assert_eq!(reconciliation::run_due(&db, &upi, &config, start).await.unwrap(), ReconciliationRun::default());
+    age(61.0).await;
+    assert_eq!(
+        reconciliation::run_due(&db, &upi, &config, at(61)).await.unwrap(),
+        ReconciliationRun { polled: 3, ..Default::default() }
+    );
+
+    switch.settle(settles, PspStatus::Success, None);
+    switch.settle(fails, PspStatus::Failed, Some("remitter bank timeout"));
+    assert_eq!(reconciliation::run_due(&db, &upi, &config, at(71)).await.unwrap(), ReconciliationRun::default());
+    assert_eq!(
+        reconciliation::run_due(&db, &upi, &config, at(92)).await.unwrap(),
+        ReconciliationRun { polled: 3, settled: 1, failed: 1, ..Default::default() }
+    );
+    assert_eq!(status_of(settles).await.0, TransactionStatus::Success);
+    assert_eq!(
+        status_of(fails).await,
+        (TransactionStatus::Failed, Some("remitter bank timeout".to_string()))
+    );
+    assert_eq!(status_of(times_out).await.0, TransactionStatus::Pending);
+    assert_eq!(balance().await, Money::from_rupees(800));
+    assert_eq!(
+        ledger::balance(&db, LedgerAccount::merchant_payable(merchant_id)).await.unwrap(),
+        Money::from_rupees(100)
+    );
+
+    // The third poll waits twice as long, and a failed status call is recorded and retried later.
+    assert_eq!(reconciliation::run_due(&db, &upi, &config, at(151)).await.unwrap(), ReconciliationRun::default());
+    wiremock::Mock::given(wiremock::matchers::any())
+        .respond_with(wiremock::ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(psp)
+        .await;
+    assert_eq!(
+        reconciliation::run_due(&db, &upi, &config, at(152)).await.unwrap(),
+        ReconciliationRun { polled: 1, errors: 1, ..Default::default() }
+    );
+
+    // A status the payment cannot be settled from is recorded against it instead of aborting the run.
+    sqlx::query("UPDATE transactions SET upi_txn_id = NULL WHERE id = $1")
+        .bind(times_out)
+        .execute(&db)
+        .await
+        .unwrap();
+    wiremock::Mock::given(wiremock::matchers::method("GET"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(json!({
+            "reference": times_out, "upi_txn_id": null, "status": "success", "reason": null
+        })))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(psp)
+        .await;
+    assert_eq!(
+        reconciliation::run_due(&db, &upi, &config, at(272)).await.unwrap(),
+        ReconciliationRun { polled: 1, errors: 1, ..Default::default() }
+    );
+    assert_eq!(status_of(times_out).await.0, TransactionStatus::Pending);
+
+    // Past the maximum age the PSP is asked to reverse. The hold goes back to the payer only once the
+    // reversal has completed.
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(
+            wiremock::ResponseTemplate::new(200).set_body_json(json!({ "reversal_id": "REVPENDING", "status": "pending" })),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(psp)
+        .await;
+    age(3540.0).await;
+    assert_eq!(
+        reconciliation::run_due(&db, &upi, &config, at(3601)).await.unwrap(),
+        ReconciliationRun { polled: 1, ..Default::default() }
+    );
+    assert_eq!(status_of(times_out).await.0, TransactionStatus::Pending);
+    assert_eq!(ledger::balance(&db, LedgerAccount::payment_hold(times_out)).await.unwrap(), Money::from_rupees(100));
+    assert_eq!(
+        reconciliation::run_due(&db, &upi, &config, at(3902)).await.unwrap(),
+        ReconciliationRun { polled: 1, reversed: 1, ..Default::default() }
+    );
+    assert_eq!(switch.get(times_out).unwrap().status, PspStatus::Failed);
+    let (status, error_message) = status_of(times_out).await;
+    assert_eq!(status, TransactionStatus::Failed);
+    assert_eq!(error_message.as_deref(), Some("payment was not confirmed by the payment network in time"));
+    assert_eq!(balance().await, Money::from_rupees(900));
+    assert_eq!(ledger::balance(&db, LedgerAccount::payment_hold(times_out)).await.unwrap(), Money::ZERO);
+
+    let polls = reconciliation::polls_for_transaction(&db, times_out).await.unwrap();
+    assert_eq!(
+        polls.iter().map(|p| (p.attempt, p.outcome)).collect::<Vec<_>>(),
+        vec![
+            (1, PollOutcome::Pending),
+            (2, PollOutcome::Pending),
+            (3, PollOutcome::Error),
+            (4, PollOutcome::Error),
+            (5, PollOutcome::Pending),
+            (6, PollOutcome::Reversed),
+        ]
+    );
+    assert!(polls[2].psp_status.is_none() && polls[2].error_message.is_some());
+    assert!(polls[3].error_message.as_deref().is_some_and(|m| m.contains("missing upi_txn_id")));
+    let polls = reconciliation::polls_for_transaction(&db, settles).await.unwrap();
+    assert_eq!(
+        polls.iter().map(|p| (p.outcome, p.psp_status.as_deref())).collect::<Vec<_>>(),
+        vec![(PollOutcome::Pending, Some("pending")), (PollOutcome::Success, Some("success"))]
+    );
+
+    assert_eq!(reconciliation::run_due(&db, &upi, &config, at(7500)).await.unwrap(), ReconciliationRun::default());
+    assert!(ledger::wallet_mismatches(&db).await.unwrap().is_empty());
+}
@@ -0,0 +1,61 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use qr_payment_backend::models::money::Money;
+use qr_payment_backend::utils::intent_signature::{decode_public_key, verify, SignatureError};
+use qr_payment_backend::utils::upi_intent::UpiIntent;
+
+fn signed_link(key: &SigningKey, intent: &UpiIntent) -> String {
+    let mut signed = intent.clone();
+    signed.signature = Some(STANDARD.encode(key.sign(intent.signed_payload().as_bytes()).to_bytes()));
+    signed.to_uri()
+}
+
+#[test]
+fn verifies_signed_intent_against_any_active_key() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let other = SigningKey::from_bytes(&[9; 32]);
+    let keys = vec![
+        STANDARD.encode(other.verifying_key().to_bytes()),
+        STANDARD.encode(key.verifying_key().to_bytes()),
+    ];
+
+    let mut intent = UpiIntent::for_payee("coffeeshop@upi", "Coffee Shop");
+    intent.amount = Some(Money::from_rupees(100));
+    let parsed = UpiIntent::parse(&signed_link(&key, &intent)).unwrap();
+    let signature = parsed.signature.clone().unwrap();
+
+    assert_eq!(verify(&parsed.signed_payload(), &signature, &keys), Ok(true));
+    assert_eq!(verify(&parsed.signed_payload(), &signature, &keys[..1]), Ok(false));
+}
+
+#[test]
+fn tampered_intent_fails_verification() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let keys = vec![URL_SAFE_NO_PAD.encode(key.verifying_key().to_bytes())];
+
+    let intent = UpiIntent::for_payee("coffeeshop@upi", "Coffee Shop");
+    let mut parsed = UpiIntent::parse(&signed_link(&key, &intent)).unwrap();
+    parsed.payee_address = "fraudster@upi".to_string();
+    let signature = parsed.signature.clone().unwrap();
+
+    assert_eq!(verify(&parsed.signed_payload(), &signature, &keys), Ok(false));
+    assert_eq!(
+        verify(&parsed.signed_payload(), "not-a-signature", &keys),
+        Err(SignatureError::MalformedSignature)
+    );
+    assert_eq!(verify(&parsed.signed_payload(), &signature, &["short".to_string()]), Ok(false));
+}
+
+#[test]
+fn malformed_keys_are_skipped() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let keys = vec!["short".to_string(), STANDARD.encode(key.verifying_key().to_bytes())];
+
+    let intent = UpiIntent::for_payee("coffeeshop@upi", "Coffee Shop");
+    let parsed = UpiIntent::parse(&signed_link(&key, &intent)).unwrap();
+    let signature = parsed.signature.clone().unwrap();
+
+    assert_eq!(verify(&parsed.signed_payload(), &signature, &keys), Ok(true));
+    assert_eq!(decode_public_key("short"), Err(SignatureError::MalformedKey));
+}
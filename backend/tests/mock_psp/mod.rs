@@ -0,0 +1,133 @@
+// In-process stand-in for the PSP switch. It checks request signatures, remembers payments by
+// reference so repeated calls are idempotent, declines any payer whose VPA starts with "decline"
+// and leaves payments from a VPA starting with "pending" for a callback to settle.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use qr_payment_backend::utils::upi_client::{
+    sign, PayRequest, PspPayment, PspReversal, PspStatus, KEY_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::matchers::any;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+pub struct MockPsp {
+    secret: String,
+    payments: PspPayments,
+}
+
+// The switch's view of the payments it has seen, shared with the test so it can settle pending ones.
+#[derive(Clone, Default)]
+pub struct PspPayments(Arc<Mutex<HashMap<Uuid, PspPayment>>>);
+
+impl PspPayments {
+    pub fn get(&self, reference: Uuid) -> Option<PspPayment> {
+        self.0.lock().unwrap().get(&reference).cloned()
+    }
+
+    pub fn settle(&self, reference: Uuid, status: PspStatus, reason: Option<&str>) {
+        let mut payments = self.0.lock().unwrap();
+        let payment = payments.get_mut(&reference).expect("unknown payment");
+        payment.status = status;
+        payment.reason = reason.map(str::to_string);
+    }
+}
+
+pub async fn mount(server: &MockServer, secret: &str) -> PspPayments {
+    let payments = PspPayments::default();
+    Mock::given(any())
+        .respond_with(MockPsp {
+            secret: secret.to_string(),
+            payments: payments.clone(),
+        })
+        .mount(server)
+        .await;
+    payments
+}
+
+impl Respond for MockPsp {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        if !self.signed(request) {
+            return error(401, "invalid_signature", "request signature does not match");
+        }
+
+        let segments: Vec<&str> = request.url.path().trim_start_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["v1", "payments"]) => match serde_json::from_slice::<PayRequest>(&request.body) {
+                Ok(pay) => ResponseTemplate::new(200).set_body_json(self.pay(pay)),
+                Err(_) => error(400, "invalid_request", "malformed payment request"),
+            },
+            ("GET", ["v1", "payments", reference]) => match self.find(reference) {
+                Some(payment) => ResponseTemplate::new(200).set_body_json(payment),
+                None => error(404, "not_found", "unknown payment reference"),
+            },
+            ("POST", ["v1", "payments", reference, "reversals"]) => match self.find(reference) {
+                Some(payment) if payment.status != PspStatus::Failed => {
+                    if payment.status == PspStatus::Pending {
+                        self.payments.settle(payment.reference, PspStatus::Failed, Some("reversed"));
+                    }
+                    ResponseTemplate::new(200).set_body_json(PspReversal {
+                        reversal_id: format!("REV{}", payment.reference.simple()).to_uppercase(),
+                        status: PspStatus::Success,
+                    })
+                }
+                Some(_) => error(422, "not_reversible", "failed payments cannot be reversed"),
+                None => error(404, "not_found", "unknown payment reference"),
+            },
+            _ => error(404, "not_found", "no such endpoint"),
+        }
+    }
+}
+
+impl MockPsp {
+    fn signed(&self, request: &Request) -> bool {
+        let header = |name: &str| request.headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let (Some(_), Some(timestamp), Some(signature)) =
+            (header(KEY_ID_HEADER), header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER))
+        else {
+            return false;
+        };
+        let Ok(timestamp) = timestamp.parse::<i64>() else {
+            return false;
+        };
+        let body = String::from_utf8_lossy(&request.body);
+        sign(&self.secret, timestamp, request.method.as_str(), request.url.path(), &body) == signature
+    }
+
+    fn pay(&self, pay: PayRequest) -> PspPayment {
+        let mut payments = self.payments.0.lock().unwrap();
+        payments
+            .entry(pay.reference)
+            .or_insert_with(|| {
+                if pay.payer_vpa.starts_with("decline") {
+                    PspPayment {
+                        reference: pay.reference,
+                        upi_txn_id: None,
+                        status: PspStatus::Failed,
+                        reason: Some("declined by remitter bank".to_string()),
+                    }
+                } else {
+                    PspPayment {
+                        reference: pay.reference,
+                        upi_txn_id: Some(format!("PSP{}", pay.reference.simple()).to_uppercase()),
+                        status: if pay.payer_vpa.starts_with("pending") {
+                            PspStatus::Pending
+                        } else {
+                            PspStatus::Success
+                        },
+                        reason: None,
+                    }
+                }
+            })
+            .clone()
+    }
+
+    fn find(&self, reference: &str) -> Option<PspPayment> {
+        self.payments.get(Uuid::parse_str(reference).ok()?)
+    }
+}
+
+fn error(status: u16, code: &str, message: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(json!({ "code": code, "message": message }))
+}
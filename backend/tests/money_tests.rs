@@ -0,0 +1,36 @@
+use qr_payment_backend::models::money::{Money, MoneyError};
+
+#[test]
+fn parses_and_formats_rupee_amounts() {
+    assert_eq!("100".parse(), Ok(Money::from_paise(10000)));
+    assert_eq!("0.5".parse(), Ok(Money::from_paise(50)));
+    assert_eq!("12.34".parse(), Ok(Money::from_paise(1234)));
+    assert_eq!("-1.05".parse(), Ok(Money::from_paise(-105)));
+    assert_eq!("1.234".parse::<Money>(), Err(MoneyError::TooPrecise));
+    assert_eq!("1.".parse::<Money>(), Err(MoneyError::Invalid));
+    assert_eq!(".5".parse::<Money>(), Err(MoneyError::Invalid));
+    assert_eq!("1e3".parse::<Money>(), Err(MoneyError::Invalid));
+    assert_eq!("99999999999999999999".parse::<Money>(), Err(MoneyError::OutOfRange));
+
+    assert_eq!(Money::from_paise(10050).to_string(), "100.50");
+    assert_eq!(Money::from_paise(-5).to_string(), "-0.05");
+}
+
+#[test]
+fn arithmetic_is_exact() {
+    let sum = Money::from_paise(10) + Money::from_paise(20);
+    assert_eq!(sum, "0.3".parse().unwrap());
+    assert_eq!(Money::from_rupees(1000) - Money::from_paise(1), Money::from_paise(99999));
+    assert_eq!(Money::from_paise(i64::MAX).checked_add(Money::from_paise(1)), None);
+}
+
+#[test]
+fn serde_uses_rupees_and_rejects_sub_paise_amounts() {
+    assert_eq!(serde_json::to_string(&Money::from_paise(10050)).unwrap(), "100.5");
+    assert_eq!(serde_json::from_str::<Money>("100.5").unwrap(), Money::from_paise(10050));
+    assert_eq!(serde_json::from_str::<Money>("0.1").unwrap(), Money::from_paise(10));
+    assert_eq!(serde_json::from_str::<Money>("42").unwrap(), Money::from_rupees(42));
+    assert_eq!(serde_json::from_str::<Money>("\"19.99\"").unwrap(), Money::from_paise(1999));
+    assert!(serde_json::from_str::<Money>("100.005").is_err());
+    assert!(serde_json::from_str::<Money>("\"abc\"").is_err());
+}
@@ -0,0 +1,63 @@
+use image::{GrayImage, Luma};
+use qr_payment_backend::utils::qr_decoder::{decode_image, decode_luma, QrDecodeError};
+use qr_payment_backend::utils::qr_render::{render, QrErrorCorrection, QrImageFormat};
+use qrcode::{EcLevel, QrCode, Version};
+
+fn rasterize(code: &QrCode, module: u32) -> GrayImage {
+    code.render::<Luma<u8>>().module_dimensions(module, module).build()
+}
+
+#[test]
+fn round_trips_every_version_and_ec_level() {
+    for version in 1..=40 {
+        for ec in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            let long = format!("upi://pay?pa=merchant{}@upi&pn=Shop&tn={:?}", version, ec);
+            let short = format!("{}{:?}", version, ec);
+            let (payload, code) = match QrCode::with_version(long.as_bytes(), Version::Normal(version), ec) {
+                Ok(code) => (long, code),
+                Err(_) => {
+                    let code = QrCode::with_version(short.as_bytes(), Version::Normal(version), ec).unwrap();
+                    (short, code)
+                }
+            };
+            let decoded = decode_luma(&rasterize(&code, 3));
+            assert_eq!(decoded, Ok(vec![payload]), "version {} {:?}", version, ec);
+        }
+    }
+}
+
+#[test]
+fn decodes_rendered_png_and_jpeg() {
+    let payload = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100.00";
+    let png = render(payload, QrImageFormat::Png, 400, QrErrorCorrection::M).unwrap();
+    assert_eq!(decode_image(&png), Ok(vec![payload.to_string()]));
+
+    let image = image::load_from_memory(&png).unwrap().to_luma8();
+    let mut jpeg = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+        .unwrap();
+    assert_eq!(decode_image(&jpeg), Ok(vec![payload.to_string()]));
+}
+
+#[test]
+fn decodes_rotated_and_multiple_codes() {
+    let first = QrCode::new(b"upi://pay?pa=first@upi").unwrap();
+    let second = QrCode::new(b"upi://pay?pa=second@upi").unwrap();
+    let first = rasterize(&first, 4);
+    let second = image::imageops::rotate90(&rasterize(&second, 5));
+
+    let mut canvas = GrayImage::from_pixel(first.width() + second.width() + 20, first.height().max(second.height()), Luma([255]));
+    image::imageops::replace(&mut canvas, &first, 0, 0);
+    image::imageops::replace(&mut canvas, &second, first.width() as i64 + 20, 0);
+
+    let mut decoded = decode_luma(&canvas).unwrap();
+    decoded.sort();
+    assert_eq!(decoded, vec!["upi://pay?pa=first@upi", "upi://pay?pa=second@upi"]);
+}
+
+#[test]
+fn rejects_images_without_a_code() {
+    assert_eq!(decode_image(b"not an image"), Err(QrDecodeError::InvalidImage));
+    assert_eq!(decode_luma(&GrayImage::from_pixel(200, 200, Luma([255]))), Err(QrDecodeError::NotFound));
+}
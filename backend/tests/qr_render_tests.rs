@@ -0,0 +1,25 @@
+use qr_payment_backend::utils::qr_render::{render, QrErrorCorrection, QrImageFormat, QrRenderError};
+
+const PAYLOAD: &str = "upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop";
+
+#[test]
+fn renders_png_and_svg() {
+    let png = render(PAYLOAD, QrImageFormat::Png, 256, QrErrorCorrection::M).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let svg = render(PAYLOAD, QrImageFormat::Svg, 256, QrErrorCorrection::H).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.contains("<svg"));
+}
+
+#[test]
+fn rejects_out_of_range_sizes() {
+    assert!(matches!(
+        render(PAYLOAD, QrImageFormat::Png, 16, QrErrorCorrection::M),
+        Err(QrRenderError::InvalidSize)
+    ));
+    assert!(matches!(
+        render(PAYLOAD, QrImageFormat::Svg, 10_000, QrErrorCorrection::M),
+        Err(QrRenderError::InvalidSize)
+    ));
+}
@@ -0,0 +1,179 @@
+use qr_payment_backend::models::money::Money;
+use qr_payment_backend::utils::circuit_breaker::{CircuitBreakerConfig, CircuitState};
+use qr_payment_backend::utils::upi_client::{sign, verify, PspConfig, PspStatus, UpiClient, UpiError};
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod mock_psp;
+
+const SECRET: &str = "test-psp-secret";
+
+fn client(server: &MockServer, secret: &str, timeout_ms: u64) -> UpiClient {
+    UpiClient::new(PspConfig {
+        base_url: server.uri(),
+        key_id: "qrpay".to_string(),
+        secret: secret.to_string(),
+        timeout_ms,
+        breaker: CircuitBreakerConfig::default(),
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn signature_covers_every_field() {
+    let base = sign(SECRET, 1_700_000_000, "POST", "/v1/payments", "{}");
+    assert_eq!(base.len(), 64);
+    assert_eq!(base, sign(SECRET, 1_700_000_000, "POST", "/v1/payments", "{}"));
+    assert_ne!(base, sign("other-secret", 1_700_000_000, "POST", "/v1/payments", "{}"));
+    assert_ne!(base, sign(SECRET, 1_700_000_001, "POST", "/v1/payments", "{}"));
+    assert_ne!(base, sign(SECRET, 1_700_000_000, "GET", "/v1/payments", "{}"));
+    assert_ne!(base, sign(SECRET, 1_700_000_000, "POST", "/v1/payments/x", "{}"));
+    assert_ne!(base, sign(SECRET, 1_700_000_000, "POST", "/v1/payments", "{ }"));
+
+    assert!(verify(SECRET, 1_700_000_000, "POST", "/v1/payments", "{}", &base));
+    assert!(!verify(SECRET, 1_700_000_000, "POST", "/v1/payments", "{\"x\":1}", &base));
+    assert!(!verify("other-secret", 1_700_000_000, "POST", "/v1/payments", "{}", &base));
+    assert!(!verify(SECRET, 1_700_000_000, "POST", "/v1/payments", "{}", "not-hex"));
+}
+
+#[tokio::test]
+async fn pay_status_and_reverse_against_the_switch() {
+    let server = MockServer::start().await;
+    mock_psp::mount(&server, SECRET).await;
+    let upi = client(&server, SECRET, 2000);
+    let reference = Uuid::new_v4();
+
+    let paid = upi
+        .pay(reference, "alice@paytm", "coffeeshop@upi", Money::from_rupees(100), Some("coffee"))
+        .await
+        .unwrap();
+    assert_eq!(paid.status, PspStatus::Success);
+    assert!(paid.upi_txn_id.is_some());
+
+    // The reference is the idempotency key, so a retried call returns the original outcome.
+    let retried = upi.pay(reference, "alice@paytm", "coffeeshop@upi", Money::from_rupees(100), None).await.unwrap();
+    assert_eq!(retried, paid);
+    assert_eq!(upi.status(reference).await.unwrap(), paid);
+
+    let reversal = upi.reverse(reference, Money::from_rupees(100), "customer refund").await.unwrap();
+    assert_eq!(reversal.status, PspStatus::Success);
+
+    let declined = upi
+        .pay(Uuid::new_v4(), "decline.bob@paytm", "coffeeshop@upi", Money::from_rupees(100), None)
+        .await
+        .unwrap();
+    assert_eq!(declined.status, PspStatus::Failed);
+    assert_eq!(declined.reason.as_deref(), Some("declined by remitter bank"));
+
+    match upi.status(Uuid::new_v4()).await {
+        Err(UpiError::Rejected { code, .. }) => assert_eq!(code, "not_found"),
+        other => panic!("expected rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn unsigned_requests_are_rejected() {
+    let server = MockServer::start().await;
+    mock_psp::mount(&server, SECRET).await;
+    let upi = client(&server, "wrong-secret", 2000);
+
+    let result = upi.pay(Uuid::new_v4(), "alice@paytm", "coffeeshop@upi", Money::from_rupees(1), None).await;
+    match result {
+        Err(UpiError::Rejected { code, .. }) => assert_eq!(code, "invalid_signature"),
+        other => panic!("expected rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn switch_failures_are_classified() {
+    let server = MockServer::start().await;
+    let upi = client(&server, SECRET, 200);
+    let reference = Uuid::new_v4();
+
+    Mock::given(method("GET"))
+        .and(path(format!("/v1/payments/{}", reference)))
+        .and(header_exists("X-PSP-Signature"))
+        .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_millis(1000)))
+        .mount(&server)
+        .await;
+    assert_eq!(upi.status(reference).await, Err(UpiError::Timeout));
+
+    server.reset().await;
+    Mock::given(method("GET")).respond_with(ResponseTemplate::new(504)).mount(&server).await;
+    assert_eq!(upi.status(reference).await, Err(UpiError::Timeout));
+
+    server.reset().await;
+    Mock::given(method("GET")).respond_with(ResponseTemplate::new(503)).mount(&server).await;
+    assert!(matches!(upi.status(reference).await, Err(UpiError::Unavailable(_))));
+
+    server.reset().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(422).set_body_json(json!({ "code": "limit_exceeded", "message": "daily limit" })))
+        .mount(&server)
+        .await;
+    assert_eq!(
+        upi.status(reference).await,
+        Err(UpiError::Rejected {
+            code: "limit_exceeded".to_string(),
+            message: "daily limit".to_string()
+        })
+    );
+
+    server.reset().await;
+    Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_string("not json")).mount(&server).await;
+    assert!(matches!(upi.status(reference).await, Err(UpiError::InvalidResponse(_))));
+
+    let unreachable = UpiClient::new(PspConfig {
+        base_url: "http://127.0.0.1:1".to_string(),
+        key_id: "qrpay".to_string(),
+        secret: SECRET.to_string(),
+        timeout_ms: 200,
+        breaker: CircuitBreakerConfig::default(),
+    })
+    .unwrap();
+    assert!(matches!(unreachable.status(reference).await, Err(UpiError::Unavailable(_))));
+}
+
+#[tokio::test]
+async fn open_circuit_fails_fast_per_endpoint() {
+    let server = MockServer::start().await;
+    let upi = UpiClient::new(PspConfig {
+        base_url: server.uri(),
+        key_id: "qrpay".to_string(),
+        secret: SECRET.to_string(),
+        timeout_ms: 2000,
+        breaker: CircuitBreakerConfig {
+            consecutive_failures: 2,
+            ..CircuitBreakerConfig::default()
+        },
+    })
+    .unwrap();
+    Mock::given(method("POST")).respond_with(ResponseTemplate::new(503)).mount(&server).await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "code": "not_found", "message": "unknown" })))
+        .mount(&server)
+        .await;
+
+    let pay = || upi.pay(Uuid::new_v4(), "alice@paytm", "coffeeshop@upi", Money::from_rupees(1), None);
+    assert!(matches!(pay().await, Err(UpiError::Unavailable(_))));
+    assert!(matches!(pay().await, Err(UpiError::Unavailable(_))));
+    assert_eq!(pay().await, Err(UpiError::CircuitOpen));
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+    // Rejections from the status endpoint neither trip its own circuit nor share the payments one.
+    for _ in 0..3 {
+        assert!(matches!(upi.status(Uuid::new_v4()).await, Err(UpiError::Rejected { .. })));
+    }
+    let states: Vec<(String, CircuitState)> =
+        upi.circuit_breakers().into_iter().map(|breaker| (breaker.name, breaker.state)).collect();
+    assert_eq!(
+        states,
+        vec![
+            ("psp.payments".to_string(), CircuitState::Open),
+            ("psp.status".to_string(), CircuitState::Closed),
+            ("psp.reversals".to_string(), CircuitState::Closed),
+        ]
+    );
+}
@@ -0,0 +1,61 @@
+use qr_payment_backend::models::money::Money;
+use qr_payment_backend::utils::upi_intent::{UpiIntent, UpiIntentError};
+
+#[test]
+fn parses_all_known_params() {
+    let intent = UpiIntent::parse(
+        "upi://pay?pa=CoffeeShop@UPI&pn=Coffee%20Shop&am=100.50&cu=inr&tn=Latte&tr=ORD-1&mc=5814&mode=02&orgid=000000&url=https%3A%2F%2Fshop.example",
+    )
+    .unwrap();
+
+    assert_eq!(intent.payee_address, "coffeeshop@upi");
+    assert_eq!(intent.payee_name.as_deref(), Some("Coffee Shop"));
+    assert_eq!(intent.amount, Some(Money::from_paise(10050)));
+    assert_eq!(intent.min_amount, None);
+    assert_eq!(intent.currency.as_deref(), Some("INR"));
+    assert_eq!(intent.transaction_note.as_deref(), Some("Latte"));
+    assert_eq!(intent.transaction_ref.as_deref(), Some("ORD-1"));
+    assert_eq!(intent.merchant_code.as_deref(), Some("5814"));
+    assert_eq!(intent.mode.as_deref(), Some("02"));
+    assert_eq!(intent.org_id.as_deref(), Some("000000"));
+    assert_eq!(intent.url.as_deref(), Some("https://shop.example"));
+}
+
+#[test]
+fn param_order_and_encoding_do_not_matter() {
+    let a = UpiIntent::parse("upi://pay?pa=coffeeshop@upi&pn=Coffee%20Shop&am=100").unwrap();
+    let b = UpiIntent::parse("UPI://pay?am=100&pn=Coffee+Shop&pa=coffeeshop%40upi").unwrap();
+    assert_eq!(a, b);
+}
+
+#[test]
+fn rejects_malformed_links() {
+    assert_eq!(UpiIntent::parse("https://example.com"), Err(UpiIntentError::NotUpiLink));
+    assert_eq!(UpiIntent::parse("upi://collect?pa=a@upi"), Err(UpiIntentError::Malformed));
+    assert_eq!(UpiIntent::parse("upi://pay?pn=Shop"), Err(UpiIntentError::MissingPayee));
+    assert_eq!(UpiIntent::parse("upi://pay?pa=not-a-vpa"), Err(UpiIntentError::InvalidPayee));
+    assert_eq!(
+        UpiIntent::parse("upi://pay?pa=a@upi&pa=b@upi"),
+        Err(UpiIntentError::DuplicateParam("pa".to_string()))
+    );
+    assert!(UpiIntent::parse("upi://pay?pa=a@upi&am=1.234").is_err());
+    assert!(UpiIntent::parse("upi://pay?pa=a@upi&am=-5").is_err());
+    assert!(UpiIntent::parse("upi://pay?pa=a@upi&am=0").is_err());
+    assert!(UpiIntent::parse("upi://pay?pa=a@upi&mc=12").is_err());
+    assert!(UpiIntent::parse("upi://pay?pa=a@upi&am=10&mam=20").is_err());
+}
+
+#[test]
+fn to_uri_round_trips() {
+    let mut intent = UpiIntent::for_payee("CoffeeShop@upi", "Coffee & Tea Shop");
+    intent.amount = Some(Money::from_paise(9950));
+    intent.currency = Some("INR".to_string());
+    intent.transaction_note = Some("Table 4".to_string());
+
+    let uri = intent.to_uri();
+    assert_eq!(
+        uri,
+        "upi://pay?pa=coffeeshop@upi&pn=Coffee%20%26%20Tea%20Shop&am=99.50&cu=INR&tn=Table%204"
+    );
+    assert_eq!(UpiIntent::parse(&uri).unwrap(), intent);
+}
@@ -15,15 +15,23 @@ struct UserPublic: Codable {
 
 struct PaymentInitResponse: Codable {
     let sessionId: String
-    let merchant: MerchantInfo
+    let payee: PayeeInfo
     let amount: Double
+    let currency: String
+    let amountEditable: Bool
+    let minAmount: Double?
+    let promoCode: String?
+    let discount: Double
+    let payableAmount: Double
     let status: String
 }
 
-struct MerchantInfo: Codable {
+struct PayeeInfo: Codable {
+    let payeeType: String
     let name: String
     let upiId: String
     let category: String?
+    let verified: Bool
 }
 
 struct PaymentExecuteResponse: Codable {
@@ -31,5 +39,12 @@ struct PaymentExecuteResponse: Codable {
     let status: String
     let upiTxnId: String?
     let message: String
+    let remainingLimits: RemainingLimits?
+    let cashback: Double?
+}
+
+struct RemainingLimits: Codable {
+    let daily: Double
+    let monthly: Double
 }
 
@@ -34,13 +34,16 @@ final class APIService {
         return response
     }
     
-    func initiatePayment(qrData: String, amount: Double) async throws -> PaymentInitResponse {
+    func initiatePayment(qrData: String, amount: Double, promoCode: String? = nil) async throws -> PaymentInitResponse {
         let endpoint = "\(baseURL)/api/payment/initiate"
-        let body: [String: Any] = [
+        var body: [String: Any] = [
             "qr_data": qrData,
             "amount": amount,
             "idempotency_key": UUID().uuidString
         ]
+        if let promoCode = promoCode {
+            body["promo_code"] = promoCode
+        }
         
         return try await post(endpoint: endpoint, body: body, requiresAuth: true)
     }
@@ -20,7 +20,7 @@ struct PaymentSheet: View {
                         Text("Paying to")
                             .font(.headline)
                         
-                        Text(response.merchant.name)
+                        Text(response.payee.name)
                             .font(.title2)
                             .fontWeight(.semibold)
                         